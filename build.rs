@@ -17,15 +17,13 @@ use std::io::Result;
 use std::process::Command;
 
 fn main() -> Result<()> {
+    // the file metas hold column stats as doubles, they can't derive Eq
     tonic_build::configure()
-        .type_attribute("FileList", "#[derive(Eq)]")
         .type_attribute("FileList", "#[derive(serde::Serialize)]")
-        .type_attribute("FileKey", "#[derive(Eq)]")
         .type_attribute("FileKey", "#[derive(serde::Serialize)]")
-        .type_attribute("FileDescriptor", "#[derive(Eq)]")
         .type_attribute("FileDescriptor", "#[derive(serde::Serialize)]")
-        .type_attribute("FileMeta", "#[derive(Eq)]")
         .type_attribute("FileMeta", "#[derive(serde::Serialize)]")
+        .type_attribute("ColumnStats", "#[derive(serde::Serialize)]")
         .type_attribute("Job", "#[derive(Eq)]")
         .type_attribute("Job", "#[derive(serde::Serialize)]")
         .type_attribute("Partition", "#[derive(Eq)]")
@@ -128,7 +128,18 @@ pub fn unflatten_json(obj: &Value) -> Value {
                 current = current.get_mut(part).unwrap().as_object_mut().unwrap();
             }
         }
-        current.insert(last.to_string(), value.clone());
+        // nested columns come back as objects, merge them with the keys
+        // already unflattened under the same name
+        match (current.get_mut(last), value) {
+            (Some(Value::Object(old)), Value::Object(value)) => {
+                for (k, v) in value {
+                    old.insert(k.to_string(), v.clone());
+                }
+            }
+            _ => {
+                current.insert(last.to_string(), value.clone());
+            }
+        }
     }
     Value::Object(unflattened)
 }
@@ -162,4 +173,12 @@ mod tests {
             json!({"key1": "value1", "nested_key": {"key2": {"":"value2", "foo": "bar"}}}),
         );
     }
+    #[test]
+    fn test_unflatten_json3() {
+        let obj = json!({"nested_key.key1": "value1", "nested_key": {"key2": "value2"}});
+        assert_eq!(
+            unflatten_json(&obj),
+            json!({"nested_key": {"key1": "value1", "key2": "value2"}}),
+        );
+    }
 }
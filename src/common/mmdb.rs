@@ -0,0 +1,323 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minimal reader for MaxMind DB (`.mmdb`) files, as used by GeoIP2 / GeoLite2.
+//! Records are decoded into json values.
+
+use serde_json::{Map, Number, Value};
+use std::net::IpAddr;
+
+const METADATA_MARKER: &[u8] = b"\xab\xcd\xefMaxMind.com";
+const DATA_SECTION_SEPARATOR: usize = 16;
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug)]
+pub struct Reader {
+    buf: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u16,
+    tree_size: usize,
+    ipv4_start: usize,
+    pub database_type: String,
+}
+
+impl Reader {
+    pub fn from_bytes(buf: Vec<u8>) -> Result<Self, anyhow::Error> {
+        let marker_pos = match memchr::memmem::rfind(&buf, METADATA_MARKER) {
+            Some(pos) => pos,
+            None => return Err(anyhow::anyhow!("mmdb metadata section not found")),
+        };
+        let metadata_start = marker_pos + METADATA_MARKER.len();
+        let (metadata, _) = decode(&buf[metadata_start..], 0, 0)?;
+        let get_u64 = |key: &str| metadata.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+        let node_count = get_u64("node_count") as usize;
+        let record_size = get_u64("record_size") as usize;
+        let ip_version = get_u64("ip_version") as u16;
+        if ![24, 28, 32].contains(&record_size) {
+            return Err(anyhow::anyhow!(
+                "mmdb unsupported record size: {}",
+                record_size
+            ));
+        }
+        let tree_size = node_count * record_size / 4;
+        if tree_size + DATA_SECTION_SEPARATOR > marker_pos {
+            return Err(anyhow::anyhow!("mmdb search tree is truncated"));
+        }
+        let mut reader = Reader {
+            buf,
+            node_count,
+            record_size,
+            ip_version,
+            tree_size,
+            ipv4_start: 0,
+            database_type: metadata
+                .get("database_type")
+                .and_then(|v| v.as_str())
+                .unwrap_or_default()
+                .to_string(),
+        };
+        if ip_version == 6 {
+            // IPv4 addresses live under ::/96
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = reader.read_record(node, 0);
+            }
+            reader.ipv4_start = node;
+        }
+        Ok(reader)
+    }
+
+    pub fn lookup(&self, ip: IpAddr) -> Result<Option<Value>, anyhow::Error> {
+        let (bytes, mut node) = match ip {
+            IpAddr::V4(ip) => (ip.octets().to_vec(), self.ipv4_start),
+            IpAddr::V6(ip) => {
+                if self.ip_version == 4 {
+                    return Ok(None);
+                }
+                (ip.octets().to_vec(), 0)
+            }
+        };
+        for i in 0..bytes.len() * 8 {
+            if node >= self.node_count {
+                break;
+            }
+            let bit = (bytes[i >> 3] >> (7 - (i & 7))) & 1;
+            node = self.read_record(node, bit as usize);
+        }
+        if node == self.node_count {
+            return Ok(None);
+        }
+        if node < self.node_count {
+            return Err(anyhow::anyhow!("mmdb invalid search tree"));
+        }
+        let offset = match (node - self.node_count).checked_sub(DATA_SECTION_SEPARATOR) {
+            Some(offset) => offset,
+            None => return Err(anyhow::anyhow!("mmdb invalid data pointer")),
+        };
+        let data = match self.buf.get(self.tree_size + DATA_SECTION_SEPARATOR..) {
+            Some(data) if offset < data.len() => data,
+            _ => return Err(anyhow::anyhow!("mmdb invalid data pointer")),
+        };
+        let (value, _) = decode(data, offset, 0)?;
+        Ok(Some(value))
+    }
+
+    fn read_record(&self, node: usize, index: usize) -> usize {
+        let b = &self.buf[node * self.record_size / 4..];
+        match (self.record_size, index) {
+            (24, 0) => be_uint(&b[0..3]) as usize,
+            (24, _) => be_uint(&b[3..6]) as usize,
+            (28, 0) => (((b[3] as usize) & 0xf0) << 20) | be_uint(&b[0..3]) as usize,
+            (28, _) => (((b[3] as usize) & 0x0f) << 24) | be_uint(&b[4..7]) as usize,
+            (_, 0) => be_uint(&b[0..4]) as usize,
+            (_, _) => be_uint(&b[4..8]) as usize,
+        }
+    }
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn take(data: &[u8], offset: usize, len: usize) -> Result<&[u8], anyhow::Error> {
+    data.get(offset..offset + len)
+        .ok_or_else(|| anyhow::anyhow!("mmdb data section is truncated"))
+}
+
+/// Decodes the value at `offset` of the data section, returns it with the
+/// offset of the next value.
+fn decode(data: &[u8], offset: usize, depth: usize) -> Result<(Value, usize), anyhow::Error> {
+    if depth > MAX_DEPTH {
+        return Err(anyhow::anyhow!("mmdb data is nested too deeply"));
+    }
+    let ctrl = take(data, offset, 1)?[0];
+    let mut offset = offset + 1;
+    let mut data_type = ctrl >> 5;
+    if data_type == 1 {
+        // pointer, the value is decoded from the pointed offset
+        let size = ((ctrl >> 3) & 0x3) as usize;
+        let prefix = (ctrl & 0x7) as u64;
+        let bytes = take(data, offset, size + 1)?;
+        let pointer = match size {
+            0 => (prefix << 8) | be_uint(bytes),
+            1 => ((prefix << 16) | be_uint(bytes)) + 2048,
+            2 => ((prefix << 24) | be_uint(bytes)) + 526336,
+            _ => be_uint(bytes),
+        };
+        let (value, _) = decode(data, pointer as usize, depth + 1)?;
+        return Ok((value, offset + size + 1));
+    }
+    if data_type == 0 {
+        data_type = 7 + take(data, offset, 1)?[0];
+        offset += 1;
+    }
+    let mut size = (ctrl & 0x1f) as usize;
+    if size >= 29 {
+        let len = size - 28;
+        let extra = be_uint(take(data, offset, len)?) as usize;
+        offset += len;
+        size = match len {
+            1 => 29 + extra,
+            2 => 285 + extra,
+            _ => 65821 + extra,
+        };
+    }
+    match data_type {
+        2 => {
+            let s = String::from_utf8_lossy(take(data, offset, size)?).to_string();
+            Ok((Value::String(s), offset + size))
+        }
+        3 => {
+            let bytes = take(data, offset, 8)?;
+            let v = f64::from_bits(be_uint(bytes));
+            Ok((
+                Number::from_f64(v).map_or(Value::Null, Value::Number),
+                offset + 8,
+            ))
+        }
+        4 => Ok((Value::Null, offset + size)),
+        5 | 6 | 9 => {
+            let v = be_uint(take(data, offset, size)?);
+            Ok((Value::from(v), offset + size))
+        }
+        10 => {
+            // uint128, only keep it when it fits
+            let bytes = take(data, offset, size)?;
+            let v = bytes.iter().fold(0u128, |acc, b| (acc << 8) | *b as u128);
+            Ok((
+                u64::try_from(v).map_or(Value::Null, Value::from),
+                offset + size,
+            ))
+        }
+        7 => {
+            let mut map = Map::with_capacity(size);
+            for _ in 0..size {
+                let (key, next) = decode(data, offset, depth + 1)?;
+                let (value, next) = decode(data, next, depth + 1)?;
+                offset = next;
+                if let Value::String(key) = key {
+                    map.insert(key, value);
+                }
+            }
+            Ok((Value::Object(map), offset))
+        }
+        8 => {
+            let v = be_uint(take(data, offset, size)?) as u32 as i32;
+            Ok((Value::from(v), offset + size))
+        }
+        11 => {
+            let mut arr = Vec::with_capacity(size);
+            for _ in 0..size {
+                let (value, next) = decode(data, offset, depth + 1)?;
+                offset = next;
+                arr.push(value);
+            }
+            Ok((Value::Array(arr), offset))
+        }
+        14 => Ok((Value::Bool(size != 0), offset)),
+        15 => {
+            let bytes = take(data, offset, 4)?;
+            let v = f32::from_bits(be_uint(bytes) as u32) as f64;
+            Ok((
+                Number::from_f64(v).map_or(Value::Null, Value::Number),
+                offset + 4,
+            ))
+        }
+        _ => Err(anyhow::anyhow!("mmdb unsupported data type: {}", data_type)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encode_str(s: &str) -> Vec<u8> {
+        let mut buf = vec![(2 << 5) | s.len() as u8];
+        buf.extend_from_slice(s.as_bytes());
+        buf
+    }
+
+    fn encode_map(pairs: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let mut buf = vec![(7 << 5) | pairs.len() as u8];
+        for (key, value) in pairs {
+            buf.extend(encode_str(key));
+            buf.extend(value);
+        }
+        buf
+    }
+
+    fn encode_u16(v: u16) -> Vec<u8> {
+        vec![(5 << 5) | 2, (v >> 8) as u8, v as u8]
+    }
+
+    #[test]
+    fn test_mmdb_lookup() {
+        // one node: addresses starting with bit 0 point to the first record
+        // of the data section, the others are not found
+        let node_count = 1u32;
+        let data_pointer = node_count + DATA_SECTION_SEPARATOR as u32;
+        let mut buf = vec![
+            (data_pointer >> 16) as u8,
+            (data_pointer >> 8) as u8,
+            data_pointer as u8,
+            0,
+            0,
+            node_count as u8,
+        ];
+        buf.extend(vec![0; DATA_SECTION_SEPARATOR]);
+        buf.extend(encode_map(vec![(
+            "country",
+            encode_map(vec![("iso_code", encode_str("US"))]),
+        )]));
+        buf.extend_from_slice(METADATA_MARKER);
+        buf.extend(encode_map(vec![
+            ("node_count", encode_u16(node_count as u16)),
+            ("record_size", encode_u16(24)),
+            ("ip_version", encode_u16(4)),
+            ("database_type", encode_str("Test")),
+        ]));
+
+        let reader = Reader::from_bytes(buf).unwrap();
+        assert_eq!(reader.database_type, "Test");
+        let value = reader.lookup("10.0.0.1".parse().unwrap()).unwrap().unwrap();
+        assert_eq!(value["country"]["iso_code"].as_str(), Some("US"));
+        assert!(reader
+            .lookup("192.168.0.1".parse().unwrap())
+            .unwrap()
+            .is_none());
+        assert!(reader.lookup("::1".parse().unwrap()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_mmdb_invalid_pointer() {
+        // the record points into the data section separator
+        let node_count = 1u32;
+        let mut buf = vec![0, 0, node_count as u8 + 1, 0, 0, node_count as u8];
+        buf.extend(vec![0; DATA_SECTION_SEPARATOR]);
+        buf.extend_from_slice(METADATA_MARKER);
+        buf.extend(encode_map(vec![
+            ("node_count", encode_u16(node_count as u16)),
+            ("record_size", encode_u16(24)),
+            ("ip_version", encode_u16(4)),
+            ("database_type", encode_str("Test")),
+        ]));
+
+        let reader = Reader::from_bytes(buf).unwrap();
+        assert!(reader.lookup("10.0.0.1".parse().unwrap()).is_err());
+    }
+}
@@ -16,8 +16,11 @@ pub mod auth;
 pub mod file;
 pub mod http;
 pub mod json;
+pub mod mmdb;
 pub mod notification;
+pub mod sql;
 pub mod str;
 pub mod stream;
 pub mod time;
+pub mod useragent;
 pub mod utils;
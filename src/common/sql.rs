@@ -0,0 +1,88 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use sqlparser::ast::{BinaryOperator, Expr as SqlExpr};
+
+/// The expressions the where clause is a conjunction of, every result has to
+/// match each of them, the ones under OR or NOT are left as they are
+pub fn conjuncts(expr: &SqlExpr) -> Vec<&SqlExpr> {
+    let mut exprs = Vec::new();
+    collect_conjuncts(expr, &mut exprs);
+    exprs
+}
+
+fn collect_conjuncts<'a>(expr: &'a SqlExpr, exprs: &mut Vec<&'a SqlExpr>) {
+    match expr {
+        SqlExpr::Nested(e) => collect_conjuncts(e, exprs),
+        SqlExpr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            collect_conjuncts(left, exprs);
+            collect_conjuncts(right, exprs);
+        }
+        _ => exprs.push(expr),
+    }
+}
+
+/// The name of the field an identifier refers to, without the table
+pub fn field_name(expr: &SqlExpr) -> Option<String> {
+    match expr {
+        SqlExpr::Identifier(ident) => Some(ident.value.to_string()),
+        SqlExpr::CompoundIdentifier(idents) => idents.last().map(|ident| ident.value.to_string()),
+        _ => None,
+    }
+}
+
+/// The where clause of a query, for the tests of the file pruning
+#[cfg(test)]
+pub fn parse_selection(sql: &str) -> SqlExpr {
+    use sqlparser::ast::{SetExpr, Statement};
+    use sqlparser::dialect::GenericDialect;
+    use sqlparser::parser::Parser;
+
+    let statement = Parser::parse_sql(&GenericDialect {}, sql).unwrap();
+    match &statement[0] {
+        Statement::Query(query) => match query.body.as_ref() {
+            SetExpr::Select(select) => select.selection.clone().unwrap(),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conjuncts() {
+        let selection = parse_selection(
+            "SELECT * FROM tbl WHERE a = 1 AND (b = 2 AND t.c > 3) AND (d = 4 OR e = 5)",
+        );
+        let exprs = conjuncts(&selection);
+        assert_eq!(
+            exprs
+                .iter()
+                .map(|expr| expr.to_string())
+                .collect::<Vec<_>>(),
+            vec!["a = 1", "b = 2", "t.c > 3", "d = 4 OR e = 5"]
+        );
+        match exprs[2] {
+            SqlExpr::BinaryOp { left, .. } => assert_eq!(field_name(left), Some("c".to_string())),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -24,6 +24,16 @@ pub fn find(haystack: &str, needle: &str) -> bool {
     haystack.contains(needle)
 }
 
+/// FNV-1a hash, stable across nodes and releases
+pub fn hash64(s: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in s.as_bytes() {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
 #[cfg(test)]
 mod test_utils {
     use super::*;
@@ -34,4 +44,9 @@ mod test_utils {
         let result = find(haystack, needle);
         assert_eq!(result, true)
     }
+    #[test]
+    fn test_hash64() {
+        assert_eq!(hash64(""), 0xcbf29ce484222325);
+        assert_eq!(hash64("a"), 0xaf63dc4c8601ec8c);
+    }
 }
@@ -13,6 +13,14 @@
 // limitations under the License.
 
 use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use datafusion::arrow::array::{ArrayRef, Int64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+use datafusion::arrow::error::ArrowError;
+use datafusion::arrow::record_batch::RecordBatch;
+use std::sync::Arc;
+
+use crate::infra::config::CONFIG;
 
 pub fn parse_str_to_time(s: &str) -> Result<DateTime<FixedOffset>, anyhow::Error> {
     if s.contains('T') {
@@ -86,3 +94,268 @@ pub fn parse_timestamp_micro_from_value(v: &serde_json::Value) -> Result<i64, an
     };
     parse_i64_to_timestamp_micros(n)
 }
+
+/// Like `parse_timestamp_micro_from_value`, keeping the nanoseconds of the
+/// values which have them
+pub fn parse_timestamp_nano_from_value(v: &serde_json::Value) -> Result<i64, anyhow::Error> {
+    match v {
+        serde_json::Value::String(s) => {
+            if let Ok(t) = parse_str_to_time(s) {
+                return Ok(t.timestamp_nanos());
+            }
+            if let Ok(n) = s.parse::<i64>() {
+                return parse_i64_keep_nanos(n);
+            }
+        }
+        serde_json::Value::Number(n) => {
+            if let Some(n) = n.as_i64() {
+                return parse_i64_keep_nanos(n);
+            }
+        }
+        _ => {}
+    }
+    Ok(parse_timestamp_micro_from_value(v)? * 1000)
+}
+
+fn parse_i64_keep_nanos(v: i64) -> Result<i64, anyhow::Error> {
+    if v > (1e18 as i64) {
+        return Ok(v);
+    }
+    Ok(parse_i64_to_timestamp_micros(v)? * 1000)
+}
+
+/// The time column of the WAL holds microseconds, or nanoseconds for the
+/// streams keeping them
+#[inline]
+pub fn time_value_to_nanos(v: i64, keep_nanos: bool) -> i64 {
+    if keep_nanos {
+        v
+    } else {
+        v * 1000
+    }
+}
+
+/// Value of the time column written to the WAL, `v` is the time field of the
+/// record and `micros` the time parsed from it
+pub fn wal_time_value(v: Option<&serde_json::Value>, micros: i64, keep_nanos: bool) -> i64 {
+    if !keep_nanos {
+        return micros;
+    }
+    match v.and_then(|v| parse_timestamp_nano_from_value(v).ok()) {
+        Some(nanos) if nanos / 1000 == micros => nanos,
+        _ => micros * 1000,
+    }
+}
+
+/// SQL expression of the time column in microseconds, the column holds Int64
+/// microseconds or a native timestamp
+pub fn time_column_micros_expr(schema: &Schema) -> String {
+    let col = &CONFIG.common.time_stamp_col;
+    let data_type = match schema.field_with_name(col) {
+        Ok(field) => field.data_type(),
+        Err(_) => return col.to_string(),
+    };
+    match data_type {
+        DataType::Timestamp(TimeUnit::Second, _) => format!("CAST({} AS BIGINT) * 1000000", col),
+        DataType::Timestamp(TimeUnit::Millisecond, _) => format!("CAST({} AS BIGINT) * 1000", col),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => format!("CAST({} AS BIGINT)", col),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => format!("CAST({} AS BIGINT) / 1000", col),
+        _ => col.to_string(),
+    }
+}
+
+/// SQL literal to compare the time column with, `micros` is rounded up to the
+/// unit of a native timestamp column so the column keeps its statistics use
+pub fn time_column_literal(schema: &Schema, micros: i64) -> String {
+    let data_type = match schema.field_with_name(&CONFIG.common.time_stamp_col) {
+        Ok(field) => field.data_type(),
+        Err(_) => return micros.to_string(),
+    };
+    let ceil_div = |v: i64, d: i64| v.div_euclid(d) + i64::from(v.rem_euclid(d) > 0);
+    match data_type {
+        DataType::Timestamp(TimeUnit::Second, _) => {
+            format!("to_timestamp_seconds({})", ceil_div(micros, 1_000_000))
+        }
+        DataType::Timestamp(TimeUnit::Millisecond, _) => {
+            format!("to_timestamp_millis({})", ceil_div(micros, 1000))
+        }
+        DataType::Timestamp(TimeUnit::Microsecond, _) => format!("to_timestamp_micros({})", micros),
+        DataType::Timestamp(TimeUnit::Nanosecond, _) => {
+            format!("to_timestamp({})", micros.saturating_mul(1000))
+        }
+        _ => micros.to_string(),
+    }
+}
+
+/// SQL condition of the time column within the time range in microseconds,
+/// the end is exclusive
+pub fn time_range_condition(schema: &Schema, start: i64, end: i64) -> String {
+    let col = &CONFIG.common.time_stamp_col;
+    format!(
+        "{} >= {} AND {} < {}",
+        col,
+        time_column_literal(schema, start),
+        col,
+        time_column_literal(schema, end)
+    )
+}
+
+/// Converts the Int64 time column of a batch read from the WAL to the native
+/// timestamp type it has in `schema`, `keep_nanos` tells the WAL holds
+/// nanoseconds
+pub fn convert_time_column(
+    batch: RecordBatch,
+    schema: Arc<Schema>,
+    keep_nanos: bool,
+) -> Result<RecordBatch, ArrowError> {
+    let index = match schema.index_of(&CONFIG.common.time_stamp_col) {
+        Ok(v) => v,
+        Err(_) => return Ok(batch),
+    };
+    let data_type = schema.field(index).data_type();
+    let unit = match data_type {
+        DataType::Timestamp(unit, _) => unit,
+        _ => return Ok(batch),
+    };
+    let values = match batch.column(index).as_any().downcast_ref::<Int64Array>() {
+        Some(v) => v,
+        None => return Ok(batch),
+    };
+    let values: Int64Array = values
+        .iter()
+        .map(|v| {
+            v.map(|v| {
+                let nanos = time_value_to_nanos(v, keep_nanos);
+                match unit {
+                    TimeUnit::Second => nanos / 1_000_000_000,
+                    TimeUnit::Millisecond => nanos / 1_000_000,
+                    TimeUnit::Microsecond => nanos / 1_000,
+                    TimeUnit::Nanosecond => nanos,
+                }
+            })
+        })
+        .collect();
+    let mut columns = batch.columns().to_vec();
+    columns[index] = cast(&(Arc::new(values) as ArrayRef), data_type)?;
+    RecordBatch::try_new(schema, columns)
+}
+
+/// Converts the native timestamp time column of a batch back to Int64 in
+/// `unit`, the search hits keep returning microseconds
+pub fn time_column_to_int64(batch: RecordBatch, unit: TimeUnit) -> Result<RecordBatch, ArrowError> {
+    let schema = batch.schema();
+    let index = match schema.index_of(&CONFIG.common.time_stamp_col) {
+        Ok(v) => v,
+        Err(_) => return Ok(batch),
+    };
+    if !matches!(schema.field(index).data_type(), DataType::Timestamp(_, _)) {
+        return Ok(batch);
+    }
+    let values = cast(batch.column(index), &DataType::Timestamp(unit, None))?;
+    let mut columns = batch.columns().to_vec();
+    columns[index] = cast(&values, &DataType::Int64)?;
+    let mut fields = schema.fields().to_vec();
+    fields[index] = Field::new(
+        fields[index].name(),
+        DataType::Int64,
+        fields[index].is_nullable(),
+    );
+    RecordBatch::try_new(
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone())),
+        columns,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_timestamp_nano_from_value() {
+        let v = serde_json::json!(1667978841110123456i64);
+        assert_eq!(
+            parse_timestamp_nano_from_value(&v).unwrap(),
+            1667978841110123456
+        );
+        let v = serde_json::json!(1667978841110123i64);
+        assert_eq!(
+            parse_timestamp_nano_from_value(&v).unwrap(),
+            1667978841110123000
+        );
+        let v = serde_json::json!("2022-11-09T07:27:21.110123456Z");
+        assert_eq!(
+            parse_timestamp_nano_from_value(&v).unwrap(),
+            1667978841110123456
+        );
+        assert_eq!(
+            wal_time_value(Some(&v), 1667978841110123, false),
+            1667978841110123
+        );
+        assert_eq!(
+            wal_time_value(Some(&v), 1667978841110123, true),
+            1667978841110123456
+        );
+    }
+
+    #[test]
+    fn test_convert_time_column() {
+        let col = CONFIG.common.time_stamp_col.clone();
+        let raw_schema = Arc::new(Schema::new(vec![Field::new(&col, DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            raw_schema,
+            vec![Arc::new(Int64Array::from(vec![
+                1667978841110123,
+                1667978841110456,
+            ]))],
+        )
+        .unwrap();
+        let schema = Arc::new(Schema::new(vec![Field::new(
+            &col,
+            DataType::Timestamp(TimeUnit::Millisecond, None),
+            false,
+        )]));
+        let batch = convert_time_column(batch, schema.clone(), false).unwrap();
+        assert_eq!(batch.schema(), schema);
+        let values = cast(batch.column(0), &DataType::Int64).unwrap();
+        let values = values.as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(values.values(), &[1667978841110, 1667978841110]);
+        assert_eq!(
+            time_column_micros_expr(&schema),
+            format!("CAST({} AS BIGINT) * 1000", col)
+        );
+        let batch = time_column_to_int64(batch, TimeUnit::Microsecond).unwrap();
+        assert_eq!(batch.schema().field(0).data_type(), &DataType::Int64);
+        let values = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(values.values(), &[1667978841110000, 1667978841110000]);
+    }
+
+    #[test]
+    fn test_time_column_literal() {
+        let col = CONFIG.common.time_stamp_col.clone();
+        let schema = Schema::new(vec![Field::new(&col, DataType::Int64, false)]);
+        assert_eq!(
+            time_column_literal(&schema, 1667978841110123),
+            "1667978841110123"
+        );
+        let schema = Schema::new(vec![Field::new(
+            &col,
+            DataType::Timestamp(TimeUnit::Millisecond, None),
+            false,
+        )]);
+        assert_eq!(
+            time_column_literal(&schema, 1667978841110123),
+            "to_timestamp_millis(1667978841111)"
+        );
+        assert_eq!(
+            time_range_condition(&schema, 1667978841110000, 1667978841111000),
+            format!(
+                "{} >= to_timestamp_millis(1667978841110) AND {} < to_timestamp_millis(1667978841111)",
+                col, col
+            )
+        );
+    }
+}
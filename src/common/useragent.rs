@@ -0,0 +1,140 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use regex::Regex;
+
+// Bundled parsers, the first match wins. The first capture group is the
+// version, `_` in versions is replaced by `.`.
+const BROWSER_REGEXES: &[(&str, &str)] = &[
+    ("Googlebot", r"Googlebot(?:-\w+)?/(\d+[\.\d]*)"),
+    ("Bingbot", r"bingbot/(\d+[\.\d]*)"),
+    ("YandexBot", r"YandexBot/(\d+[\.\d]*)"),
+    ("Baiduspider", r"Baiduspider(?:-\w+)?/(\d+[\.\d]*)"),
+    ("curl", r"^curl/(\d+[\.\d]*)"),
+    ("Wget", r"^Wget/(\d+[\.\d]*)"),
+    ("Python Requests", r"python-requests/(\d+[\.\d]*)"),
+    ("Go HTTP Client", r"Go-http-client/(\d+[\.\d]*)"),
+    ("Edge", r"(?:Edge|Edg|EdgA|EdgiOS)/(\d+[\.\d]*)"),
+    ("Opera", r"(?:OPR|OPiOS|Opera)/(\d+[\.\d]*)"),
+    ("Samsung Internet", r"SamsungBrowser/(\d+[\.\d]*)"),
+    ("UC Browser", r"UCBrowser/(\d+[\.\d]*)"),
+    ("Yandex Browser", r"YaBrowser/(\d+[\.\d]*)"),
+    ("Vivaldi", r"Vivaldi/(\d+[\.\d]*)"),
+    ("Firefox", r"(?:Firefox|FxiOS)/(\d+[\.\d]*)"),
+    ("Chrome", r"(?:Chrome|CriOS)/(\d+[\.\d]*)"),
+    ("Safari", r"Version/(\d+[\.\d]*)(?: Mobile/\w+)? Safari/"),
+    ("IE", r"(?:MSIE |Trident/.*rv:)(\d+[\.\d]*)"),
+];
+
+const OS_REGEXES: &[(&str, &str)] = &[
+    ("iOS", r"(?:iPhone|iPad|iPod).*? OS (\d+[_\d]*)"),
+    ("Android", r"Android[ /]?(\d+[\.\d]*)?"),
+    ("Chrome OS", r"CrOS \w+ (\d+[\.\d]*)"),
+    ("Windows", r"Windows NT (\d+\.\d+)"),
+    ("Mac OS X", r"Mac OS X (\d+[_\.\d]*)?"),
+    ("Linux", r"Linux()"),
+];
+
+const BOT_REGEX: &str = r"(?i)bot|crawler|spider|^curl/|^wget/|python-requests|go-http-client";
+const TABLET_REGEX: &str = r"iPad|Tablet|Kindle|Silk/";
+const MOBILE_REGEX: &str = r"Mobile|iPhone|iPod|Windows Phone|Opera Mini";
+
+lazy_static! {
+    static ref BROWSERS: Vec<(&'static str, Regex)> = compile(BROWSER_REGEXES);
+    static ref OPERATING_SYSTEMS: Vec<(&'static str, Regex)> = compile(OS_REGEXES);
+    static ref BOT: Regex = Regex::new(BOT_REGEX).unwrap();
+    static ref TABLET: Regex = Regex::new(TABLET_REGEX).unwrap();
+    static ref MOBILE: Regex = Regex::new(MOBILE_REGEX).unwrap();
+}
+
+fn compile(regexes: &[(&'static str, &str)]) -> Vec<(&'static str, Regex)> {
+    regexes
+        .iter()
+        .map(|(name, re)| (*name, Regex::new(re).unwrap()))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UserAgent {
+    pub browser: String,
+    pub browser_version: String,
+    pub os: String,
+    pub os_version: String,
+    /// one of `bot`, `tablet`, `mobile`, `desktop` or `other`
+    pub device: String,
+}
+
+fn find(regexes: &[(&'static str, Regex)], ua: &str) -> Option<(String, String)> {
+    for (name, re) in regexes {
+        if let Some(caps) = re.captures(ua) {
+            let version = caps
+                .get(1)
+                .map(|m| m.as_str().replace('_', "."))
+                .unwrap_or_default();
+            return Some((name.to_string(), version));
+        }
+    }
+    None
+}
+
+pub fn parse(ua: &str) -> UserAgent {
+    let (browser, browser_version) =
+        find(&BROWSERS, ua).unwrap_or_else(|| ("Other".to_string(), String::new()));
+    let (os, os_version) =
+        find(&OPERATING_SYSTEMS, ua).unwrap_or_else(|| ("Other".to_string(), String::new()));
+    let device = if BOT.is_match(ua) {
+        "bot"
+    } else if TABLET.is_match(ua) || (os == "Android" && !ua.contains("Mobile")) {
+        "tablet"
+    } else if MOBILE.is_match(ua) {
+        "mobile"
+    } else if os != "Other" {
+        "desktop"
+    } else {
+        "other"
+    };
+    UserAgent {
+        browser,
+        browser_version,
+        os,
+        os_version,
+        device: device.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let ua = parse("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36 Edg/110.0.1587.57");
+        assert_eq!(ua.browser, "Edge");
+        assert_eq!(ua.browser_version, "110.0.1587.57");
+        assert_eq!(ua.os, "Windows");
+        assert_eq!(ua.os_version, "10.0");
+        assert_eq!(ua.device, "desktop");
+
+        let ua = parse("Mozilla/5.0 (iPhone; CPU iPhone OS 16_3_1 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/16.3 Mobile/15E148 Safari/604.1");
+        assert_eq!(ua.browser, "Safari");
+        assert_eq!(ua.browser_version, "16.3");
+        assert_eq!(ua.os, "iOS");
+        assert_eq!(ua.os_version, "16.3.1");
+        assert_eq!(ua.device, "mobile");
+
+        let ua = parse("curl/7.87.0");
+        assert_eq!(ua.browser, "curl");
+        assert_eq!(ua.device, "bot");
+    }
+}
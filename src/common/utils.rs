@@ -20,6 +20,7 @@ use datafusion::{
 use serde_json::Value;
 use std::sync::Arc;
 
+use crate::common::time::time_column_micros_expr;
 use crate::infra::config::CONFIG;
 use crate::meta::common::FileMeta;
 use crate::meta::StreamType;
@@ -62,7 +63,8 @@ pub async fn populate_file_meta(
     ctx.register_table("temp", Arc::new(provider)).unwrap();
 
     let sql = format!(
-        "SELECT min({0}) as min, max({0}) as max ,count({0}) as num_records FROM temp ;",
+        "SELECT min({0}) as min, max({0}) as max ,count({1}) as num_records FROM temp ;",
+        time_column_micros_expr(&schema),
         CONFIG.common.time_stamp_col
     );
     let df = ctx.sql(sql.as_str()).await.unwrap();
@@ -155,6 +157,7 @@ mod test_utils {
             records: 0,
             original_size: 1000,
             compressed_size: 700,
+            column_stats: vec![],
         };
         populate_file_meta(schema, vec![vec![batch]], &mut file_meta).await;
         assert_eq!(file_meta.records, 4);
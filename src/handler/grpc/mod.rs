@@ -68,6 +68,11 @@ impl From<&meta::common::FileMeta> for cluster_rpc::FileMeta {
             records: req.records,
             original_size: req.original_size,
             compressed_size: req.compressed_size,
+            column_stats: req
+                .column_stats
+                .iter()
+                .map(cluster_rpc::ColumnStats::from)
+                .collect(),
         }
     }
 }
@@ -80,6 +85,33 @@ impl From<&cluster_rpc::FileMeta> for meta::common::FileMeta {
             records: req.records,
             original_size: req.original_size,
             compressed_size: req.compressed_size,
+            column_stats: req
+                .column_stats
+                .iter()
+                .map(meta::common::ColumnStats::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&meta::common::ColumnStats> for cluster_rpc::ColumnStats {
+    fn from(req: &meta::common::ColumnStats) -> Self {
+        cluster_rpc::ColumnStats {
+            name: req.name.clone(),
+            min: req.min,
+            max: req.max,
+            null_count: req.null_count,
+        }
+    }
+}
+
+impl From<&cluster_rpc::ColumnStats> for meta::common::ColumnStats {
+    fn from(req: &cluster_rpc::ColumnStats) -> Self {
+        meta::common::ColumnStats {
+            name: req.name.clone(),
+            min: req.min,
+            max: req.max,
+            null_count: req.null_count,
         }
     }
 }
@@ -105,6 +137,12 @@ mod test {
             records: 300,
             original_size: 10,
             compressed_size: 1,
+            column_stats: vec![meta::common::ColumnStats {
+                name: "status".to_string(),
+                min: 200.0,
+                max: 503.0,
+                null_count: 2,
+            }],
         };
 
         let rpc_meta = cluster_rpc::FileMeta::from(&file_meta);
@@ -0,0 +1,110 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{get, http, post, web, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use std::collections::HashMap;
+use std::io::Error;
+
+use crate::common::http::get_stream_type_from_request;
+use crate::meta::{self, delete_by_query::DeleteByQueryRequest, StreamType};
+use crate::service::delete_by_query as delete_by_query_service;
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "DeleteByQuery",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+    ),
+    request_body(content = DeleteByQueryRequest, description = "Records to delete", content_type = "application/json"),
+    responses(
+        (status = 202, description="Accepted", content_type = "application/json", body = DeleteByQueryReport),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/{stream_name}/_delete_by_query")]
+pub async fn delete_by_query(
+    credentials: BasicAuth,
+    path: web::Path<(String, String)>,
+    body: web::Json<DeleteByQueryRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    delete_by_query_service::delete_by_query(
+        &org_id,
+        &stream_name,
+        stream_type,
+        credentials.user_id(),
+        body.into_inner(),
+    )
+    .await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "DeleteByQueryList",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = DeleteByQueryList),
+    )
+)]
+#[get("/{org_id}/delete_by_query")]
+pub async fn list_delete_by_query(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    delete_by_query_service::list_reports(&org_id.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "DeleteByQueryReport",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("id" = String, Path, description = "Delete by query job id"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = DeleteByQueryReport),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/delete_by_query/{id}")]
+pub async fn get_delete_by_query(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (org_id, id) = path.into_inner();
+    delete_by_query_service::get_report(&org_id, &id).await
+}
@@ -0,0 +1,98 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{delete, get, http, post, web, HttpRequest, HttpResponse};
+use std::io::Error;
+
+use crate::meta::lookup::LookupTableParams;
+use crate::service::lookup_tables;
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "LookupTables",
+    operation_id = "LookupTableSave",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("name" = String, Path, description = "Lookup table name"),
+        ("key" = String, Query, description = "Column used to match records"),
+        ("format" = Option<String>, Query, description = "csv or json"),
+    ),
+    request_body(content = String, description = "Lookup table data", content_type = "text/csv"),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/lookup_tables/{name}")]
+pub async fn save_lookup_table(
+    path: web::Path<(String, String)>,
+    params: web::Query<LookupTableParams>,
+    body: web::Bytes,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, name) = path.into_inner();
+    let is_csv = match &params.format {
+        Some(format) => format.eq_ignore_ascii_case("csv"),
+        None => req
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .map_or(false, |v| v.contains("csv")),
+    };
+    lookup_tables::save_lookup_table(&org_id, &name, &params.key, is_csv, body).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "LookupTables",
+    operation_id = "LookupTableList",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = LookupTableList),
+    )
+)]
+#[get("/{org_id}/lookup_tables")]
+pub async fn list_lookup_tables(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    lookup_tables::list_lookup_tables(&org_id.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "LookupTables",
+    operation_id = "LookupTableDelete",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("name" = String, Path, description = "Lookup table name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/lookup_tables/{name}")]
+pub async fn delete_lookup_table(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (org_id, name) = path.into_inner();
+    lookup_tables::delete_lookup_table(&org_id, &name).await
+}
@@ -14,12 +14,17 @@
 
 pub mod alerts;
 pub mod dashboards;
+pub mod delete_by_query;
 pub mod functions;
 pub mod ingest;
+pub mod lookup_tables;
 pub mod organization;
 pub mod prom;
+pub mod reindex;
 pub mod search;
 pub mod status;
 pub mod stream;
+pub mod stream_aliases;
+pub mod stream_templates;
 pub mod traces;
 pub mod users;
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use actix_web::{get, put, web, HttpResponse, Result};
+use actix_web::{get, post, put, web, HttpResponse, Result};
 use actix_web_httpauth::extractors::basic::BasicAuth;
 use serde::Serialize;
 use std::collections::HashSet;
@@ -20,7 +20,7 @@ use std::io::Error;
 
 use crate::common::auth::is_root_user;
 use crate::infra::config::USERS;
-use crate::meta::organization::PasscodeResponse;
+use crate::meta::organization::{OrganizationSettings, PasscodeResponse};
 use crate::service::organization::get_passcode;
 use crate::service::organization::{self, update_passcode};
 
@@ -201,3 +201,49 @@ async fn update_user_passcode(
     let passcode = update_passcode(org_id, user_id).await;
     Ok(HttpResponse::Ok().json(PasscodeResponse { data: passcode }))
 }
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Organizations",
+    operation_id = "GetOrganizationSettings",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = OrganizationSettings),
+        (status = 500, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/organizations/settings")]
+pub async fn get_org_settings(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    let org = org_id.into_inner();
+    organization::get_settings(&org).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Organizations",
+    operation_id = "SetOrganizationSettings",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    request_body(content = OrganizationSettings, description = "Organization settings", content_type = "application/json"),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/organizations/settings")]
+pub async fn set_org_settings(
+    org_id: web::Path<String>,
+    settings: web::Json<OrganizationSettings>,
+) -> Result<HttpResponse, Error> {
+    let org = org_id.into_inner();
+    organization::set_settings(&org, settings.into_inner()).await
+}
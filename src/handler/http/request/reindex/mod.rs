@@ -0,0 +1,133 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{get, http, post, web, HttpRequest, HttpResponse};
+use actix_web_httpauth::extractors::basic::BasicAuth;
+use std::collections::HashMap;
+use std::io::Error;
+
+use crate::common::http::get_stream_type_from_request;
+use crate::meta::{self, reindex::ReindexRequest, StreamType};
+use crate::service::reindex as reindex_service;
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "Reindex",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+    ),
+    request_body(content = ReindexRequest, description = "Records to reindex", content_type = "application/json"),
+    responses(
+        (status = 202, description="Accepted", content_type = "application/json", body = ReindexJob),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/{stream_name}/_reindex")]
+pub async fn reindex(
+    credentials: BasicAuth,
+    path: web::Path<(String, String)>,
+    body: web::Json<ReindexRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    reindex_service::create_job(
+        &org_id,
+        &stream_name,
+        stream_type,
+        credentials.user_id(),
+        body.into_inner(),
+    )
+    .await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "ReindexList",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = ReindexJobList),
+    )
+)]
+#[get("/{org_id}/reindex")]
+pub async fn list_reindex(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    reindex_service::list_jobs(&org_id.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "ReindexJob",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("id" = String, Path, description = "Reindex job id"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = ReindexJob),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/reindex/{id}")]
+pub async fn get_reindex(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (org_id, id) = path.into_inner();
+    reindex_service::get_job(&org_id, &id).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "ReindexCancel",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("id" = String, Path, description = "Reindex job id"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = ReindexJob),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/reindex/{id}/_cancel")]
+pub async fn cancel_reindex(path: web::Path<(String, String)>) -> Result<HttpResponse, Error> {
+    let (org_id, id) = path.into_inner();
+    reindex_service::cancel_job(&org_id, &id).await
+}
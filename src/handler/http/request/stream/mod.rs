@@ -12,13 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use actix_web::{get, http, post, web, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, get, http, post, web, HttpRequest, HttpResponse, Responder};
 use std::collections::HashMap;
 use std::io::Error;
 use std::io::ErrorKind;
 
 use crate::common::http::get_stream_type_from_request;
-use crate::meta::{self, stream::StreamSettings, StreamType};
+use crate::meta::stream::{
+    FieldStatsRequest, StreamCloneRequest, StreamRenameRequest, StreamSettings,
+};
+use crate::meta::{self, StreamType};
 use crate::service::stream;
 
 #[utoipa::path(
@@ -192,6 +195,427 @@ async fn list(org_id: web::Path<String>, req: HttpRequest) -> impl Responder {
     }
 }
 
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamDelete",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+    ),
+    responses(
+        (status = 202, description="Accepted", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/{stream_name}")]
+async fn delete(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    stream::delete_stream(org_id.as_str(), stream_name.as_str(), stream_type).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamDeleteStatus",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamDeletion),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/{stream_name}/delete_status")]
+async fn delete_status(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    stream::get_stream_deletion(org_id.as_str(), stream_name.as_str(), stream_type).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamSchemaVersions",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = SchemaVersionList),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/{stream_name}/schema/versions")]
+async fn schema_versions(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    stream::get_schema_versions(org_id.as_str(), stream_name.as_str(), stream_type).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamSchemaVersion",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("version" = usize, Path, description = "Schema version"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = SchemaVersion),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/{stream_name}/schema/versions/{version}")]
+async fn schema_version(
+    path: web::Path<(String, String, usize)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name, version) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    stream::get_schema_version(org_id.as_str(), stream_name.as_str(), stream_type, version).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamSchemaDiff",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("from" = usize, Query, description = "Schema version to compare from"),
+        ("to" = Option<usize>, Query, description = "Schema version to compare to, defaults to the latest"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = SchemaDiff),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/{stream_name}/schema/diff")]
+async fn schema_diff(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    let from = match query.get("from").map(|v| v.parse::<usize>()) {
+        Some(Ok(v)) => v,
+        _ => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some("'from' query param with a schema version is required".to_string()),
+                )),
+            )
+        }
+    };
+    let to = match query.get("to").map(|v| v.parse::<usize>()) {
+        Some(Ok(v)) => Some(v),
+        Some(Err(_)) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some("'to' query param must be a schema version".to_string()),
+                )),
+            )
+        }
+        None => None,
+    };
+    stream::diff_schema_versions(org_id.as_str(), stream_name.as_str(), stream_type, from, to).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamFieldStats",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("field" = String, Path, description = "Field name"),
+        ("start_time" = i64, Query, description = "Start time in microseconds"),
+        ("end_time" = i64, Query, description = "End time in microseconds"),
+        ("top" = Option<usize>, Query, description = "Number of most frequent values, defaults to 10, max 100"),
+        ("sample_ratio" = Option<f64>, Query, description = "Share of the files to scan, defaults to 1"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = FieldStats),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/{stream_name}/fields/{field}/stats")]
+async fn field_stats(
+    path: web::Path<(String, String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name, field) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    let stats_req = match web::Query::<FieldStatsRequest>::from_query(req.query_string()) {
+        Ok(v) => v.into_inner(),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    crate::service::field_stats::get_field_stats(
+        org_id.as_str(),
+        stream_name.as_str(),
+        stream_type,
+        field.as_str(),
+        stats_req,
+    )
+    .await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamVolumeHistory",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("start_time" = Option<i64>, Query, description = "Start time in microseconds, defaults to 0"),
+        ("end_time" = Option<i64>, Query, description = "End time in microseconds, defaults to now"),
+        ("interval" = Option<String>, Query, description = "hour or day, defaults to hour"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamVolumeHistory),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[get("/{org_id}/{stream_name}/volume")]
+async fn volume_history(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    let start_time = query
+        .get("start_time")
+        .map_or(0, |v| v.parse::<i64>().unwrap_or(0));
+    let end_time = query.get("end_time").map_or_else(
+        || chrono::Utc::now().timestamp_micros(),
+        |v| v.parse::<i64>().unwrap_or(0),
+    );
+    let interval = query.get("interval").map_or("hour", |v| v.as_str());
+    crate::service::volume::get_volume_history(
+        org_id.as_str(),
+        stream_name.as_str(),
+        stream_type,
+        start_time,
+        end_time,
+        interval,
+    )
+    .await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamRename",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("type" = Option<String>, Query, description = "Stream type, defaults to logs"),
+    ),
+    request_body(content = StreamRenameRequest, description = "New stream name", content_type = "application/json"),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamCopyReport),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/{stream_name}/rename")]
+async fn rename(
+    path: web::Path<(String, String)>,
+    body: web::Json<StreamRenameRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    crate::service::stream_copy::rename_stream(
+        org_id.as_str(),
+        stream_name.as_str(),
+        stream_type,
+        body.into_inner(),
+    )
+    .await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "Streams",
+    operation_id = "StreamClone",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Path, description = "Stream name"),
+        ("type" = Option<String>, Query, description = "Stream type, defaults to logs"),
+    ),
+    request_body(content = StreamCloneRequest, description = "New stream name and time range", content_type = "application/json"),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamCopyReport),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/{stream_name}/clone")]
+async fn clone(
+    path: web::Path<(String, String)>,
+    body: web::Json<StreamCloneRequest>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, stream_name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    crate::service::stream_copy::clone_stream(
+        org_id.as_str(),
+        stream_name.as_str(),
+        stream_type,
+        body.into_inner(),
+    )
+    .await
+}
+
 #[get("/{org_id}/")]
 async fn org_index(_org_id: web::Path<String>, req: HttpRequest) -> impl Responder {
     // eg.1: User-Agent:[elastic-transport-ruby/8.0.1 (RUBY_VERSION: 3.1.2; linux x86_64; Faraday v1.10.0)]
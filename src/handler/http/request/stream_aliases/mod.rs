@@ -0,0 +1,105 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{delete, get, http, post, web, HttpRequest, HttpResponse};
+use std::collections::HashMap;
+use std::io::Error;
+
+use crate::common::http::get_stream_type_from_request;
+use crate::meta::stream::StreamAlias;
+use crate::meta::{self, StreamType};
+use crate::service::stream_aliases;
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamAliases",
+    operation_id = "StreamAliasSave",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("name" = String, Path, description = "Stream alias name"),
+    ),
+    request_body(content = StreamAlias, description = "Stream alias", content_type = "application/json"),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/stream_aliases/{name}")]
+pub async fn save_stream_alias(
+    path: web::Path<(String, String)>,
+    alias: web::Json<StreamAlias>,
+) -> Result<HttpResponse, Error> {
+    let (org_id, name) = path.into_inner();
+    stream_aliases::save_alias(&org_id, &name, alias.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamAliases",
+    operation_id = "StreamAliasList",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamAliasList),
+    )
+)]
+#[get("/{org_id}/stream_aliases")]
+pub async fn list_stream_aliases(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    stream_aliases::list_aliases(&org_id.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamAliases",
+    operation_id = "StreamAliasDelete",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("name" = String, Path, description = "Stream alias name"),
+        ("type" = Option<String>, Query, description = "Stream type, defaults to logs"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/stream_aliases/{name}")]
+pub async fn delete_stream_alias(
+    path: web::Path<(String, String)>,
+    req: HttpRequest,
+) -> Result<HttpResponse, Error> {
+    let (org_id, name) = path.into_inner();
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string()).unwrap();
+    let stream_type = match get_stream_type_from_request(&query) {
+        Ok(v) => v.unwrap_or(StreamType::Logs),
+        Err(e) => {
+            return Ok(
+                HttpResponse::BadRequest().json(meta::http::HttpResponse::error(
+                    http::StatusCode::BAD_REQUEST.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    stream_aliases::delete_alias(&org_id, stream_type, &name).await
+}
@@ -0,0 +1,114 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{delete, get, post, web, HttpResponse};
+use std::io::Error;
+
+use crate::meta::template::{StreamTemplate, StreamTemplateMatchParams};
+use crate::meta::StreamType;
+use crate::service::stream_templates;
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamTemplates",
+    operation_id = "StreamTemplateSave",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("name" = String, Path, description = "Stream template name"),
+    ),
+    request_body(content = StreamTemplate, description = "Stream template", content_type = "application/json"),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 400, description="Failure", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[post("/{org_id}/stream_templates/{name}")]
+pub async fn save_stream_template(
+    path: web::Path<(String, String)>,
+    template: web::Json<StreamTemplate>,
+) -> Result<HttpResponse, Error> {
+    let (org_id, name) = path.into_inner();
+    stream_templates::save_template(&org_id, &name, template.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamTemplates",
+    operation_id = "StreamTemplateList",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamTemplateList),
+    )
+)]
+#[get("/{org_id}/stream_templates")]
+pub async fn list_stream_templates(org_id: web::Path<String>) -> Result<HttpResponse, Error> {
+    stream_templates::list_templates(&org_id.into_inner()).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamTemplates",
+    operation_id = "StreamTemplateMatch",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("stream_name" = String, Query, description = "Name of the stream to create"),
+        ("type" = Option<String>, Query, description = "Stream type, defaults to logs"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = StreamTemplateMatch),
+    )
+)]
+#[get("/{org_id}/stream_templates/_match")]
+pub async fn match_stream_template(
+    org_id: web::Path<String>,
+    params: web::Query<StreamTemplateMatchParams>,
+) -> Result<HttpResponse, Error> {
+    let stream_type = params.stream_type.unwrap_or(StreamType::Logs);
+    stream_templates::match_template(&org_id.into_inner(), &params.stream_name, stream_type).await
+}
+
+#[utoipa::path(
+    context_path = "/api",
+    tag = "StreamTemplates",
+    operation_id = "StreamTemplateDelete",
+    security(
+        ("Authorization"= [])
+    ),
+    params(
+        ("org_id" = String, Path, description = "Organization name"),
+        ("name" = String, Path, description = "Stream template name"),
+    ),
+    responses(
+        (status = 200, description="Success", content_type = "application/json", body = HttpResponse),
+        (status = 404, description="NotFound", content_type = "application/json", body = HttpResponse),
+    )
+)]
+#[delete("/{org_id}/stream_templates/{name}")]
+pub async fn delete_stream_template(
+    path: web::Path<(String, String)>,
+) -> Result<HttpResponse, Error> {
+    let (org_id, name) = path.into_inner();
+    stream_templates::delete_template(&org_id, &name).await
+}
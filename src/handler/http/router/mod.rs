@@ -22,13 +22,18 @@ use utoipa_swagger_ui::{SwaggerUi, Url};
 use super::auth::validator;
 use super::request::alerts::*;
 use super::request::dashboards::*;
+use super::request::delete_by_query;
 use super::request::functions;
 use super::request::ingest;
+use super::request::lookup_tables;
 use super::request::organization::*;
 use super::request::prom::*;
+use super::request::reindex;
 use super::request::search;
 use super::request::status;
 use super::request::stream;
+use super::request::stream_aliases;
+use super::request::stream_templates;
 use super::request::traces::*;
 use super::request::users;
 use crate::infra::config::CONFIG;
@@ -93,9 +98,37 @@ pub fn get_service_routes(cfg: &mut web::ServiceConfig) {
             .service(ingest::json)
             .service(search::search)
             .service(search::around)
+            .service(lookup_tables::save_lookup_table)
+            .service(lookup_tables::list_lookup_tables)
+            .service(lookup_tables::delete_lookup_table)
+            .service(delete_by_query::delete_by_query)
+            .service(delete_by_query::list_delete_by_query)
+            .service(delete_by_query::get_delete_by_query)
+            .service(reindex::reindex)
+            .service(reindex::list_reindex)
+            .service(reindex::get_reindex)
+            .service(reindex::cancel_reindex)
+            .service(stream_templates::save_stream_template)
+            .service(stream_templates::list_stream_templates)
+            .service(stream_templates::match_stream_template)
+            .service(stream_templates::delete_stream_template)
+            .service(stream_aliases::save_stream_alias)
+            .service(stream_aliases::list_stream_aliases)
+            .service(stream_aliases::delete_stream_alias)
+            .service(get_org_settings)
+            .service(set_org_settings)
             .service(stream::schema)
+            .service(stream::schema_versions)
+            .service(stream::schema_version)
+            .service(stream::schema_diff)
+            .service(stream::field_stats)
+            .service(stream::volume_history)
+            .service(stream::rename)
+            .service(stream::clone)
             .service(stream::settings)
             .service(stream::list)
+            .service(stream::delete)
+            .service(stream::delete_status)
             .service(stream::org_index)
             .service(functions::save_function)
             .service(functions::list_functions)
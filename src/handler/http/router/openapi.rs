@@ -26,6 +26,22 @@ use crate::meta;
         request::stream::list,
         request::stream::schema,
         request::stream::settings,
+        request::stream::delete,
+        request::stream::delete_status,
+        request::stream::schema_versions,
+        request::stream::schema_version,
+        request::stream::schema_diff,
+        request::stream::field_stats,
+        request::stream::volume_history,
+        request::stream::rename,
+        request::stream::clone,
+        request::delete_by_query::delete_by_query,
+        request::delete_by_query::list_delete_by_query,
+        request::delete_by_query::get_delete_by_query,
+        request::reindex::reindex,
+        request::reindex::list_reindex,
+        request::reindex::get_reindex,
+        request::reindex::cancel_reindex,
         request::ingest::bulk,
         request::ingest::multi,
         request::ingest::json,
@@ -46,6 +62,18 @@ use crate::meta;
         request::alerts::list_alerts,
         request::alerts::get_alert,
         request::alerts::delete_alert,
+        request::lookup_tables::save_lookup_table,
+        request::lookup_tables::list_lookup_tables,
+        request::lookup_tables::delete_lookup_table,
+        request::stream_templates::save_stream_template,
+        request::stream_templates::list_stream_templates,
+        request::stream_templates::match_stream_template,
+        request::stream_templates::delete_stream_template,
+        request::stream_aliases::save_stream_alias,
+        request::stream_aliases::list_stream_aliases,
+        request::stream_aliases::delete_stream_alias,
+        request::organization::get_org_settings,
+        request::organization::set_org_settings,
 
     ),
     components(
@@ -56,7 +84,56 @@ use crate::meta;
             meta::stream::StreamStats,
             meta::stream::StreamProperty,
             meta::stream::StreamSettings,
+            meta::stream::PartitionTimeLevel,
+            meta::stream::TimestampUnit,
             meta::stream::ListStream,
+            meta::stream::StreamDeletion,
+            meta::stream::StreamDeletionStatus,
+            meta::stream::FieldStats,
+            meta::stream::FieldValueCount,
+            meta::stream::StreamVolume,
+            meta::stream::StreamVolumeHistory,
+            meta::stream::StreamAlias,
+            meta::stream::StreamAliasList,
+            meta::stream::StreamRenameRequest,
+            meta::stream::StreamCloneRequest,
+            meta::stream::StreamCopyReport,
+            meta::delete_by_query::DeleteByQueryRequest,
+            meta::delete_by_query::DeleteByQueryStatus,
+            meta::delete_by_query::DeleteByQueryFile,
+            meta::delete_by_query::DeleteByQueryReport,
+            meta::delete_by_query::DeleteByQueryList,
+            meta::reindex::ReindexRequest,
+            meta::reindex::ReindexStatus,
+            meta::reindex::ReindexJob,
+            meta::reindex::ReindexJobList,
+            meta::quality::QualityRule,
+            meta::quality::QualityRuleType,
+            meta::quality::QualityValueType,
+            meta::quality::QualityAction,
+            meta::sampling::SamplingRule,
+            meta::sampling::SamplingAction,
+            meta::schema::DefinedField,
+            meta::schema::FieldType,
+            meta::schema::NestedField,
+            meta::schema::NestedFieldType,
+            meta::schema::VirtualField,
+            meta::schema::SchemaMode,
+            meta::schema::SchemaVersion,
+            meta::schema::SchemaVersionList,
+            meta::schema::SchemaFieldChange,
+            meta::schema::SchemaDiff,
+            meta::schema::SchemaChangeEvent,
+            meta::prom::DerivedMetric,
+            meta::prom::DerivedMetricType,
+            meta::lookup::LookupTable,
+            meta::lookup::LookupTableList,
+            meta::lookup::LookupEnrichment,
+            meta::enrichment::EnrichmentProcessor,
+            meta::enrichment::ProcessorType,
+            meta::template::StreamTemplate,
+            meta::template::StreamTemplateList,
+            meta::template::StreamTemplateMatch,
             meta::ingestion::RecordStatus,
             meta::ingestion::StreamStatus,
             meta::ingestion::IngestionResponse,
@@ -74,6 +151,7 @@ use crate::meta;
             meta::alert::AlertList,
             meta::alert::Condition,
             meta::alert::AllOperator,
+            meta::organization::OrganizationSettings,
         ),
     ),
     modifiers(&SecurityAddon),
@@ -84,6 +162,10 @@ use crate::meta;
         (name = "Functions", description = "Functions retrieval & management operations"),
         (name = "Users", description = "Users retrieval & management operations"),
         (name = "Alerts", description = "Alerts retrieval & management operations"),
+        (name = "LookupTables", description = "Lookup tables retrieval & management operations"),
+        (name = "StreamTemplates", description = "Stream templates retrieval & management operations"),
+        (name = "StreamAliases", description = "Stream aliases retrieval & management operations"),
+        (name = "Organizations", description = "Organizations retrieval & management operations"),
     ),
     info(
         description = "ZincObserve API documents [https://docs.zinc.dev/](https://docs.zinc.dev/)",
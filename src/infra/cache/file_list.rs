@@ -15,7 +15,7 @@
 use chrono::{Datelike, TimeZone, Timelike, Utc};
 use dashmap::DashMap;
 
-use crate::meta::{common::FileMeta, StreamType};
+use crate::meta::{common::FileMeta, stream::PartitionTimeLevel, StreamType};
 
 lazy_static! {
     pub static ref FILES: DashMap<String, Box<FileList>> = DashMap::new();
@@ -183,7 +183,7 @@ pub fn get_file_from_cache(key: &str) -> Result<FileMeta, anyhow::Error> {
 
     // println!("get_file_meta_from_cache: {}", key);
 
-    Ok(*resp)
+    Ok(resp.clone())
 }
 
 async fn scan_prefix(
@@ -374,11 +374,14 @@ pub async fn get_file_list(
     stream_type: StreamType,
     time_min: i64,
     time_max: i64,
+    partition_time_level: PartitionTimeLevel,
 ) -> Result<Vec<String>, anyhow::Error> {
     let mut files = Vec::new();
     let mut keys = Vec::new();
     let mut key = "".to_string();
     if time_min > 0 && time_max > 0 {
+        // daily partitions are stored under the first hour of the day
+        let time_min = partition_time_level.truncate(time_min);
         let time_min = Utc.timestamp_nanos(time_min * 1000);
         let time_max = Utc.timestamp_nanos(time_max * 1000);
         if time_min.year() == time_max.year() {
@@ -496,6 +499,19 @@ pub fn get_all_organization() -> Result<Vec<String>, anyhow::Error> {
     Ok(orgs)
 }
 
+pub fn remove_stream(org_id: &str, stream_type: StreamType, stream_name: &str) {
+    if let Some(org_cache) = FILES.get(org_id) {
+        if let Some(type_cache) = org_cache
+            .sub
+            .as_ref()
+            .unwrap()
+            .get(&stream_type.to_string())
+        {
+            type_cache.sub.as_ref().unwrap().remove(stream_name);
+        }
+    }
+}
+
 pub fn get_all_stream(org_id: &str, stream_type: StreamType) -> Result<Vec<String>, anyhow::Error> {
     let mut streams = Vec::new();
     let org_cache = FILES.get(org_id).unwrap();
@@ -522,10 +538,11 @@ mod tests {
             records: 10000,
             original_size: 1024,
             compressed_size: 1,
+            column_stats: vec![],
         };
         let _ret = set_file_to_cache(
             "files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet",
-            Some(meta),
+            Some(meta.clone()),
             false,
         )
         .unwrap();
@@ -12,13 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use chrono::Utc;
 use dashmap::DashMap;
 
 use crate::meta::common::FileMeta;
-use crate::meta::stream::StreamStats;
+use crate::meta::stream::{PartitionTimeLevel, StreamStats, StreamVolume};
 
 lazy_static! {
     static ref STATS: DashMap<String, StreamStats> = DashMap::with_capacity(2);
+    /// volumes not flushed yet, key: org_id/stream_type/stream_name/hour
+    static ref VOLUMES: DashMap<String, StreamVolume> = DashMap::new();
+    /// volumes flushed since the node started, key: org_id/stream_type/stream_name
+    static ref VOLUME_TOTALS: DashMap<String, StreamVolume> = DashMap::new();
 }
 
 const STREAM_STATS_MEM_SIZE: usize = std::mem::size_of::<StreamStats>();
@@ -37,7 +42,12 @@ pub fn set_stream_stats(org_id: &str, stream_name: &str, stream_type: &str, val:
     STATS.insert(key, val);
 }
 
-pub fn incr_stream_stats(key: &str, val: FileMeta) -> Result<(), anyhow::Error> {
+pub fn remove_stream_stats(org_id: &str, stream_name: &str, stream_type: &str) {
+    let key = format!("{}/{}/{}", org_id, stream_type, stream_name);
+    STATS.remove(&key);
+}
+
+pub fn incr_stream_stats(key: &str, val: &FileMeta) -> Result<(), anyhow::Error> {
     // eg: files/default/olympics/2022/10/03/10/6982652937134804993_1.parquet
     let columns = key.split('/').collect::<Vec<&str>>();
     if columns.len() < 8 {
@@ -69,7 +79,7 @@ pub fn incr_stream_stats(key: &str, val: FileMeta) -> Result<(), anyhow::Error>
     Ok(())
 }
 
-pub fn decr_stream_stats(key: &str, val: FileMeta) -> Result<(), anyhow::Error> {
+pub fn decr_stream_stats(key: &str, val: &FileMeta) -> Result<(), anyhow::Error> {
     // eg: files/default/olympics/2022/10/03/10/6982652937134804993_1.parquet
     let columns = key.split('/').collect::<Vec<&str>>();
     if columns.len() < 8 {
@@ -83,7 +93,11 @@ pub fn decr_stream_stats(key: &str, val: FileMeta) -> Result<(), anyhow::Error>
     let stream_type = columns[2].to_string();
     let stream_name = columns[3].to_string();
     let key = format!("{}/{}/{}", org_id, stream_type, stream_name);
-    let mut stats = STATS.entry(key).or_default();
+    // the stats of a deleted stream are gone
+    let mut stats = match STATS.get_mut(&key) {
+        Some(stats) => stats,
+        None => return Ok(()),
+    };
     stats.doc_num -= val.records;
     stats.file_num -= 1;
     stats.storage_size -= val.original_size as f64;
@@ -92,6 +106,67 @@ pub fn decr_stream_stats(key: &str, val: FileMeta) -> Result<(), anyhow::Error>
     Ok(())
 }
 
+/// Adds a newly ingested file to the volume of the hour of its records
+pub fn incr_stream_volume(key: &str, val: &FileMeta) -> Result<(), anyhow::Error> {
+    // eg: files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet
+    let columns = key.split('/').collect::<Vec<&str>>();
+    if columns.len() < 8 {
+        return Err(anyhow::anyhow!(
+            "[TRACE] [incr_stream_volume] Invalid file path: {}",
+            key
+        ));
+    }
+    let stream_key = columns[1..4].join("/");
+    let timestamp = if val.min_ts > 0 {
+        val.min_ts
+    } else {
+        Utc::now().timestamp_micros()
+    };
+    let volume = StreamVolume {
+        timestamp: PartitionTimeLevel::Hour.truncate(timestamp),
+        records: val.records,
+        files: 1,
+        original_size: val.original_size,
+        compressed_size: val.compressed_size,
+    };
+    add_stream_volume(&stream_key, &volume);
+    Ok(())
+}
+
+/// Adds to the pending volume of the stream, key: org_id/stream_type/stream_name
+pub fn add_stream_volume(stream_key: &str, volume: &StreamVolume) {
+    let key = format!("{}/{}", stream_key, volume.timestamp);
+    VOLUMES
+        .entry(key)
+        .or_insert_with(|| StreamVolume {
+            timestamp: volume.timestamp,
+            ..Default::default()
+        })
+        .add(volume);
+}
+
+/// Takes the pending volumes, returns the stream keys with the hourly volumes
+pub fn take_stream_volumes() -> Vec<(String, StreamVolume)> {
+    let keys: Vec<String> = VOLUMES.iter().map(|item| item.key().clone()).collect();
+    let mut volumes = Vec::with_capacity(keys.len());
+    for key in keys {
+        if let Some((key, volume)) = VOLUMES.remove(&key) {
+            let stream_key = key[..key.rfind('/').unwrap()].to_string();
+            volumes.push((stream_key, volume));
+        }
+    }
+    volumes
+}
+
+/// Adds the flushed volumes to the total of the stream, returns the new total
+pub fn add_stream_volume_total(stream_key: &str, volumes: &[StreamVolume]) -> StreamVolume {
+    let mut total = VOLUME_TOTALS.entry(stream_key.to_string()).or_default();
+    for volume in volumes {
+        total.add(volume);
+    }
+    *total
+}
+
 pub fn get_stream_stats_len() -> usize {
     STATS.len()
 }
@@ -131,16 +206,43 @@ mod tests {
             records: 300,
             original_size: 10,
             compressed_size: 1,
+            column_stats: vec![],
         };
 
         let file_key = "files/nexus/logs/default/2022/10/03/10/6982652937134804993_1.parquet";
-        let _ = incr_stream_stats(file_key, file_meta);
+        let _ = incr_stream_stats(file_key, &file_meta);
 
         let stats = get_stream_stats("nexus", "default", "logs");
         assert_eq!(stats.unwrap().doc_num, 5300);
 
-        let _ = decr_stream_stats(file_key, file_meta);
+        let _ = decr_stream_stats(file_key, &file_meta);
         let stats = get_stream_stats("nexus", "default", "logs");
         assert_eq!(stats.unwrap().doc_num, 5000);
     }
+
+    #[test]
+    fn test_stream_volume() {
+        let file_meta = FileMeta {
+            min_ts: 1667978841110,
+            max_ts: 1667978845354,
+            records: 300,
+            original_size: 10,
+            compressed_size: 1,
+            column_stats: vec![],
+        };
+        let file_key = "files/nexus/logs/volume/2022/10/03/10/6982652937134804993_1.parquet";
+        incr_stream_volume(file_key, &file_meta).unwrap();
+        incr_stream_volume(file_key, &file_meta).unwrap();
+        assert!(incr_stream_volume("files/nexus/logs", &file_meta).is_err());
+
+        let volumes = take_stream_volumes()
+            .into_iter()
+            .filter(|(key, _)| key == "nexus/logs/volume")
+            .map(|(_, v)| v)
+            .collect::<Vec<_>>();
+        let records: u64 = volumes.iter().map(|v| v.records).sum();
+        let files: u64 = volumes.iter().map(|v| v.files).sum();
+        assert_eq!(records, 600);
+        assert_eq!(files, 2);
+    }
 }
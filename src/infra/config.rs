@@ -24,7 +24,10 @@ use sys_info::hostname;
 use crate::common::file::get_file_meta;
 use crate::meta::alert::{AlertList, Trigger, TriggerTimer};
 use crate::meta::functions::{FunctionList, Transform};
+use crate::meta::lookup::LookupData;
 use crate::meta::prom::ClusterLeader;
+use crate::meta::stream::StreamAlias;
+use crate::meta::template::StreamTemplate;
 use crate::meta::user::User;
 
 pub static VERSION: &str = env!("GIT_VERSION");
@@ -63,6 +66,9 @@ lazy_static! {
     pub static ref STREAM_ALERTS: DashMap<String, AlertList> = DashMap::new();
     pub static ref TRIGGERS: DashMap<String, Trigger> = DashMap::new();
     pub static ref TRIGGERS_IN_PROCESS: DashMap<String, TriggerTimer> = DashMap::new();
+    pub static ref LOOKUP_TABLES: DashMap<String, LookupData> = DashMap::new();
+    pub static ref STREAM_TEMPLATES: DashMap<String, StreamTemplate> = DashMap::new();
+    pub static ref STREAM_ALIASES: DashMap<String, StreamAlias> = DashMap::new();
 }
 
 #[derive(Clone, Debug, EnvConfig)]
@@ -162,12 +168,23 @@ pub struct Common {
         default = "Basic YWRtaW46Q29tcGxleHBhc3MjMTIz"
     )]
     pub tracing_header_value: String,
+    #[env_config(name = "ZO_GEOIP_DB_PATH", default = "")] // comma separated .mmdb files
+    pub geoip_db_path: String,
     #[env_config(name = "ZO_TELEMETRY", default = true)]
     pub telemetry_enabled: bool,
     #[env_config(name = "ZO_TELEMETRY_URL", default = "https://e1.zinclabs.dev")]
     pub telemetry_url: String,
     #[env_config(name = "ZO_PROMETHEUS_ENABLED", default = false)]
     pub prometheus_enabled: bool,
+    // also write the ingested volumes into the metrics streams zo_stream_ingested_*
+    #[env_config(name = "ZO_VOLUME_HISTORY_METRICS_ENABLED", default = false)]
+    pub volume_history_metrics_enabled: bool,
+    // schema changes of the streams opted in are posted to this url
+    #[env_config(name = "ZO_SCHEMA_CHANGE_WEBHOOK", default = "")]
+    pub schema_change_webhook: String,
+    // and written into this logs stream of the org, empty disables it
+    #[env_config(name = "ZO_SCHEMA_CHANGE_STREAM", default = "schema_changes")]
+    pub schema_change_stream: String,
 }
 
 #[derive(Clone, Debug, EnvConfig)]
@@ -192,6 +209,12 @@ pub struct Limit {
     pub metrics_leader_push_interval: u64,
     #[env_config(name = "ZO_METRICS_LEADER_ELECTION_INTERVAL", default = 30)]
     pub metrics_leader_election_interval: i64,
+    #[env_config(name = "ZO_DERIVED_METRICS_PUSH_INTERVAL", default = 60)] // seconds
+    pub derived_metrics_push_interval: u64,
+    #[env_config(name = "ZO_GEOIP_RELOAD_INTERVAL", default = 60)] // seconds
+    pub geoip_reload_interval: u64,
+    #[env_config(name = "ZO_VOLUME_HISTORY_PUSH_INTERVAL", default = 60)] // seconds
+    pub volume_history_push_interval: u64,
     #[env_config(name = "ZO_HEARTBEAT_INTERVAL", default = 30)] // in minutes
     pub hb_interval: i64,
     // no need set by environment
@@ -206,6 +229,11 @@ pub struct Compact {
     pub interval: u64,
     #[env_config(name = "ZO_COMPACT_MAX_FILE_SIZE", default = 256)] // MB
     pub max_file_size: u64,
+    #[env_config(name = "ZO_COMPACT_DATA_RETENTION_DAYS", default = 0)] // 0 means keep forever
+    pub data_retention_days: i64,
+    #[env_config(name = "ZO_COMPACT_VOLUME_HISTORY_RETENTION_DAYS", default = 400)]
+    // 0 means keep forever
+    pub volume_history_retention_days: i64,
 }
 
 #[derive(Clone, Debug, EnvConfig)]
@@ -17,7 +17,8 @@ use async_once::AsyncOnce;
 use async_trait::async_trait;
 use bytes::Bytes;
 use etcd_client::{
-    Compare, CompareOp, DeleteOptions, EventType, GetOptions, SortOrder, SortTarget, TxnOp,
+    Compare, CompareOp, DeleteOptions, EventType, GetOptions, LockOptions, SortOrder, SortTarget,
+    TxnOp,
 };
 use std::sync::atomic::{AtomicU8, Ordering};
 use std::sync::Arc;
@@ -529,8 +530,16 @@ impl Locker {
         if n < 1 {
             n = 1;
         }
+        // the lock is bound to the lease of the node, it is released when the
+        // node is gone
+        let lease_id = unsafe { cluster::LOCAL_NODE_KEY_LEASE_ID };
+        let options = if lease_id > 0 {
+            Some(LockOptions::new().with_lease(lease_id))
+        } else {
+            None
+        };
         for _ in 0..n {
-            match client.lock(self.key.as_str(), None).await {
+            match client.lock(self.key.as_str(), options.clone()).await {
                 Ok(resp) => {
                     self.lock_id = String::from_utf8_lossy(resp.key()).to_string();
                     self.state.store(1, Ordering::SeqCst);
@@ -67,6 +67,14 @@ impl FileStorage for Local {
             Err(e) => Err(anyhow::anyhow!(e)),
         }
     }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), anyhow::Error> {
+        let from = format!("{}{}", CONFIG.common.data_stream_dir, from);
+        let to = format!("{}{}", CONFIG.common.data_stream_dir, to);
+        fs::create_dir_all(Path::new(&to).parent().unwrap())?;
+        fs::copy(from, to)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -87,6 +95,13 @@ mod test_util {
         let resp = local.list("").await;
         assert!(resp.unwrap().contains(&file_name.to_string()));
 
+        let resp = local.copy(file_name, "copied/new_file.parquet").await;
+        assert!(resp.is_ok());
+        let resp = local.get("copied/new_file.parquet").await;
+        assert_eq!(resp.unwrap(), bytes::Bytes::from(file_text));
+        let resp = local.del("copied/new_file.parquet").await;
+        assert!(resp.is_ok());
+
         let resp = local.del(file_name).await;
         assert!(resp.is_ok());
     }
@@ -16,7 +16,8 @@ use async_trait::async_trait;
 use bytes::Bytes;
 use chrono::{TimeZone, Utc};
 
-use crate::{common::utils::is_local_disk_storage, infra::ider, meta::StreamType};
+use crate::meta::{stream::PartitionTimeLevel, StreamType};
+use crate::{common::utils::is_local_disk_storage, infra::ider};
 
 pub mod local;
 pub mod s3;
@@ -27,6 +28,8 @@ pub trait FileStorage: Sync + 'static {
     async fn get(&self, file: &str) -> Result<Bytes, anyhow::Error>;
     async fn put(&self, file: &str, data: Bytes) -> Result<(), anyhow::Error>;
     async fn del(&self, file: &str) -> Result<(), anyhow::Error>;
+    /// copies an object within the storage, without downloading it
+    async fn copy(&self, from: &str, to: &str) -> Result<(), anyhow::Error>;
 }
 
 lazy_static! {
@@ -45,6 +48,7 @@ pub fn generate_partioned_file_key(
     stream_name: &str,
     stream_type: StreamType,
     min_ts: i64,
+    partition_time_level: PartitionTimeLevel,
     extn: &str,
 ) -> (std::string::String, std::string::String) {
     let id = ider::generate();
@@ -53,7 +57,8 @@ pub fn generate_partioned_file_key(
     let prefix = if stream_type.eq(&StreamType::Metadata) {
         time.format("%Y/00/00/00").to_string()
     } else {
-        time.format("%Y/%m/%d/%H").to_string()
+        time.format(partition_time_level.storage_key_format())
+            .to_string()
     };
     (
         format!("{}/{}/{}/{}/", org_id, stream_type, stream_name, prefix),
@@ -72,9 +77,20 @@ mod tests {
             "stream_name",
             StreamType::Logs,
             1665580243211047,
+            PartitionTimeLevel::Hour,
             &CONFIG.common.file_ext_parquet,
         );
         assert_eq!(file_key.0.as_str(), "org/logs/stream_name/2022/10/12/13/");
         assert!(file_key.1.as_str().contains(".parquet"));
+
+        let file_key = generate_partioned_file_key(
+            "org",
+            "stream_name",
+            StreamType::Logs,
+            1665580243211047,
+            PartitionTimeLevel::Day,
+            &CONFIG.common.file_ext_parquet,
+        );
+        assert_eq!(file_key.0.as_str(), "org/logs/stream_name/2022/10/12/00/");
     }
 }
@@ -123,6 +123,28 @@ impl FileStorage for S3 {
             }
         }
     }
+
+    async fn copy(&self, from: &str, to: &str) -> Result<(), anyhow::Error> {
+        let s3config = S3CONFIG.get().await.clone().unwrap();
+        let client = Client::new(&s3config);
+        let result = client
+            .copy_object()
+            .bucket(&CONFIG.s3.bucket_name)
+            .copy_source(format!("{}/{}", CONFIG.s3.bucket_name, from))
+            .key(to)
+            .send()
+            .await;
+        match result {
+            Ok(_output) => {
+                log::info!("s3 File copy success: {} -> {}", from, to);
+                Ok(())
+            }
+            Err(err) => {
+                log::error!("s3 File copy error: {:?}", err);
+                Err(anyhow::anyhow!(err))
+            }
+        }
+    }
 }
 
 async fn init_s3config() -> Option<Arc<SdkConfig>> {
@@ -21,6 +21,7 @@ use std::{fs, path::Path, sync::Arc};
 use tokio::{sync::Semaphore, task, time};
 
 use crate::common::file::scan_files;
+use crate::common::time::convert_time_column;
 use crate::common::utils::populate_file_meta;
 use crate::infra::config::{get_parquet_compression, CONFIG};
 use crate::infra::file_lock;
@@ -29,8 +30,12 @@ use crate::infra::storage::generate_partioned_file_key;
 use crate::infra::{cluster, config};
 use crate::meta::common::FileMeta;
 use crate::meta::StreamType;
-use crate::service::db;
-use crate::service::schema::schema_evolution;
+use crate::service::schema::{apply_nested_types, schema_evolution, set_time_column_type};
+use crate::service::stream::{
+    get_stream_setting_keep_nanos, get_stream_setting_partition_time_level,
+    get_stream_setting_timestamp_unit,
+};
+use crate::service::{bloom_filter, column_stats, db, fulltext_index};
 
 /// TaskResult (local file path, remote file key, file meta, stream type)
 type TaskResult = (String, String, FileMeta, StreamType);
@@ -179,14 +184,23 @@ async fn upload_file(
 
     let mut schema_reader = BufReader::new(&file);
     let inferred_schema = infer_json_schema(&mut schema_reader, None).unwrap();
+    let stream_schema = db::schema::get(org_id, stream_name, Some(stream_type)).await?;
+    let inferred_schema = apply_nested_types(inferred_schema, &stream_schema);
     let arrow_schema = Arc::new(inferred_schema.clone());
+    // the WAL holds the time column as Int64, the file gets the stream type
+    let inferred_schema = match get_stream_setting_timestamp_unit(&stream_schema) {
+        Some(unit) => set_time_column_type(inferred_schema, &unit.data_type()),
+        None => inferred_schema,
+    };
+    let file_schema = Arc::new(inferred_schema.clone());
+    let keep_nanos = get_stream_setting_keep_nanos(&stream_schema);
     drop(schema_reader);
 
     file.seek(SeekFrom::Start(0)).unwrap();
     let mut json_reader = BufReader::new(&file);
     let json = arrow::json::Reader::new(
         &mut json_reader,
-        arrow_schema.clone(),
+        arrow_schema,
         arrow::json::reader::DecoderOptions::new(),
     );
 
@@ -198,10 +212,10 @@ async fn upload_file(
         .set_max_row_group_size(1024 * 1024 * 256);
     let writer_props = props.build();
     let mut writer =
-        ArrowWriter::try_new(&mut buf_parquet, arrow_schema.clone(), Some(writer_props)).unwrap();
+        ArrowWriter::try_new(&mut buf_parquet, file_schema.clone(), Some(writer_props)).unwrap();
     let mut meta_batch = vec![];
     for batch in json {
-        let batch_write = batch.unwrap();
+        let batch_write = convert_time_column(batch.unwrap(), file_schema.clone(), keep_nanos)?;
         writer.write(&batch_write).expect("Writing batch");
         meta_batch.push(batch_write);
     }
@@ -214,9 +228,10 @@ async fn upload_file(
         records: 0,
         original_size: file_size,
         compressed_size: buf_parquet.len() as u64,
+        column_stats: vec![],
     };
 
-    populate_file_meta(arrow_schema.clone(), vec![meta_batch], &mut file_meta).await;
+    populate_file_meta(file_schema, vec![meta_batch.clone()], &mut file_meta).await;
 
     schema_evolution(
         org_id,
@@ -227,11 +242,14 @@ async fn upload_file(
     )
     .await;
 
+    let schema = db::schema::get(org_id, stream_name, Some(stream_type)).await?;
+    file_meta.column_stats = column_stats::build(&schema, &meta_batch);
     let new_file = generate_partioned_file_key(
         org_id,
         stream_name,
         stream_type,
         file_meta.min_ts,
+        get_stream_setting_partition_time_level(&schema),
         &CONFIG.common.file_ext_parquet,
     );
 
@@ -248,6 +266,12 @@ async fn upload_file(
     match result {
         Ok(_output) => {
             log::info!("[JOB] disk file upload success: {}", new_file_key);
+            if let Some(index) = fulltext_index::build(&schema, &meta_batch) {
+                fulltext_index::put(&new_file_key, &index).await;
+            }
+            if let Some(filters) = bloom_filter::build(&schema, &meta_batch) {
+                bloom_filter::put(&new_file_key, &filters).await;
+            }
             Ok((new_file_key, file_meta, stream_type))
         }
         Err(err) => {
@@ -22,10 +22,14 @@ use regex::Regex;
 
 mod alert_manager;
 mod compact;
+mod derived_metrics;
 mod file_list;
 mod files;
+mod geoip;
 mod prom;
+mod reindex;
 mod telemetry;
+mod volume;
 
 pub async fn init() -> Result<(), anyhow::Error> {
     let email_regex = Regex::new(
@@ -76,6 +80,9 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::spawn(async move { db::watch_prom_cluster_leader().await });
     tokio::task::spawn(async move { db::alerts::watch().await });
     tokio::task::spawn(async move { db::triggers::watch().await });
+    tokio::task::spawn(async move { db::lookup_tables::watch().await });
+    tokio::task::spawn(async move { db::stream_templates::watch().await });
+    tokio::task::spawn(async move { db::stream_aliases::watch().await });
     tokio::task::yield_now().await; // yield let other tasks run
     db::functions::cache().await?;
     db::user::cache().await?;
@@ -83,6 +90,9 @@ pub async fn init() -> Result<(), anyhow::Error> {
     db::cache_prom_cluster_leader().await?;
     db::alerts::cache().await?;
     db::triggers::cache().await?;
+    db::lookup_tables::cache().await?;
+    db::stream_templates::cache().await?;
+    db::stream_aliases::cache().await?;
 
     // cache file list
     db::file_list::local::cache().await?;
@@ -102,6 +112,10 @@ pub async fn init() -> Result<(), anyhow::Error> {
     tokio::task::spawn(async move { files::memory::run().await });
     tokio::task::spawn(async move { file_list::run().await });
     tokio::task::spawn(async move { prom::run().await });
+    tokio::task::spawn(async move { derived_metrics::run().await });
+    tokio::task::spawn(async move { geoip::run().await });
+    tokio::task::spawn(async move { volume::run().await });
+    tokio::task::spawn(async move { reindex::run().await });
 
     Ok(())
 }
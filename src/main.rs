@@ -39,6 +39,7 @@ use zincobserve::infra::cluster;
 use zincobserve::infra::config::CONFIG;
 use zincobserve::infra::file_lock;
 use zincobserve::meta::telemetry::Telemetry;
+use zincobserve::service::logs::quality::DQ_VIOLATIONS;
 use zincobserve::service::router;
 
 #[cfg(feature = "mimalloc")]
@@ -141,6 +142,10 @@ async fn main() -> Result<(), anyhow::Error> {
         .registry
         .register(Box::new(stats.clone()))
         .unwrap();
+    prometheus
+        .registry
+        .register(Box::new(DQ_VIOLATIONS.clone()))
+        .unwrap();
 
     // HTTP server
     let thread_id = Arc::new(AtomicU8::new(0));
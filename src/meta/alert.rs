@@ -31,7 +31,7 @@ pub struct Condition {
 }
 
 impl Evaluate for Condition {
-    fn evaluate(&self, row: Map<String, Value>) -> bool {
+    fn evaluate(&self, row: &Map<String, Value>) -> bool {
         if !row.contains_key(&self.column) {
             return false;
         };
@@ -217,7 +217,7 @@ pub struct TriggerTimer {
 }
 
 pub trait Evaluate {
-    fn evaluate(&self, row: Map<String, Value>) -> bool;
+    fn evaluate(&self, row: &Map<String, Value>) -> bool;
 }
 
 #[cfg(test)]
@@ -234,6 +234,6 @@ mod tests {
             is_numeric: None,
         };
         let row = serde_json::json!({"Country":"USA","occurance": 10});
-        condition.evaluate(row.as_object().unwrap().clone());
+        condition.evaluate(row.as_object().unwrap());
     }
 }
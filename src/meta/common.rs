@@ -16,20 +16,34 @@ use serde::{Deserialize, Serialize};
 
 use crate::common::json;
 
-#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct FileKey {
     pub key: String,
     pub meta: FileMeta,
     pub deleted: bool,
 }
 
-#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct FileMeta {
     pub min_ts: i64, // microseconds
     pub max_ts: i64, // microseconds
     pub records: u64,
     pub original_size: u64,
     pub compressed_size: u64,
+    /// stats of the stats fields of the stream when the file was written
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub column_stats: Vec<ColumnStats>,
+}
+
+/// Min/max of the values of a numeric column of a file, they are only set
+/// when not all the values are null, that is `null_count < records`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ColumnStats {
+    pub name: String,
+    pub min: f64,
+    pub max: f64,
+    pub null_count: u64,
 }
 
 impl From<&str> for FileMeta {
@@ -68,6 +82,12 @@ mod tests {
             records: 1000,
             original_size: 10000,
             compressed_size: 150,
+            column_stats: vec![ColumnStats {
+                name: "status".to_string(),
+                min: 200.0,
+                max: 503.0,
+                null_count: 10,
+            }],
         };
         let file_meta_str = json::to_string(&file_meta).unwrap();
         assert_eq!(FileMeta::try_from(file_meta_str.as_str()), Ok(file_meta));
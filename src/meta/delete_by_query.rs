@@ -0,0 +1,73 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::StreamType;
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeleteByQueryRequest {
+    /// microseconds, inclusive
+    pub start_time: i64,
+    /// microseconds, exclusive
+    pub end_time: i64,
+    /// SQL WHERE predicate of the records to delete
+    pub sql_where: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeleteByQueryStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeleteByQueryFile {
+    pub file: String,
+    /// the rewritten file, none when all the records were removed
+    pub new_file: Option<String>,
+    pub records: u64,
+    pub removed_records: u64,
+}
+
+/// Audit report of a delete by query job
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeleteByQueryReport {
+    pub id: String,
+    pub stream_name: String,
+    pub stream_type: StreamType,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub sql_where: String,
+    pub user: String,
+    pub status: DeleteByQueryStatus,
+    pub files_scanned: usize,
+    pub files_rewritten: usize,
+    pub files_deleted: usize,
+    pub removed_records: u64,
+    pub files: Vec<DeleteByQueryFile>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct DeleteByQueryList {
+    pub list: Vec<DeleteByQueryReport>,
+}
@@ -0,0 +1,35 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessorType {
+    /// country, city, location and ASN of an IP address from the GeoIP database
+    Geoip,
+    /// browser, OS and device of a user agent string
+    UserAgent,
+}
+
+/// Stream enrichment processor: parses the value of `field` and adds the
+/// result as `{prefix}{name}` fields.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct EnrichmentProcessor {
+    pub processor: ProcessorType,
+    pub field: String,
+    #[serde(default)]
+    pub prefix: String,
+}
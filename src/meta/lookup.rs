@@ -0,0 +1,129 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap;
+use datafusion::arrow::datatypes::Schema;
+use datafusion::arrow::json::reader::{infer_json_schema, DecoderOptions};
+use datafusion::arrow::json::Reader;
+use datafusion::arrow::record_batch::RecordBatch;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::io::BufReader;
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::common::json;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LookupTable {
+    pub name: String,
+    /// column used to match records at ingest
+    pub key: String,
+    pub columns: Vec<String>,
+    pub rows: usize,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct LookupTableList {
+    pub list: Vec<LookupTable>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct LookupTableParams {
+    pub key: String,
+    /// `csv` or `json`, detected from content type when omitted
+    pub format: Option<String>,
+}
+
+/// Stream enrichment rule: adds the columns of the lookup row whose key equals
+/// the value of `field`, named `{prefix}{column}`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct LookupEnrichment {
+    pub table: String,
+    pub field: String,
+    #[serde(default)]
+    pub prefix: String,
+}
+
+/// Lookup table loaded on a node
+#[derive(Clone, Debug)]
+pub struct LookupData {
+    pub table: LookupTable,
+    pub rows: Arc<AHashMap<String, Map<String, Value>>>,
+    pub schema: Arc<Schema>,
+    pub batches: Vec<RecordBatch>,
+}
+
+impl LookupData {
+    /// Builds the key index and the arrow batches from newline delimited json
+    pub fn try_new(table: LookupTable, data: &[u8]) -> Result<Self, anyhow::Error> {
+        let mut rows = AHashMap::new();
+        for line in data.split(|b| *b == b'\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let row: Map<String, Value> = json::from_slice(line)?;
+            let key = match row.get(&table.key) {
+                Some(Value::String(v)) => v.to_string(),
+                Some(Value::Null) | None => continue,
+                Some(v) => v.to_string(),
+            };
+            rows.insert(key, row);
+        }
+
+        let mut schema_reader = BufReader::new(data);
+        let schema = Arc::new(infer_json_schema(&mut schema_reader, None)?);
+        let reader = Reader::new(BufReader::new(data), schema.clone(), DecoderOptions::new());
+        let mut batches = vec![];
+        for batch in reader {
+            batches.push(batch?);
+        }
+
+        Ok(LookupData {
+            table,
+            rows: Arc::new(rows),
+            schema,
+            batches,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_lookup_data() {
+        let table = LookupTable {
+            name: "tiers".to_string(),
+            key: "customer_id".to_string(),
+            columns: vec!["customer_id".to_string(), "tier".to_string()],
+            rows: 2,
+            updated_at: 0,
+        };
+        let data =
+            b"{\"customer_id\":1,\"tier\":\"gold\"}\n{\"customer_id\":2,\"tier\":\"free\"}\n";
+        let lookup = LookupData::try_new(table, data).unwrap();
+        assert_eq!(lookup.rows.len(), 2);
+        assert_eq!(
+            lookup.rows.get("1").unwrap().get("tier").unwrap().as_str(),
+            Some("gold")
+        );
+        assert_eq!(
+            lookup.batches.iter().map(|b| b.num_rows()).sum::<usize>(),
+            2
+        );
+    }
+}
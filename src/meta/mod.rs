@@ -19,16 +19,24 @@ use utoipa::ToSchema;
 pub mod alert;
 pub mod common;
 pub mod dashboards;
+pub mod delete_by_query;
+pub mod enrichment;
 pub mod functions;
 pub mod http;
 pub mod ingestion;
+pub mod lookup;
 pub mod organization;
 pub mod prom;
+pub mod quality;
+pub mod reindex;
+pub mod sampling;
+pub mod schema;
 pub mod search;
 pub mod service;
 pub mod sql;
 pub mod stream;
 pub mod telemetry;
+pub mod template;
 pub mod traces;
 pub mod user;
 
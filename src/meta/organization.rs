@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use super::{alert::Alert, functions::Transform, stream::Stream};
 
@@ -25,6 +26,14 @@ pub struct OrgSummary {
     pub alerts: Vec<Alert>,
 }
 
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct OrganizationSettings {
+    /// default retention period in days of the org streams, 0 falls back to
+    /// the global default
+    #[serde(default)]
+    pub data_retention: i64,
+}
+
 #[derive(Serialize)]
 pub struct IngestionPasscode {
     pub passcode: String,
@@ -15,6 +15,9 @@
 use ahash::AHashMap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::alert::Condition;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Metric {
@@ -51,6 +54,36 @@ impl Metric {
     }
 }
 
+/// Metric derived from a logs stream at ingest, written periodically to the
+/// metrics stream of the same name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DerivedMetric {
+    pub name: String,
+    pub metric_type: DerivedMetricType,
+    /// all conditions must match, an empty list matches every record
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// numeric field for `sum` and `histogram`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub field: Option<String>,
+    /// record fields used as metric labels
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// histogram bucket upper bounds, prometheus defaults when empty
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub buckets: Vec<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DerivedMetricType {
+    Counter,
+    Sum,
+    Histogram,
+}
+
 #[cfg(test)]
 mod test {
 
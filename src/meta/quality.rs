@@ -0,0 +1,108 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+pub const DQ_VIOLATIONS_FIELD: &str = "_dq_violations";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct QualityRule {
+    pub name: String,
+    pub field: String,
+    pub rule_type: QualityRuleType,
+    /// allowed values for `enum` rules
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<String>,
+    /// regular expression for `regex` rules
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// expected value type for `type` rules
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value_type: Option<QualityValueType>,
+    #[serde(default)]
+    pub action: QualityAction,
+    /// target stream for `route` action
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub route_to: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityRuleType {
+    Required,
+    Enum,
+    Regex,
+    Range,
+    Type,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityValueType {
+    String,
+    Number,
+    Integer,
+    Boolean,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum QualityAction {
+    Reject,
+    Tag,
+    Route,
+}
+
+impl Default for QualityAction {
+    fn default() -> Self {
+        Self::Reject
+    }
+}
+
+impl std::fmt::Display for QualityAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            QualityAction::Reject => write!(f, "reject"),
+            QualityAction::Tag => write!(f, "tag"),
+            QualityAction::Route => write!(f, "route"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_quality_rule() {
+        let rule: QualityRule = serde_json::from_str(
+            r#"{"name":"level_enum","field":"level","rule_type":"enum","values":["info","error"]}"#,
+        )
+        .unwrap();
+        assert_eq!(rule.rule_type, QualityRuleType::Enum);
+        assert_eq!(rule.action, QualityAction::Reject);
+        assert_eq!(rule.values.len(), 2);
+    }
+}
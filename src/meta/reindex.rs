@@ -0,0 +1,93 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReindexRequest {
+    /// microseconds, inclusive
+    pub start_time: i64,
+    /// microseconds, exclusive
+    pub end_time: i64,
+    /// stream receiving the records, the stream itself when empty
+    #[serde(default)]
+    pub target_stream: String,
+    /// max records replayed per second, 0 means no limit
+    #[serde(default)]
+    pub max_records_per_second: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReindexStatus {
+    Pending,
+    Running,
+    Cancelling,
+    Cancelled,
+    Completed,
+    Failed,
+}
+
+impl ReindexStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            ReindexStatus::Cancelled | ReindexStatus::Completed | ReindexStatus::Failed
+        )
+    }
+}
+
+/// Reindex job, the progress is saved during each file so the job resumes
+/// from it after a restart
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReindexJob {
+    pub id: String,
+    pub stream_name: String,
+    pub target_stream: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub max_records_per_second: u64,
+    pub user: String,
+    pub status: ReindexStatus,
+    /// the node running the job
+    #[serde(default)]
+    pub node: String,
+    /// files overlapping the time range when the job started
+    pub files: Vec<String>,
+    /// number of files of the list already replayed
+    pub files_done: usize,
+    /// records of the file in progress up to this time, in microseconds, are
+    /// replayed, 0 when none
+    #[serde(default)]
+    pub file_checkpoint: i64,
+    pub records: u64,
+    pub failed_records: u64,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl ReindexJob {
+    pub fn in_place(&self) -> bool {
+        self.target_stream == self.stream_name
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct ReindexJobList {
+    pub list: Vec<ReindexJob>,
+}
@@ -0,0 +1,64 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::alert::Condition;
+
+pub const SAMPLE_RATE_FIELD: &str = "_sample_rate";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SamplingRule {
+    pub name: String,
+    /// all conditions must match, an empty list matches every record
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    pub action: SamplingAction,
+    /// keep 1 of every `rate` records for `one_in_n`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate: Option<u64>,
+    /// field used to pick records deterministically for `one_in_n`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hash_key: Option<String>,
+    /// chance to keep a record for `probability`, in (0, 1]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub probability: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SamplingAction {
+    Drop,
+    OneInN,
+    Probability,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_sampling_rule() {
+        let rule: SamplingRule = serde_json::from_str(
+            r#"{"name":"debug","conditions":[{"column":"level","operator":"=","value":"debug"}],"action":"one_in_n","rate":10,"hash_key":"trace_id"}"#,
+        )
+        .unwrap();
+        assert_eq!(rule.action, SamplingAction::OneInN);
+        assert_eq!(rule.rate, Some(10));
+    }
+}
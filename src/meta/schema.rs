@@ -0,0 +1,182 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datafusion::arrow::datatypes::{DataType, Field};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use utoipa::ToSchema;
+
+use super::stream::StreamProperty;
+use super::StreamType;
+
+/// How records are checked against the defined schema of a stream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaMode {
+    /// fields not in the defined schema are inferred as before
+    #[default]
+    Additive,
+    /// records with fields not in the defined schema are rejected
+    Strict,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    Boolean,
+    Int64,
+    Uint64,
+    Float64,
+    Utf8,
+}
+
+impl FieldType {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            FieldType::Boolean => DataType::Boolean,
+            FieldType::Int64 => DataType::Int64,
+            FieldType::Uint64 => DataType::UInt64,
+            FieldType::Float64 => DataType::Float64,
+            FieldType::Utf8 => DataType::Utf8,
+        }
+    }
+}
+
+impl fmt::Display for FieldType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldType::Boolean => write!(f, "boolean"),
+            FieldType::Int64 => write!(f, "int64"),
+            FieldType::Uint64 => write!(f, "uint64"),
+            FieldType::Float64 => write!(f, "float64"),
+            FieldType::Utf8 => write!(f, "utf8"),
+        }
+    }
+}
+
+/// Field declared up front, ingested values are coerced to its type
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct DefinedField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+}
+
+/// Field kept as a native nested column instead of being flattened
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct NestedField {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: NestedFieldType,
+}
+
+/// Field computed from a SQL expression at query time, eg:
+/// `route` as `split_part(path, '/', 2)`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct VirtualField {
+    pub name: String,
+    pub expr: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum NestedFieldType {
+    /// objects with a stable set of keys, eg: kubernetes
+    Struct,
+    /// arrays, eg: tags
+    List,
+    /// objects with arbitrary keys, the values are kept as strings, eg: labels
+    Map,
+}
+
+impl NestedFieldType {
+    /// The fixed type of the field, struct and list types are inferred
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            NestedFieldType::Map => Some(DataType::Map(
+                Box::new(Field::new(
+                    "entries",
+                    DataType::Struct(vec![
+                        Field::new("keys", DataType::Utf8, false),
+                        Field::new("values", DataType::Utf8, true),
+                    ]),
+                    false,
+                )),
+                false,
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Schema version of a stream, valid for records from `start_dt` until
+/// `end_dt`, the latest version has no end
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SchemaVersion {
+    pub version: usize,
+    pub start_dt: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_dt: Option<i64>,
+    pub fields_count: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub fields: Vec<StreamProperty>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SchemaVersionList {
+    pub list: Vec<SchemaVersion>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct SchemaFieldChange {
+    pub name: String,
+    pub from_type: String,
+    pub to_type: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SchemaDiff {
+    pub from: usize,
+    pub to: usize,
+    pub added: Vec<StreamProperty>,
+    pub removed: Vec<StreamProperty>,
+    pub changed: Vec<SchemaFieldChange>,
+}
+
+/// Sent when a new schema version of a stream adds or widens fields
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct SchemaChangeEvent {
+    pub org_id: String,
+    pub stream_name: String,
+    pub stream_type: StreamType,
+    pub timestamp: i64,
+    pub added: Vec<StreamProperty>,
+    pub changed: Vec<SchemaFieldChange>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_defined_field() {
+        let field: DefinedField =
+            serde_json::from_str(r#"{"name":"status_code","type":"int64"}"#).unwrap();
+        assert_eq!(field.field_type, FieldType::Int64);
+        assert_eq!(field.field_type.data_type(), DataType::Int64);
+        assert_eq!(field.field_type.to_string(), "int64");
+        assert_eq!(SchemaMode::default(), SchemaMode::Additive);
+    }
+}
@@ -20,7 +20,7 @@ use sqlparser::{
     ast::{
         BinaryOperator, Expr as SqlExpr, Function, FunctionArg, FunctionArgExpr,
         Offset as SqlOffset, OrderByExpr, Select, SelectItem, SetExpr, Statement, TableFactor,
-        TableWithJoins, Value,
+        TableWithJoins, UnaryOperator, Value,
     },
     parser::Parser,
 };
@@ -33,10 +33,12 @@ pub struct Sql {
     pub(crate) fields: Vec<String>,           // projection, select, fields
     pub(crate) selection: Option<SqlExpr>,    // where
     pub(crate) source: String,                // table
+    pub(crate) joins: Vec<String>,            // joined lookup tables
     pub(crate) order_by: Vec<(String, bool)>, // desc: true / false
     pub(crate) offset: usize,
     pub(crate) limit: usize,
     pub(crate) quick_text: Vec<(String, String, SqlOperator)>, // use text line quick filter
+    pub(crate) equal_items: Vec<(String, String)>, // field = value, empty when any OR is used
     pub(crate) full_text: Vec<(String, SqlOperator)>, // fulltext: value1 and value2, and: true / false
     pub(crate) time_range: Option<(i64, i64)>,        // time range: min, max
 }
@@ -62,9 +64,11 @@ pub enum SqlValue {
 
 pub struct Projection<'a>(pub(crate) &'a Vec<SelectItem>);
 pub struct Quicktext<'a>(pub(crate) &'a Option<SqlExpr>);
+pub struct Equalitems<'a>(pub(crate) &'a Option<SqlExpr>);
 pub struct Fulltext<'a>(pub(crate) &'a Option<SqlExpr>);
 pub struct Timerange<'a>(pub(crate) &'a Option<SqlExpr>);
 pub struct Source<'a>(pub(crate) &'a [TableWithJoins]);
+pub struct Joins<'a>(pub(crate) &'a [TableWithJoins]);
 pub struct Order<'a>(pub(crate) &'a OrderByExpr);
 pub struct Offset<'a>(pub(crate) &'a SqlOffset);
 pub struct Limit<'a>(pub(crate) &'a SqlExpr);
@@ -112,6 +116,7 @@ impl TryFrom<&Statement> for Sql {
                 };
 
                 let source = Source(table_with_joins).try_into()?;
+                let joins = Joins(table_with_joins).into();
 
                 let mut order_by = Vec::new();
                 for expr in orders {
@@ -126,6 +131,7 @@ impl TryFrom<&Statement> for Sql {
 
                 let quick_text: Vec<(String, String, SqlOperator)> =
                     Quicktext(&selection).try_into()?;
+                let equal_items: Vec<(String, String)> = Equalitems(&selection).try_into()?;
                 let full_text: Vec<(String, SqlOperator)> = Fulltext(&selection).try_into()?;
                 let time_range: Option<(i64, i64)> = Timerange(&selection).try_into()?;
 
@@ -133,10 +139,12 @@ impl TryFrom<&Statement> for Sql {
                     fields,
                     selection,
                     source,
+                    joins,
                     order_by,
                     offset,
                     limit,
                     quick_text,
+                    equal_items,
                     full_text,
                     time_range,
                 })
@@ -220,6 +228,57 @@ impl<'a> TryFrom<Quicktext<'a>> for Vec<(String, String, SqlOperator)> {
     }
 }
 
+impl<'a> TryFrom<Equalitems<'a>> for Vec<(String, String)> {
+    type Error = anyhow::Error;
+
+    fn try_from(selection: Equalitems<'a>) -> Result<Self, Self::Error> {
+        let mut fields = Vec::new();
+        if let Some(expr) = selection.0 {
+            if !parse_expr_for_equal_items(expr, &mut fields) {
+                return Ok(vec![]);
+            }
+        }
+        Ok(fields)
+    }
+}
+
+/// Collects the `field = value` items joined by AND, returns false when any
+/// OR or NOT is used since the items then don't hold for every row.
+fn parse_expr_for_equal_items(expr: &SqlExpr, fields: &mut Vec<(String, String)>) -> bool {
+    match expr {
+        SqlExpr::Nested(e) => parse_expr_for_equal_items(e, fields),
+        SqlExpr::UnaryOp {
+            op: UnaryOperator::Not,
+            ..
+        } => false,
+        SqlExpr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => parse_expr_for_equal_items(left, fields) && parse_expr_for_equal_items(right, fields),
+        SqlExpr::BinaryOp {
+            op: BinaryOperator::Or,
+            ..
+        } => false,
+        SqlExpr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } => {
+            if let (SqlExpr::Identifier(ident), SqlExpr::Value(value)) = (&**left, &**right) {
+                let value = match value {
+                    Value::SingleQuotedString(s) | Value::DoubleQuotedString(s) => s.to_string(),
+                    Value::Number(s, _) => s.to_string(),
+                    _ => return true,
+                };
+                fields.push((ident.value.to_string(), value));
+            }
+            true
+        }
+        _ => true,
+    }
+}
+
 impl<'a> TryFrom<Timerange<'a>> for Option<(i64, i64)> {
     type Error = anyhow::Error;
 
@@ -291,8 +350,11 @@ impl<'a> TryFrom<Source<'a>> for String {
         }
 
         let table = &source.0[0];
-        if !table.joins.is_empty() {
-            return Err(anyhow!("We do not support joint data source at the moment"));
+        // joins are only allowed against lookup tables
+        for join in table.joins.iter() {
+            if !matches!(join.relation, TableFactor::Table { .. }) {
+                return Err(anyhow!("We only support join with lookup table"));
+            }
         }
 
         match &table.relation {
@@ -302,6 +364,20 @@ impl<'a> TryFrom<Source<'a>> for String {
     }
 }
 
+impl<'a> From<Joins<'a>> for Vec<String> {
+    fn from(joins: Joins<'a>) -> Self {
+        joins
+            .0
+            .iter()
+            .flat_map(|table| table.joins.iter())
+            .filter_map(|join| match &join.relation {
+                TableFactor::Table { name, .. } => name.0.first().map(|v| v.value.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
 impl<'a> TryFrom<Order<'a>> for (String, bool) {
     type Error = anyhow::Error;
 
@@ -847,6 +923,23 @@ mod tests {
         assert_eq!(local_sql.offset, 10);
         assert_eq!(local_sql.order_by, vec![("c".into(), true)]);
         assert_eq!(local_sql.fields, vec!["a", "b", "c"]);
+        assert!(local_sql.equal_items.is_empty());
+    }
+
+    #[test]
+    fn test_parse_equal_items() {
+        let sql = "select * from tbl where a='x' and b like '%y%' and c=1";
+        let local_sql: Sql = Sql::new(sql).unwrap();
+        assert_eq!(
+            local_sql.equal_items,
+            vec![
+                ("a".to_string(), "x".to_string()),
+                ("c".to_string(), "1".to_string())
+            ]
+        );
+        let sql = "select * from tbl where a='x' and not (c=1 and d=2)";
+        let local_sql: Sql = Sql::new(sql).unwrap();
+        assert!(local_sql.equal_items.is_empty());
     }
 
     #[test]
@@ -12,14 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use datafusion::arrow::datatypes::Schema;
+use chrono::Duration;
+use datafusion::arrow::datatypes::{DataType, Schema, TimeUnit};
 use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
 use std::collections::HashMap;
 use utoipa::ToSchema;
 
+use super::enrichment::EnrichmentProcessor;
+use super::lookup::LookupEnrichment;
+use super::prom::DerivedMetric;
+use super::quality::QualityRule;
+use super::sampling::SamplingRule;
+use super::schema::{DefinedField, NestedField, SchemaMode, VirtualField};
 use super::StreamType;
 use crate::common::json;
 
+/// the longest retention period in days, 100 years
+pub const MAX_DATA_RETENTION_DAYS: i64 = 36500;
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 pub struct Stream {
     pub name: String,
@@ -54,6 +64,90 @@ pub struct StreamStats {
     pub compressed_size: f64,
 }
 
+/// Records and bytes ingested into a stream during one hour or day
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StreamVolume {
+    pub timestamp: i64, // microseconds, start of the hour or day
+    pub records: u64,
+    pub files: u64,
+    pub original_size: u64,
+    pub compressed_size: u64,
+}
+
+impl StreamVolume {
+    pub fn add(&mut self, other: &StreamVolume) {
+        self.records += other.records;
+        self.files += other.files;
+        self.original_size += other.original_size;
+        self.compressed_size += other.compressed_size;
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamVolumeHistory {
+    pub stream_name: String,
+    pub stream_type: StreamType,
+    /// `hour` or `day`
+    pub interval: String,
+    pub list: Vec<StreamVolume>,
+}
+
+/// A name resolved to one or more streams at query time
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct StreamAlias {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub stream_type: StreamType,
+    pub streams: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamAliasList {
+    pub list: Vec<StreamAlias>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamRenameRequest {
+    pub new_name: String,
+    /// keep the old name as an alias of the new stream
+    #[serde(default = "default_keep_alias")]
+    pub keep_alias: bool,
+}
+
+fn default_keep_alias() -> bool {
+    true
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamCloneRequest {
+    pub new_name: String,
+    /// microseconds, 0 means from the beginning
+    #[serde(default)]
+    pub start_time: i64,
+    /// microseconds, 0 means up to now
+    #[serde(default)]
+    pub end_time: i64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, ToSchema)]
+pub struct StreamCopyReport {
+    pub stream_name: String,
+    pub new_name: String,
+    pub files: usize,
+    pub records: u64,
+}
+
+/// Journal of a rename in progress, an interrupted rename is resumed by
+/// repeating the request
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamRename {
+    pub stream_name: String,
+    pub stream_type: StreamType,
+    pub new_name: String,
+    pub created_at: i64,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct StreamSchema {
     pub stream_name: String,
@@ -61,14 +155,73 @@ pub struct StreamSchema {
     pub schema: Schema,
 }
 
-#[derive(Clone, Debug, Deserialize, ToSchema)]
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
 pub struct StreamSettings {
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub partition_keys: Vec<String>,
+    /// partition keys hashed into a fixed number of buckets, eg: customer_id -> 16
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    #[serde(default)]
+    pub partition_buckets: HashMap<String, u64>,
+    #[serde(default)]
+    pub partition_time_level: PartitionTimeLevel,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     #[serde(default)]
     pub full_text_search_keys: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub data_quality_rules: Vec<QualityRule>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub sampling_rules: Vec<SamplingRule>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub derived_metrics: Vec<DerivedMetric>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub enrichments: Vec<LookupEnrichment>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub processors: Vec<EnrichmentProcessor>,
+    /// retention period in days, 0 falls back to the org and global default
+    #[serde(default)]
+    pub data_retention: i64,
+    /// fields with declared types, values are coerced to them on ingestion
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub defined_schema: Vec<DefinedField>,
+    #[serde(default)]
+    pub schema_mode: SchemaMode,
+    /// stores the time column as a native timestamp of this unit, Int64
+    /// microseconds when not set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub timestamp_unit: Option<TimestampUnit>,
+    /// top level fields stored as native struct, list or map columns
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub nested_fields: Vec<NestedField>,
+    /// fields computed from SQL expressions when the stream is queried
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub virtual_fields: Vec<VirtualField>,
+    /// report new fields and widened types of the stream
+    #[serde(default)]
+    pub notify_schema_changes: bool,
+    /// keep a token index of the full text search fields of each file
+    #[serde(default)]
+    pub fulltext_index: bool,
+    /// text fields looked up by exact value, eg: trace_id, a bloom filter of
+    /// their values is kept for each file
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub bloom_filter_fields: Vec<String>,
+    /// numeric fields compared by range, eg: status, their min/max of each
+    /// file is kept in the file list
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub stats_fields: Vec<String>,
 }
 
 impl Serialize for StreamSettings {
@@ -76,17 +229,175 @@ impl Serialize for StreamSettings {
     where
         S: Serializer,
     {
-        let mut state = serializer.serialize_struct("StreamSettings", 2)?;
+        let mut state = serializer.serialize_struct("StreamSettings", 19)?;
         let mut part_keys = HashMap::new();
         for (index, key) in self.partition_keys.iter().enumerate() {
             part_keys.insert(format!("L{}", index), key.to_string());
         }
         state.serialize_field("partition_keys", &part_keys)?;
+        if !self.partition_buckets.is_empty() {
+            state.serialize_field("partition_buckets", &self.partition_buckets)?;
+        } else {
+            state.skip_field("partition_buckets")?;
+        }
+        if self.partition_time_level != PartitionTimeLevel::default() {
+            state.serialize_field("partition_time_level", &self.partition_time_level)?;
+        } else {
+            state.skip_field("partition_time_level")?;
+        }
         state.serialize_field("full_text_search_keys", &self.full_text_search_keys)?;
+        if !self.data_quality_rules.is_empty() {
+            state.serialize_field("data_quality_rules", &self.data_quality_rules)?;
+        } else {
+            state.skip_field("data_quality_rules")?;
+        }
+        if !self.sampling_rules.is_empty() {
+            state.serialize_field("sampling_rules", &self.sampling_rules)?;
+        } else {
+            state.skip_field("sampling_rules")?;
+        }
+        if !self.derived_metrics.is_empty() {
+            state.serialize_field("derived_metrics", &self.derived_metrics)?;
+        } else {
+            state.skip_field("derived_metrics")?;
+        }
+        if !self.enrichments.is_empty() {
+            state.serialize_field("enrichments", &self.enrichments)?;
+        } else {
+            state.skip_field("enrichments")?;
+        }
+        if !self.processors.is_empty() {
+            state.serialize_field("processors", &self.processors)?;
+        } else {
+            state.skip_field("processors")?;
+        }
+        if self.data_retention > 0 {
+            state.serialize_field("data_retention", &self.data_retention)?;
+        } else {
+            state.skip_field("data_retention")?;
+        }
+        if !self.defined_schema.is_empty() {
+            state.serialize_field("defined_schema", &self.defined_schema)?;
+        } else {
+            state.skip_field("defined_schema")?;
+        }
+        if self.schema_mode != SchemaMode::default() {
+            state.serialize_field("schema_mode", &self.schema_mode)?;
+        } else {
+            state.skip_field("schema_mode")?;
+        }
+        match self.timestamp_unit {
+            Some(unit) => state.serialize_field("timestamp_unit", &unit)?,
+            None => state.skip_field("timestamp_unit")?,
+        }
+        if !self.nested_fields.is_empty() {
+            state.serialize_field("nested_fields", &self.nested_fields)?;
+        } else {
+            state.skip_field("nested_fields")?;
+        }
+        if !self.virtual_fields.is_empty() {
+            state.serialize_field("virtual_fields", &self.virtual_fields)?;
+        } else {
+            state.skip_field("virtual_fields")?;
+        }
+        if self.notify_schema_changes {
+            state.serialize_field("notify_schema_changes", &self.notify_schema_changes)?;
+        } else {
+            state.skip_field("notify_schema_changes")?;
+        }
+        if self.fulltext_index {
+            state.serialize_field("fulltext_index", &self.fulltext_index)?;
+        } else {
+            state.skip_field("fulltext_index")?;
+        }
+        if !self.bloom_filter_fields.is_empty() {
+            state.serialize_field("bloom_filter_fields", &self.bloom_filter_fields)?;
+        } else {
+            state.skip_field("bloom_filter_fields")?;
+        }
+        if !self.stats_fields.is_empty() {
+            state.serialize_field("stats_fields", &self.stats_fields)?;
+        } else {
+            state.skip_field("stats_fields")?;
+        }
         state.end()
     }
 }
 
+/// Unit of the native timestamp type of the time column
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampUnit {
+    Second,
+    Millisecond,
+    Microsecond,
+    Nanosecond,
+}
+
+impl TimestampUnit {
+    pub fn data_type(&self) -> DataType {
+        let unit = match self {
+            TimestampUnit::Second => TimeUnit::Second,
+            TimestampUnit::Millisecond => TimeUnit::Millisecond,
+            TimestampUnit::Microsecond => TimeUnit::Microsecond,
+            TimestampUnit::Nanosecond => TimeUnit::Nanosecond,
+        };
+        DataType::Timestamp(unit, None)
+    }
+}
+
+/// Time granularity of the WAL files and the storage keys of a stream
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PartitionTimeLevel {
+    Minute,
+    #[default]
+    Hour,
+    Day,
+}
+
+impl PartitionTimeLevel {
+    pub fn wal_key_format(&self) -> &'static str {
+        match self {
+            PartitionTimeLevel::Minute => "%Y_%m_%d_%H_%M",
+            PartitionTimeLevel::Hour => "%Y_%m_%d_%H",
+            PartitionTimeLevel::Day => "%Y_%m_%d",
+        }
+    }
+
+    /// the storage key always keeps the hour column, the file list is
+    /// organized by hour
+    pub fn storage_key_format(&self) -> &'static str {
+        match self {
+            PartitionTimeLevel::Minute => "%Y/%m/%d/%H/%M",
+            PartitionTimeLevel::Hour => "%Y/%m/%d/%H",
+            PartitionTimeLevel::Day => "%Y/%m/%d/00",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            PartitionTimeLevel::Minute => Duration::minutes(1),
+            PartitionTimeLevel::Hour => Duration::hours(1),
+            PartitionTimeLevel::Day => Duration::days(1),
+        }
+    }
+
+    /// the partitions compacted at once, minutes are compacted by hour
+    pub fn compact_level(&self) -> PartitionTimeLevel {
+        match self {
+            PartitionTimeLevel::Minute => PartitionTimeLevel::Hour,
+            level => *level,
+        }
+    }
+
+    /// start of the partition the timestamp falls into
+    pub fn truncate(&self, timestamp: i64) -> i64 {
+        let duration = self.duration().num_microseconds().unwrap();
+        timestamp - timestamp.rem_euclid(duration)
+    }
+}
+
 impl Default for StreamStats {
     fn default() -> Self {
         Self {
@@ -123,6 +434,80 @@ pub struct ListStream {
     pub list: Vec<Stream>,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StreamDeletionStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Background job removing the data files of a deleted stream from storage
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamDeletion {
+    pub stream_name: String,
+    pub stream_type: StreamType,
+    pub status: StreamDeletionStatus,
+    pub files: usize,
+    pub deleted_files: usize,
+    pub failed_files: usize,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FieldStatsRequest {
+    pub start_time: i64,
+    pub end_time: i64,
+    /// number of most frequent values to return
+    #[serde(default = "default_top_values")]
+    pub top: usize,
+    /// share of the files to scan, between 0 and 1
+    #[serde(default = "default_sample_ratio")]
+    pub sample_ratio: f64,
+}
+
+fn default_top_values() -> usize {
+    10
+}
+
+fn default_sample_ratio() -> f64 {
+    1.0
+}
+
+/// Statistics of a field over a time range, computed from the data files
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct FieldStats {
+    pub field: String,
+    #[serde(rename = "type")]
+    pub field_type: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    /// files scanned, after sampling
+    pub files: usize,
+    /// share of the files scanned, counts are not extrapolated
+    pub sample_ratio: f64,
+    pub count: u64,
+    pub null_count: u64,
+    pub null_ratio: f64,
+    /// approximate number of distinct values
+    pub distinct_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub min: Option<json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<Object>)]
+    pub max: Option<json::Value>,
+    pub top_values: Vec<FieldValueCount>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct FieldValueCount {
+    #[schema(value_type = Object)]
+    pub value: json::Value,
+    pub count: u64,
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -134,4 +519,17 @@ mod test {
         let stats_frm_str = StreamStats::from(stats_str.as_str());
         assert_eq!(stats, stats_frm_str);
     }
+
+    #[test]
+    fn test_partition_time_level() {
+        // 2022-10-03T10:20:30Z
+        let ts = 1664792430000000;
+        assert_eq!(PartitionTimeLevel::Minute.truncate(ts), 1664792400000000);
+        assert_eq!(PartitionTimeLevel::Hour.truncate(ts), 1664791200000000);
+        assert_eq!(PartitionTimeLevel::Day.truncate(ts), 1664755200000000);
+        assert_eq!(
+            PartitionTimeLevel::Minute.compact_level(),
+            PartitionTimeLevel::Hour
+        );
+    }
 }
@@ -0,0 +1,58 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::functions::Transform;
+use super::stream::StreamSettings;
+use super::StreamType;
+
+/// Settings applied to new streams whose name matches one of the patterns
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamTemplate {
+    #[serde(default)]
+    pub name: String,
+    /// glob patterns, `*` matches any characters and `?` a single one
+    pub patterns: Vec<String>,
+    /// the matching template with the highest priority wins
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default)]
+    pub stream_type: StreamType,
+    #[serde(default)]
+    pub settings: StreamSettings,
+    /// functions registered for new logs streams
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub functions: Vec<Transform>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamTemplateList {
+    pub list: Vec<StreamTemplate>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StreamTemplateMatchParams {
+    pub stream_name: String,
+    #[serde(rename = "type")]
+    pub stream_type: Option<StreamType>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+pub struct StreamTemplateMatch {
+    pub stream_name: String,
+    pub template: Option<StreamTemplate>,
+}
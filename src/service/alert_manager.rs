@@ -102,7 +102,7 @@ pub async fn handle_trigger(alert_name: &str, alert: Alert) {
                     Ok(res) => {
                         if !res.hits.is_empty() {
                             let record = res.hits.first().unwrap().as_object().unwrap();
-                            if alert.condition.clone().evaluate(record.clone()) {
+                            if alert.condition.evaluate(record) {
                                 let curr_ts = Utc::now().timestamp_micros();
                                 let mut local_trigger = trigger.clone();
 
@@ -0,0 +1,298 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::{AHashMap, AHashSet};
+use bytes::Bytes;
+use datafusion::arrow::array::{Array, StringArray};
+use datafusion::arrow::datatypes::Schema;
+use datafusion::arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{BinaryOperator, Expr as SqlExpr, Value as SqlValue};
+
+use crate::common::json;
+use crate::common::sql::{conjuncts, field_name};
+use crate::common::str::hash64;
+use crate::service::search::sql::Sql;
+use crate::service::sidecar;
+use crate::service::stream::get_stream_setting_bloom_filter_fields;
+
+/// bits per value for a false positive rate of about 1%
+const BITS_PER_VALUE: f64 = 9.6;
+const NUM_HASHES: u32 = 7;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BloomFilter {
+    num_hashes: u32,
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    pub fn with_capacity(values: usize) -> Self {
+        let num_bits = ((values.max(1) as f64) * BITS_PER_VALUE).ceil() as usize;
+        BloomFilter {
+            num_hashes: NUM_HASHES,
+            bits: vec![0; (num_bits + 63) / 64],
+        }
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        for pos in self.positions(value) {
+            self.bits[pos / 64] |= 1 << (pos % 64);
+        }
+    }
+
+    pub fn contains(&self, value: &str) -> bool {
+        self.positions(value)
+            .into_iter()
+            .all(|pos| self.bits[pos / 64] & (1 << (pos % 64)) != 0)
+    }
+
+    /// double hashing over the stable hash of the value
+    fn positions(&self, value: &str) -> Vec<usize> {
+        let num_bits = (self.bits.len() * 64) as u64;
+        let h1 = hash64(value);
+        let h2 = splitmix64(h1) | 1;
+        (0..self.num_hashes as u64)
+            .map(|i| (h1.wrapping_add(i.wrapping_mul(h2)) % num_bits) as usize)
+            .collect()
+    }
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e3779b97f4a7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d049bb133111eb);
+    x ^ (x >> 31)
+}
+
+/// Bloom filters of the bloom filter fields of a parquet file, stored next
+/// to the file as `<file>.bloom`
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileBloomFilters {
+    pub fields: AHashMap<String, BloomFilter>,
+}
+
+impl FileBloomFilters {
+    /// Whether the file may hold a record matching all the items, a file is
+    /// ruled out when its filter of a field contains none of the values
+    pub fn may_contain(&self, items: &[(String, Vec<String>)]) -> bool {
+        items
+            .iter()
+            .all(|(field, values)| match self.fields.get(field) {
+                Some(filter) => values.iter().any(|value| filter.contains(value)),
+                // the field was not a bloom filter field or not text in this file
+                None => true,
+            })
+    }
+}
+
+pub fn bloom_filter_key(file: &str) -> String {
+    format!("{}.bloom", file)
+}
+
+/// Builds the bloom filters of the records of a file, None when the stream
+/// has no bloom filter fields
+pub fn build(schema: &Schema, batches: &[RecordBatch]) -> Option<FileBloomFilters> {
+    let fields = get_stream_setting_bloom_filter_fields(schema);
+    if fields.is_empty() {
+        return None;
+    }
+    let mut filters = FileBloomFilters::default();
+    for field in fields {
+        let mut values = AHashSet::new();
+        let mut covered = true;
+        for batch in batches {
+            let column = match batch.column_by_name(&field) {
+                Some(column) => column,
+                None => continue,
+            };
+            // only text values are compared as they are written in SQL
+            match column.as_any().downcast_ref::<StringArray>() {
+                Some(column) => values.extend(column.iter().flatten()),
+                None => {
+                    covered = false;
+                    break;
+                }
+            }
+        }
+        if !covered {
+            continue;
+        }
+        // a field without values gets an empty filter, it rules out any value
+        let mut filter = BloomFilter::with_capacity(values.len());
+        for value in values {
+            filter.insert(value);
+        }
+        filters.fields.insert(field, filter);
+    }
+    Some(filters)
+}
+
+/// Builds the bloom filters of a parquet file, eg: of a compacted file
+pub fn build_from_parquet(schema: &Schema, data: Bytes) -> Option<FileBloomFilters> {
+    let fields = get_stream_setting_bloom_filter_fields(schema);
+    if fields.is_empty() {
+        return None;
+    }
+    let builder = ParquetRecordBatchReaderBuilder::try_new(data).ok()?;
+    let indices = builder
+        .schema()
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| fields.contains(field.name()))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    let mask = ProjectionMask::roots(builder.parquet_schema(), indices);
+    let reader = builder.with_projection(mask).build().ok()?;
+    let batches = reader.collect::<Result<Vec<_>, _>>().ok()?;
+    build(schema, &batches)
+}
+
+pub async fn put(file: &str, filters: &FileBloomFilters) {
+    match json::to_vec(filters) {
+        Ok(data) => sidecar::put(&bloom_filter_key(file), &data).await,
+        Err(e) => log::error!(
+            "[BLOOM_FILTER] encode bloom filters of {} failed: {}",
+            file,
+            e
+        ),
+    }
+}
+
+/// Drops the files the bloom filters of which rule out a value the where
+/// clause requires, files without bloom filters are kept
+pub async fn filter_files(sql: &Sql, files: Vec<String>) -> Vec<String> {
+    let fields = get_stream_setting_bloom_filter_fields(&sql.schema);
+    if fields.is_empty() {
+        return files;
+    }
+    let items = match sql.meta.selection.as_ref() {
+        Some(selection) => required_values(selection, &fields),
+        None => return files,
+    };
+    if items.is_empty() {
+        return files;
+    }
+    let file_num = files.len();
+    let files = sidecar::filter_files(
+        files,
+        bloom_filter_key,
+        |data| json::from_slice::<FileBloomFilters>(&data).ok(),
+        |filters| filters.may_contain(&items),
+    )
+    .await;
+    log::info!(
+        "[BLOOM_FILTER] {} of {} files may contain {:?}",
+        files.len(),
+        file_num,
+        items
+    );
+    files
+}
+
+/// The values the where clause requires for the bloom filter fields, from
+/// `field = value` and `field IN (...)` in its conjunction
+fn required_values(expr: &SqlExpr, fields: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut items = Vec::new();
+    for expr in conjuncts(expr) {
+        match expr {
+            SqlExpr::BinaryOp {
+                left,
+                op: BinaryOperator::Eq,
+                right,
+            } => {
+                let item = match (field_name(left), literal(right)) {
+                    (Some(field), Some(value)) => Some((field, value)),
+                    _ => match (field_name(right), literal(left)) {
+                        (Some(field), Some(value)) => Some((field, value)),
+                        _ => None,
+                    },
+                };
+                if let Some((field, value)) = item {
+                    if fields.contains(&field) {
+                        items.push((field, vec![value]));
+                    }
+                }
+            }
+            SqlExpr::InList {
+                expr,
+                list,
+                negated: false,
+            } => {
+                let field = match field_name(expr) {
+                    Some(field) if fields.contains(&field) => field,
+                    _ => continue,
+                };
+                let values = list.iter().map(literal).collect::<Option<Vec<_>>>();
+                if let Some(values) = values {
+                    items.push((field, values));
+                }
+            }
+            _ => {}
+        }
+    }
+    items
+}
+
+/// only strings, a number may be compared to text written otherwise
+fn literal(expr: &SqlExpr) -> Option<String> {
+    match expr {
+        SqlExpr::Value(SqlValue::SingleQuotedString(s)) => Some(s.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::sql::parse_selection;
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::with_capacity(1000);
+        for i in 0..1000 {
+            filter.insert(&format!("trace-{}", i));
+        }
+        for i in 0..1000 {
+            assert!(filter.contains(&format!("trace-{}", i)));
+        }
+        let false_positives = (1000..11000)
+            .filter(|i| filter.contains(&format!("trace-{}", i)))
+            .count();
+        assert!(false_positives < 300);
+    }
+
+    #[test]
+    fn test_required_values() {
+        let selection = parse_selection("SELECT * FROM tbl WHERE trace_id = 'abc' AND (level = 'error' AND request_id IN ('r1', 'r2')) AND (trace_id = 'x' OR trace_id = 'y') AND user_id NOT IN ('u1')");
+        let fields = vec![
+            "trace_id".to_string(),
+            "request_id".to_string(),
+            "user_id".to_string(),
+        ];
+        assert_eq!(
+            required_values(&selection, &fields),
+            vec![
+                ("trace_id".to_string(), vec!["abc".to_string()]),
+                (
+                    "request_id".to_string(),
+                    vec!["r1".to_string(), "r2".to_string()]
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,343 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datafusion::arrow::array::{Array, Float64Array};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+use sqlparser::ast::{BinaryOperator, Expr as SqlExpr, UnaryOperator, Value as SqlValue};
+
+use crate::common::sql::{conjuncts, field_name};
+use crate::meta::common::{ColumnStats, FileMeta};
+use crate::service::stream::get_stream_setting_stats_fields;
+
+pub fn is_numeric(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float32
+            | DataType::Float64
+    )
+}
+
+/// Collects the stats of the stats fields of the records of a file, a field
+/// not numeric in the file gets no stats
+pub fn build(schema: &Schema, batches: &[RecordBatch]) -> Vec<ColumnStats> {
+    let mut stats = Vec::new();
+    for field in get_stream_setting_stats_fields(schema) {
+        let mut column_stats = ColumnStats {
+            name: field.clone(),
+            min: f64::MAX,
+            max: f64::MIN,
+            null_count: 0,
+        };
+        let mut covered = true;
+        for batch in batches {
+            let column = match batch.column_by_name(&field) {
+                Some(column) => column,
+                None => {
+                    // the field is null in the records written before it
+                    column_stats.null_count += batch.num_rows() as u64;
+                    continue;
+                }
+            };
+            if !is_numeric(column.data_type()) {
+                covered = false;
+                break;
+            }
+            let column = cast(column, &DataType::Float64).unwrap();
+            let values = column.as_any().downcast_ref::<Float64Array>().unwrap();
+            column_stats.null_count += values.null_count() as u64;
+            for value in values.iter().flatten() {
+                // NaN has no place in a range
+                if value.is_nan() {
+                    covered = false;
+                    break;
+                }
+                column_stats.min = column_stats.min.min(value);
+                column_stats.max = column_stats.max.max(value);
+            }
+            if !covered {
+                break;
+            }
+        }
+        if !covered {
+            continue;
+        }
+        if column_stats.min > column_stats.max {
+            // all the values are null
+            column_stats.min = 0.0;
+            column_stats.max = 0.0;
+        }
+        stats.push(column_stats);
+    }
+    stats
+}
+
+/// Merges the stats of the files compacted into one, a field keeps its stats
+/// only when every file has them
+pub fn merge(metas: &[FileMeta]) -> Vec<ColumnStats> {
+    let first = match metas.first() {
+        Some(meta) => meta,
+        None => return vec![],
+    };
+    let mut stats = Vec::new();
+    for field in first.column_stats.iter() {
+        let mut merged = ColumnStats {
+            name: field.name.clone(),
+            min: f64::MAX,
+            max: f64::MIN,
+            null_count: 0,
+        };
+        let mut covered = true;
+        for meta in metas {
+            let column_stats = match meta.column_stats.iter().find(|s| s.name == field.name) {
+                Some(column_stats) if meta.records > 0 => column_stats,
+                _ => {
+                    covered = false;
+                    break;
+                }
+            };
+            merged.null_count += column_stats.null_count;
+            if column_stats.null_count < meta.records {
+                merged.min = merged.min.min(column_stats.min);
+                merged.max = merged.max.max(column_stats.max);
+            }
+        }
+        if !covered {
+            continue;
+        }
+        if merged.min > merged.max {
+            merged.min = 0.0;
+            merged.max = 0.0;
+        }
+        stats.push(merged);
+    }
+    stats
+}
+
+/// Whether the file may hold a record matching the where clause, only the
+/// range conditions on numeric fields in its conjunction are checked. The
+/// bounds are compared inclusively as the values are kept as f64.
+pub fn may_match(expr: &SqlExpr, schema: &Schema, file_meta: &FileMeta) -> bool {
+    if file_meta.column_stats.is_empty() || file_meta.records == 0 {
+        return true;
+    }
+    conjuncts(expr)
+        .into_iter()
+        .all(|expr| may_match_condition(expr, schema, file_meta))
+}
+
+fn may_match_condition(expr: &SqlExpr, schema: &Schema, file_meta: &FileMeta) -> bool {
+    match expr {
+        SqlExpr::BinaryOp { left, op, right } => {
+            let (field, op, value) = match (field_name(left), number(right)) {
+                (Some(field), Some(value)) => (field, op.clone(), value),
+                _ => match (field_name(right), number(left), flip(op)) {
+                    (Some(field), Some(value), Some(op)) => (field, op, value),
+                    _ => return true,
+                },
+            };
+            let stats = match get_stats(&field, schema, file_meta) {
+                Some(stats) => stats,
+                None => return true,
+            };
+            if stats.null_count >= file_meta.records {
+                // null matches no comparison
+                return !matches!(
+                    op,
+                    BinaryOperator::Eq
+                        | BinaryOperator::Gt
+                        | BinaryOperator::GtEq
+                        | BinaryOperator::Lt
+                        | BinaryOperator::LtEq
+                );
+            }
+            match op {
+                BinaryOperator::Eq => value >= stats.min && value <= stats.max,
+                BinaryOperator::Gt | BinaryOperator::GtEq => stats.max >= value,
+                BinaryOperator::Lt | BinaryOperator::LtEq => stats.min <= value,
+                _ => true,
+            }
+        }
+        SqlExpr::Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        } => {
+            let (field, low, high) = match (field_name(expr), number(low), number(high)) {
+                (Some(field), Some(low), Some(high)) => (field, low, high),
+                _ => return true,
+            };
+            match get_stats(&field, schema, file_meta) {
+                Some(stats) => {
+                    stats.null_count < file_meta.records && stats.max >= low && stats.min <= high
+                }
+                None => true,
+            }
+        }
+        SqlExpr::IsNull(e) => match field_name(e).and_then(|f| get_stats(&f, schema, file_meta)) {
+            Some(stats) => stats.null_count > 0,
+            None => true,
+        },
+        SqlExpr::IsNotNull(e) => {
+            match field_name(e).and_then(|f| get_stats(&f, schema, file_meta)) {
+                Some(stats) => stats.null_count < file_meta.records,
+                None => true,
+            }
+        }
+        _ => true,
+    }
+}
+
+/// the stats of a field the stream still has as numeric
+fn get_stats<'a>(field: &str, schema: &Schema, file_meta: &'a FileMeta) -> Option<&'a ColumnStats> {
+    match schema.field_with_name(field) {
+        Ok(f) if is_numeric(f.data_type()) => {
+            file_meta.column_stats.iter().find(|s| s.name == field)
+        }
+        _ => None,
+    }
+}
+
+/// the operator of `value op field` written as `field op value`
+fn flip(op: &BinaryOperator) -> Option<BinaryOperator> {
+    match op {
+        BinaryOperator::Eq => Some(BinaryOperator::Eq),
+        BinaryOperator::Gt => Some(BinaryOperator::Lt),
+        BinaryOperator::GtEq => Some(BinaryOperator::LtEq),
+        BinaryOperator::Lt => Some(BinaryOperator::Gt),
+        BinaryOperator::LtEq => Some(BinaryOperator::GtEq),
+        _ => None,
+    }
+}
+
+fn number(expr: &SqlExpr) -> Option<f64> {
+    match expr {
+        SqlExpr::Value(SqlValue::Number(n, _)) => n.parse::<f64>().ok(),
+        SqlExpr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => number(expr).map(|n| -n),
+        SqlExpr::Nested(e) => number(e),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::sql::parse_selection;
+    use datafusion::arrow::array::Int64Array;
+    use datafusion::arrow::datatypes::Field;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_build() {
+        let mut meta = HashMap::new();
+        meta.insert(
+            "settings".to_string(),
+            r#"{"stats_fields":["status","duration"]}"#.to_string(),
+        );
+        let schema = Schema::new(vec![
+            Field::new("status", DataType::Int64, true),
+            Field::new("duration", DataType::Int64, true),
+        ])
+        .with_metadata(meta);
+        let batch = RecordBatch::try_new(
+            Arc::new(Schema::new(vec![Field::new(
+                "status",
+                DataType::Int64,
+                true,
+            )])),
+            vec![Arc::new(Int64Array::from(vec![Some(200), None, Some(503)]))],
+        )
+        .unwrap();
+        let stats = build(&schema, &[batch]);
+        assert_eq!(
+            stats,
+            vec![
+                ColumnStats {
+                    name: "status".to_string(),
+                    min: 200.0,
+                    max: 503.0,
+                    null_count: 1,
+                },
+                ColumnStats {
+                    name: "duration".to_string(),
+                    min: 0.0,
+                    max: 0.0,
+                    null_count: 3,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_may_match() {
+        let schema = Schema::new(vec![
+            Field::new("status", DataType::Int64, true),
+            Field::new("duration", DataType::Float64, true),
+        ]);
+        let file_meta = FileMeta {
+            records: 10,
+            column_stats: vec![
+                ColumnStats {
+                    name: "status".to_string(),
+                    min: 200.0,
+                    max: 404.0,
+                    null_count: 0,
+                },
+                ColumnStats {
+                    name: "duration".to_string(),
+                    min: 0.0,
+                    max: 0.0,
+                    null_count: 10,
+                },
+            ],
+            ..Default::default()
+        };
+        let cases = vec![
+            ("status >= 500", false),
+            ("status >= 404", true),
+            ("500 <= status", false),
+            ("status BETWEEN 405 AND 499", false),
+            ("status = 300 AND level = 'error'", true),
+            ("status >= 500 OR level = 'error'", true),
+            ("(status < 199) AND level = 'error'", false),
+            ("status > -1", true),
+            ("duration > 1000", false),
+            ("duration IS NULL", true),
+            ("status IS NULL", false),
+        ];
+        for (where_clause, expected) in cases {
+            let expr = parse_selection(&format!("SELECT * FROM tbl WHERE {}", where_clause));
+            assert_eq!(
+                may_match(&expr, &schema, &file_meta),
+                expected,
+                "{}",
+                where_clause
+            );
+        }
+    }
+}
@@ -14,7 +14,8 @@
 
 use ::datafusion::arrow::datatypes::Schema;
 use ahash::AHashMap as HashMap;
-use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use bytes::Bytes;
+use chrono::{DateTime, Duration, TimeZone, Utc};
 use std::io::Write;
 use std::sync::Arc;
 use tokio::time;
@@ -25,9 +26,11 @@ use crate::infra::storage::generate_partioned_file_key;
 use crate::infra::{cache, ider, storage};
 use crate::infra::{config::CONFIG, db::etcd};
 use crate::meta::common::{FileKey, FileMeta};
+use crate::meta::stream::PartitionTimeLevel;
 use crate::meta::StreamType;
 use crate::service::search::datafusion;
-use crate::service::{db, file_list};
+use crate::service::stream::get_stream_setting_partition_time_level;
+use crate::service::{bloom_filter, column_stats, db, file_list, fulltext_index, sidecar};
 
 /// compactor run steps on a stream:
 /// 3. get a cluster lock for compactor stream
@@ -57,9 +60,17 @@ pub async fn merge_by_stream(
     }
 
     // get schema
-    let schema = db::schema::get(org_id, stream_name, Some(stream_type)).await?;
-    let schema_metadata = schema.metadata.clone();
-    let schema = Arc::new(schema.with_metadata(std::collections::HashMap::new()));
+    let stream_schema = db::schema::get(org_id, stream_name, Some(stream_type)).await?;
+    let schema_metadata = stream_schema.metadata.clone();
+    let partition_time_level = get_stream_setting_partition_time_level(&stream_schema);
+    let schema = Arc::new(
+        stream_schema
+            .clone()
+            .with_metadata(std::collections::HashMap::new()),
+    );
+    // the compaction offset moves by hour, or by day for daily partitions
+    let compact_level = partition_time_level.compact_level();
+    let partition_duration = compact_level.duration().num_microseconds().unwrap();
 
     // get last compacted offset
     let mut offset = db::compact::files::get_offset(org_id, stream_name, stream_type).await?;
@@ -79,22 +90,12 @@ pub async fn merge_by_stream(
         return Ok(()); // no data
     }
     let offset_time: DateTime<Utc> = Utc.timestamp_nanos(offset * 1000);
-    let offset_time_hour = Utc
-        .with_ymd_and_hms(
-            offset_time.year(),
-            offset_time.month(),
-            offset_time.day(),
-            offset_time.hour(),
-            0,
-            0,
-        )
-        .unwrap()
-        .timestamp_micros();
+    let offset_time_hour = compact_level.truncate(offset);
 
     // check sync offset, if already synced, just set to next hour
     if offset < last_file_list_offset {
         // write new offset
-        offset = offset_time_hour + Duration::hours(1).num_microseconds().unwrap();
+        offset = offset_time_hour + partition_duration;
         db::compact::files::set_offset(org_id, stream_name, stream_type, offset).await?;
         // release cluster lock
         if locker.is_some() {
@@ -106,24 +107,14 @@ pub async fn merge_by_stream(
 
     // check offset
     let time_now: DateTime<Utc> = Utc::now();
-    let time_now_hour = Utc
-        .with_ymd_and_hms(
-            time_now.year(),
-            time_now.month(),
-            time_now.day(),
-            time_now.hour(),
-            0,
-            0,
-        )
-        .unwrap()
-        .timestamp_micros();
+    let time_now_hour = compact_level.truncate(time_now.timestamp_micros());
     // if offset is future, just wait
     // if offset is last hour, must wait for at least 3 times of max_file_retention_time
     // - first period: the last hour local file upload to storage, write file list
     // - second period, the last hour file list upload to storage
     // - third period, we can do the merge, at least 3 times of max_file_retention_time
     if offset >= time_now_hour
-        || (offset_time_hour + Duration::hours(1).num_microseconds().unwrap() == time_now_hour
+        || (offset_time_hour + partition_duration == time_now_hour
             && time_now.timestamp_micros() - time_now_hour
                 < Duration::seconds(CONFIG.limit.max_file_retention_time as i64)
                     .num_microseconds()
@@ -144,8 +135,7 @@ pub async fn merge_by_stream(
         stream_name,
         Some(stream_type),
         offset_time_hour,
-        offset_time_hour + Duration::hours(1).num_microseconds().unwrap()
-            - Duration::seconds(1).num_microseconds().unwrap(),
+        offset_time_hour + partition_duration - Duration::seconds(1).num_microseconds().unwrap(),
     )
     .await?;
 
@@ -154,7 +144,7 @@ pub async fn merge_by_stream(
         // if offset > 0 && offset_time_hour + Duration::hours(CONFIG.limit.allowed_upto).num_microseconds().unwrap() < time_now_hour {
         // -- no check it
         // }
-        offset = offset_time_hour + Duration::hours(1).num_microseconds().unwrap();
+        offset = offset_time_hour + partition_duration;
         db::compact::files::set_offset(org_id, stream_name, stream_type, offset).await?;
         if locker.is_some() {
             // release cluster lock
@@ -189,6 +179,7 @@ pub async fn merge_by_stream(
                 org_id,
                 stream_name,
                 stream_type,
+                partition_time_level,
                 schema.clone(),
                 files_with_size,
             )
@@ -237,7 +228,7 @@ pub async fn merge_by_stream(
                 let mut cache_success = true;
                 for event in &events {
                     if let Err(e) =
-                        db::file_list::progress(&event.key, event.meta, event.deleted).await
+                        db::file_list::progress(&event.key, event.meta.clone(), event.deleted).await
                     {
                         cache_success = false;
                         log::error!("[COMPACT] set local cache failed, retrying: {}", e);
@@ -268,6 +259,7 @@ pub async fn merge_by_stream(
                         log::error!("[COMPACT] delete file failed: {}", e);
                     }
                 }
+                sidecar::delete(&new_file_name).await;
                 continue;
             }
 
@@ -280,6 +272,7 @@ pub async fn merge_by_stream(
                         log::error!("[COMPACT] delete file failed: {}", e);
                     }
                 }
+                sidecar::delete(file).await;
             }
             // delete files from file list
             files_with_size.retain(|value| !&new_file_list.contains(&value.0));
@@ -288,7 +281,7 @@ pub async fn merge_by_stream(
 
     // write new offset
     if merge_success {
-        offset = offset_time_hour + Duration::hours(1).num_microseconds().unwrap();
+        offset = offset_time_hour + partition_duration;
         db::compact::files::set_offset(org_id, stream_name, stream_type, offset).await?;
     }
 
@@ -306,6 +299,7 @@ async fn merge_files(
     org_id: &str,
     stream_name: &str,
     stream_type: StreamType,
+    partition_time_level: PartitionTimeLevel,
     schema: Arc<Schema>,
     files_with_size: &Vec<(String, u64)>,
 ) -> Result<(String, FileMeta, Vec<String>), anyhow::Error> {
@@ -332,7 +326,7 @@ async fn merge_files(
     let schema_versions = db::schema::get_versions(org_id, stream_name, Some(stream_type)).await?;
     let schema_latest = schema_versions.last().unwrap();
     let schema_latest_id = schema_versions.len() - 1;
-    if CONFIG.common.widening_schema_evoluation && schema_versions.len() > 1 {
+    if schema_versions.len() > 1 {
         for file in &new_file_list {
             // get the schema version of the file
             let mut file_meta = file_list::get_file_meta(file).await.unwrap_or_default();
@@ -364,7 +358,11 @@ async fn merge_files(
             let cur_fields = schema.fields();
             for field in cur_fields {
                 if let Ok(v) = schema_latest.field_with_name(field.name()) {
-                    if v.data_type() != field.data_type() {
+                    // the time column type follows the timestamp unit of the stream
+                    if v.data_type() != field.data_type()
+                        && (CONFIG.common.widening_schema_evoluation
+                            || v.name() == &CONFIG.common.time_stamp_col)
+                    {
                         diff_fields.insert(v.name().clone(), v.data_type().clone());
                     }
                 }
@@ -372,6 +370,13 @@ async fn merge_files(
             if diff_fields.is_empty() {
                 continue;
             }
+            // the stats only hold for the fields still numeric
+            file_meta
+                .column_stats
+                .retain(|stats| match diff_fields.get(&stats.name) {
+                    Some(data_type) => column_stats::is_numeric(data_type),
+                    None => true,
+                });
             // do the convert
             let mut buf = Vec::new();
             datafusion::exec::convert_parquet_file(&mut buf, Arc::new(schema), diff_fields, file)
@@ -384,15 +389,22 @@ async fn merge_files(
 
     let mut buf = Vec::new();
     let mut new_file_meta =
-        datafusion::exec::merge_parquet_files(&mut buf, schema, &new_file_list.clone()).await?;
+        datafusion::exec::merge_parquet_files(&mut buf, schema, &new_file_list.clone(), None)
+            .await?;
     new_file_meta.original_size = new_file_size;
     new_file_meta.compressed_size = buf.len() as u64;
+    let mut file_metas = Vec::with_capacity(new_file_list.len());
+    for file in new_file_list.iter() {
+        file_metas.push(file_list::get_file_meta(file).await.unwrap_or_default());
+    }
+    new_file_meta.column_stats = column_stats::merge(&file_metas);
 
     let new_file = generate_partioned_file_key(
         org_id,
         stream_name,
         stream_type,
         new_file_meta.min_ts,
+        partition_time_level,
         &CONFIG.common.file_ext_parquet,
     );
     let new_file_key = format!("files/{}{}", new_file.0, new_file.1);
@@ -406,10 +418,17 @@ async fn merge_files(
 
     // upload file
     let storage = &storage::DEFAULT;
-    match storage.put(&new_file_key, buf.into()).await {
-        Ok(_) => Ok((new_file_key, new_file_meta, new_file_list)),
-        Err(e) => Err(e),
+    let buf = Bytes::from(buf);
+    storage.put(&new_file_key, buf.clone()).await?;
+    // the index of the big file covers all the small files
+    if let Some(index) = fulltext_index::merge(&new_file_list).await {
+        fulltext_index::put(&new_file_key, &index).await;
+    }
+    // bloom filters can't be merged, they are sized by the number of values
+    if let Some(filters) = bloom_filter::build_from_parquet(schema_latest, buf) {
+        bloom_filter::put(&new_file_key, &filters).await;
     }
+    Ok((new_file_key, new_file_meta, new_file_list))
 }
 
 #[cfg(test)]
@@ -18,6 +18,7 @@ use crate::service::db;
 
 mod file_list;
 mod merge;
+mod retention;
 
 /// compactor run steps:
 /// 1. get all organization
@@ -33,6 +34,14 @@ mod merge;
 /// 11. release cluster lock
 /// 12. compact file list from storage
 pub async fn run() -> Result<(), anyhow::Error> {
+    // drop the partitions out of retention before merging
+    if let Err(e) = retention::run().await {
+        log::error!("[COMPACTOR] retention error: {}", e);
+    }
+    if let Err(e) = crate::service::volume::prune().await {
+        log::error!("[COMPACTOR] volume history retention error: {}", e);
+    }
+
     // get last file_list compact offset
     let last_file_list_offset = db::compact::file_list::get_offset().await?;
 
@@ -89,6 +98,7 @@ mod tests {
             records: 10000,
             original_size: 1024,
             compressed_size: 1,
+            column_stats: vec![],
         };
         let _ret = cache::file_list::set_file_to_cache(
             "files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet",
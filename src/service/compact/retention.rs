@@ -0,0 +1,313 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap as HashMap;
+use ahash::AHashSet as HashSet;
+use bytes::Buf;
+use chrono::{Duration, TimeZone, Utc};
+use std::io::{BufRead, BufReader, Write};
+
+use crate::common::json;
+use crate::infra::config::CONFIG;
+use crate::infra::db::etcd;
+use crate::infra::{cache, ider, storage};
+use crate::meta::common::FileKey;
+use crate::meta::stream::{PartitionTimeLevel, MAX_DATA_RETENTION_DAYS};
+use crate::meta::StreamType;
+use crate::service::stream::{
+    get_stream_setting_data_retention, get_stream_setting_partition_time_level,
+};
+use crate::service::{db, sidecar};
+
+/// retention run steps:
+/// 1. range streams by organization & stream_type
+/// 2. resolve the retention days: stream settings -> org settings -> global
+/// 3. get a cluster lock for compactor stream
+/// 4. find the hourly partitions older than the cutoff
+/// 5. delete the files keys, decrease stream stats, send broadcast
+/// 6. delete the files from storage
+/// 7. prune the deleted files keys from the file list in storage
+pub async fn run() -> Result<(), anyhow::Error> {
+    let orgs = cache::file_list::get_all_organization()?;
+    let stream_types = [StreamType::Logs, StreamType::Metrics, StreamType::Traces];
+    let mut pruned_hours = HashSet::new();
+    for org_id in orgs {
+        let org_settings = db::organization::get_settings(&org_id).await?;
+        for stream_type in stream_types {
+            let streams = cache::file_list::get_all_stream(&org_id, stream_type)?;
+            for stream_name in streams {
+                tokio::task::yield_now().await; // yield to other tasks
+                let schema = db::schema::get(&org_id, &stream_name, Some(stream_type)).await?;
+                let mut retention = get_stream_setting_data_retention(&schema);
+                if retention == 0 {
+                    retention = org_settings.data_retention;
+                }
+                if retention == 0 {
+                    retention = CONFIG.compact.data_retention_days;
+                }
+                if retention <= 0 {
+                    continue; // keep forever
+                }
+                // the global default is not validated like the settings
+                let retention = retention.min(MAX_DATA_RETENTION_DAYS);
+                let cutoff = Utc::now().timestamp_micros()
+                    - Duration::days(retention).num_microseconds().unwrap();
+                let partition_time_level = get_stream_setting_partition_time_level(&schema);
+                match delete_by_stream(
+                    cutoff,
+                    &org_id,
+                    &stream_name,
+                    stream_type,
+                    partition_time_level,
+                )
+                .await
+                {
+                    Ok(hours) => pruned_hours.extend(hours),
+                    Err(e) => log::error!(
+                        "[COMPACTOR] retention [{}:{}:{}] error: {}",
+                        org_id,
+                        stream_type,
+                        stream_name,
+                        e
+                    ),
+                }
+            }
+        }
+    }
+
+    for hour in pruned_hours {
+        if let Err(e) = prune_file_list(&hour).await {
+            log::error!(
+                "[COMPACTOR] retention prune file list {} error: {}",
+                hour,
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// delete the hourly partitions older than the cutoff, returns the deleted hours
+async fn delete_by_stream(
+    cutoff: i64,
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    partition_time_level: PartitionTimeLevel,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut locker = None;
+    if !CONFIG.common.local_mode {
+        // share the lock with merge, the files must not be compacted meanwhile
+        let lock_key = format!("compactor/files/{}/{}/{}", org_id, stream_type, stream_name);
+        let mut lock = etcd::Locker::new(&lock_key);
+        if lock.lock(CONFIG.etcd.command_timeout).await.is_err() {
+            return Ok(vec![]); // lock failed, just skip
+        }
+        locker = Some(lock);
+    }
+
+    let files = cache::file_list::get_file_list(
+        org_id,
+        stream_name,
+        stream_type,
+        0,
+        0,
+        partition_time_level,
+    )
+    .await?;
+    let mut hours = HashSet::new();
+    let files = files
+        .into_iter()
+        .filter(
+            |file| match expired_hour(file, partition_time_level, cutoff) {
+                Some(hour) => {
+                    hours.insert(hour);
+                    true
+                }
+                None => false,
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if !files.is_empty() {
+        log::info!(
+            "[COMPACTOR] retention [{}:{}:{}] delete {} files in {} partitions",
+            org_id,
+            stream_type,
+            stream_name,
+            files.len(),
+            hours.len()
+        );
+        db::file_list::delete_files(&files).await?;
+        let storage = &storage::DEFAULT;
+        for file in files.iter() {
+            tokio::task::yield_now().await; // yield to other tasks
+            if let Err(e) = storage.del(file).await {
+                log::error!("[COMPACTOR] retention delete file failed: {}", e);
+            }
+            sidecar::delete(file).await;
+        }
+    }
+
+    if locker.is_some() {
+        // release cluster lock
+        let mut lock = locker.unwrap();
+        lock.unlock().await?;
+    }
+
+    Ok(hours.into_iter().collect())
+}
+
+/// the partition hour of a file if the whole partition is older than the
+/// cutoff, a daily partition is kept under the first hour of the day and the
+/// minutes of an hour are all under that hour
+fn expired_hour(
+    file: &str,
+    partition_time_level: PartitionTimeLevel,
+    cutoff: i64,
+) -> Option<String> {
+    let (hour, hour_ts) = partition_hour(file)?;
+    let duration = partition_time_level.compact_level().duration();
+    (hour_ts + duration.num_microseconds().unwrap() <= cutoff).then_some(hour)
+}
+
+/// get the partition hour of a file key, eg: 2022/10/03/10 and its timestamp
+fn partition_hour(file: &str) -> Option<(String, i64)> {
+    // eg: files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet
+    let columns = file.split('/').collect::<Vec<&str>>();
+    if columns.len() < 9 {
+        return None;
+    }
+    let time = Utc
+        .with_ymd_and_hms(
+            columns[4].parse().ok()?,
+            columns[5].parse().ok()?,
+            columns[6].parse().ok()?,
+            columns[7].parse().ok()?,
+            0,
+            0,
+        )
+        .single()?;
+    Some((columns[4..8].join("/"), time.timestamp_micros()))
+}
+
+/// rewrite the file list of the hour in storage without the deleted files keys
+async fn prune_file_list(hour: &str) -> Result<(), anyhow::Error> {
+    let mut locker = None;
+    if !CONFIG.common.local_mode {
+        // share the lock with file list merge
+        let lock_key = "compactor/file_list";
+        let mut lock = etcd::Locker::new(lock_key);
+        if lock.lock(CONFIG.etcd.command_timeout).await.is_err() {
+            return Ok(()); // lock failed, just skip
+        }
+        locker = Some(lock);
+    }
+
+    let key = format!("file_list/{}/", hour);
+    let storage = &storage::DEFAULT;
+    let file_list = storage.list(&key).await?;
+
+    // deleted keys win over the added ones
+    let mut filter_file_keys: HashMap<String, FileKey> = HashMap::with_capacity(1024);
+    for file in file_list.iter() {
+        let data = storage.get(file).await?;
+        let uncompress = zstd::decode_all(data.reader())?;
+        let uncompress_reader = BufReader::new(uncompress.reader());
+        for line in uncompress_reader.lines() {
+            let line = line?;
+            if line.is_empty() {
+                continue;
+            }
+            let item: FileKey = json::from_slice(line.as_bytes())?;
+            match filter_file_keys.get(&item.key) {
+                Some(_) => {
+                    if item.deleted {
+                        filter_file_keys.insert(item.key.clone(), item);
+                    }
+                }
+                None => {
+                    filter_file_keys.insert(item.key.clone(), item);
+                }
+            }
+        }
+    }
+    filter_file_keys.retain(|_, item| !item.deleted);
+
+    // write new file list, if any file is left
+    let mut success = true;
+    if !filter_file_keys.is_empty() {
+        let file_name = format!("{}{}.json.zst", key, ider::generate());
+        let mut buf = zstd::Encoder::new(Vec::new(), 3)?;
+        for (_, item) in filter_file_keys.iter() {
+            let val = json::to_vec(&item)?;
+            buf.write_all(val.as_slice())?;
+            buf.write_all(b"\n")?;
+        }
+        let compressed_bytes = buf.finish().unwrap();
+        if let Err(e) = storage.put(&file_name, compressed_bytes.into()).await {
+            log::error!("[COMPACTOR] retention upload file list failed: {}", e);
+            success = false;
+        }
+    }
+    if success {
+        for file in file_list {
+            storage.del(&file).await?;
+        }
+    }
+
+    if locker.is_some() {
+        // release cluster lock
+        let mut lock = locker.unwrap();
+        lock.unlock().await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_hour() {
+        let (hour, hour_ts) = partition_hour(
+            "files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet",
+        )
+        .unwrap();
+        assert_eq!(hour, "2022/10/03/10");
+        assert_eq!(hour_ts, 1664791200000000);
+        assert!(
+            partition_hour("files/default/metadata/olympics/2022/00/00/00/1.parquet").is_none()
+        );
+    }
+
+    #[test]
+    fn test_expired_hour() {
+        let file = "files/default/logs/olympics/2022/10/03/00/6982652937134804993_1.parquet";
+        // 2022-10-03 12:00, in the middle of the day
+        let cutoff = 1664798400000000;
+        assert_eq!(
+            expired_hour(file, PartitionTimeLevel::Hour, cutoff),
+            Some("2022/10/03/00".to_string())
+        );
+        assert_eq!(expired_hour(file, PartitionTimeLevel::Day, cutoff), None);
+        let cutoff = cutoff + Duration::hours(12).num_microseconds().unwrap();
+        assert_eq!(
+            expired_hour(file, PartitionTimeLevel::Day, cutoff),
+            Some("2022/10/03/00".to_string())
+        );
+    }
+}
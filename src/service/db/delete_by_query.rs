@@ -0,0 +1,44 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::json;
+use crate::meta::delete_by_query::DeleteByQueryReport;
+
+pub async fn get(org_id: &str, id: &str) -> Result<Option<DeleteByQueryReport>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/delete_by_query/{}/{}", org_id, id);
+    let value: Option<DeleteByQueryReport> = match db.get(&key).await {
+        Ok(val) => json::from_slice(&val)?,
+        Err(_) => None,
+    };
+    Ok(value)
+}
+
+pub async fn set(org_id: &str, report: &DeleteByQueryReport) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/delete_by_query/{}/{}", org_id, report.id);
+    db.put(&key, json::to_vec(report).unwrap().into()).await?;
+    Ok(())
+}
+
+pub async fn list(org_id: &str) -> Result<Vec<DeleteByQueryReport>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/delete_by_query/{}/", org_id);
+    let ret = db.list_values(&key).await?;
+    let mut reports = Vec::with_capacity(ret.len());
+    for item_value in ret {
+        reports.push(json::from_slice(&item_value)?);
+    }
+    Ok(reports)
+}
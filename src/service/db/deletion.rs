@@ -0,0 +1,66 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::json;
+use crate::meta::stream::StreamDeletion;
+use crate::meta::StreamType;
+
+pub async fn get(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<Option<StreamDeletion>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/deletion/{}/{}/{}", org_id, stream_type, stream_name);
+    let value: Option<StreamDeletion> = match db.get(&key).await {
+        Ok(val) => json::from_slice(&val)?,
+        Err(_) => None,
+    };
+    Ok(value)
+}
+
+pub async fn set(org_id: &str, deletion: &StreamDeletion) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!(
+        "/deletion/{}/{}/{}",
+        org_id, deletion.stream_type, deletion.stream_name
+    );
+    db.put(&key, json::to_vec(deletion).unwrap().into()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::stream::StreamDeletionStatus;
+
+    #[actix_web::test]
+    async fn test_deletion() {
+        let deletion = StreamDeletion {
+            stream_name: "olympics".to_string(),
+            stream_type: StreamType::Logs,
+            status: StreamDeletionStatus::Running,
+            files: 10,
+            deleted_files: 0,
+            failed_files: 0,
+            created_at: 1667978841110,
+            updated_at: 1667978841110,
+        };
+        set("nexus", &deletion).await.unwrap();
+        let resp = get("nexus", "olympics", StreamType::Logs).await.unwrap();
+        assert_eq!(resp.unwrap().files, 10);
+        let resp = get("nexus", "not_deleted", StreamType::Logs).await.unwrap();
+        assert!(resp.is_none());
+    }
+}
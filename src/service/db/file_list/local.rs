@@ -18,6 +18,7 @@ use std::io::{BufRead, BufReader};
 
 use crate::common::file::scan_files;
 use crate::common::json;
+use crate::infra::cache;
 use crate::infra::config::CONFIG;
 use crate::infra::file_lock;
 use crate::meta::common::{FileKey, FileMeta};
@@ -26,7 +27,7 @@ use crate::meta::StreamType;
 pub async fn set(key: &str, meta: FileMeta, deleted: bool) -> Result<(), anyhow::Error> {
     let file_data = FileKey {
         key: key.to_string(),
-        meta,
+        meta: meta.clone(),
         deleted,
     };
     let mut write_buf = json::to_vec(&file_data)?;
@@ -39,6 +40,16 @@ pub async fn set(key: &str, meta: FileMeta, deleted: bool) -> Result<(), anyhow:
     file.write(write_buf.as_ref());
 
     super::progress(key, meta, deleted).await?;
+    if !deleted {
+        // only ingested files are written by this path, count them as volume
+        if let Err(e) = cache::stats::incr_stream_volume(key, &file_data.meta) {
+            log::error!(
+                "service:db:file_list: add {}, incr_stream_volume error: {}",
+                key,
+                e
+            );
+        }
+    }
     super::broadcast::send(&[file_data]).await
 }
 
@@ -95,6 +106,7 @@ mod tests {
             records: 300,
             original_size: 10,
             compressed_size: 1,
+            column_stats: vec![],
         };
 
         let resp = set(file_key, file_meta, false).await;
@@ -12,10 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use ahash::AHashMap as HashMap;
+use std::io::Write;
+
+use crate::common::json;
 use crate::infra::cache;
 use crate::infra::cluster;
 use crate::infra::config::CONFIG;
-use crate::meta::common::FileMeta;
+use crate::infra::{ider, storage};
+use crate::meta::common::{FileKey, FileMeta};
 
 pub mod broadcast;
 pub mod local;
@@ -42,7 +47,7 @@ pub async fn progress(key: &str, data: FileMeta, delete: bool) -> Result<(), any
                     );
                 }
             }
-            match cache::stats::decr_stream_stats(key, data) {
+            match cache::stats::decr_stream_stats(key, &data) {
                 Ok(_) => {}
                 Err(e) => {
                     log::error!(
@@ -54,7 +59,7 @@ pub async fn progress(key: &str, data: FileMeta, delete: bool) -> Result<(), any
             }
         }
         false => {
-            match cache::file_list::set_file_to_cache(key, Some(data), false) {
+            match cache::file_list::set_file_to_cache(key, Some(data.clone()), false) {
                 Ok(_) => {}
                 Err(e) => {
                     log::error!(
@@ -77,7 +82,7 @@ pub async fn progress(key: &str, data: FileMeta, delete: bool) -> Result<(), any
             if old_data.is_ok() {
                 return Ok(()); // already exists, skip increase stats
             };
-            match cache::stats::incr_stream_stats(key, data) {
+            match cache::stats::incr_stream_stats(key, &data) {
                 Ok(_) => {}
                 Err(e) => {
                     log::error!(
@@ -92,3 +97,54 @@ pub async fn progress(key: &str, data: FileMeta, delete: bool) -> Result<(), any
 
     Ok(())
 }
+
+/// Marks the files deleted: the delete events are written into the storage
+/// file list of the hour of each file, set to local cache and broadcast
+pub async fn delete_files(files: &[String]) -> Result<(), anyhow::Error> {
+    let mut hour_events: HashMap<String, Vec<FileKey>> = HashMap::new();
+    for file in files {
+        // eg: files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet
+        let columns = file.split('/').collect::<Vec<&str>>();
+        if columns.len() < 9 {
+            return Err(anyhow::anyhow!(
+                "[TRACE] [delete_files] Invalid file path: {}",
+                file
+            ));
+        }
+        let meta = cache::file_list::get_file_from_cache(file).unwrap_or_default();
+        hour_events
+            .entry(columns[4..8].join("/"))
+            .or_default()
+            .push(FileKey {
+                key: file.to_string(),
+                meta,
+                deleted: true,
+            });
+    }
+
+    for (hour, events) in hour_events {
+        set_events(&hour, &events).await?;
+    }
+
+    Ok(())
+}
+
+/// Writes the events into one file list of the hour (eg: 2022/10/03/10) in
+/// storage, sets them to local cache and sends broadcast
+pub async fn set_events(hour: &str, events: &[FileKey]) -> Result<(), anyhow::Error> {
+    let file_list_key = format!("file_list/{}/{}.json.zst", hour, ider::generate());
+    let mut buf = zstd::Encoder::new(Vec::new(), 3)?;
+    for event in events.iter() {
+        let mut write_buf = json::to_vec(&event)?;
+        write_buf.push(b'\n');
+        buf.write_all(&write_buf)?;
+    }
+    let compressed_bytes = buf.finish().unwrap();
+    let storage = &storage::DEFAULT;
+    storage.put(&file_list_key, compressed_bytes.into()).await?;
+
+    for event in events.iter() {
+        progress(&event.key, event.meta.clone(), event.deleted).await?;
+    }
+    broadcast::send(events).await
+}
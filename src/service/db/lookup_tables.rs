@@ -0,0 +1,122 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::common::json;
+use crate::infra::config::LOOKUP_TABLES;
+use crate::infra::db::Event;
+use crate::infra::storage;
+use crate::meta::lookup::{LookupData, LookupTable};
+
+pub fn get_storage_key(org_id: &str, name: &str) -> String {
+    format!("lookup_tables/{}/{}.json", org_id, name)
+}
+
+pub async fn get(org_id: &str, name: &str) -> Result<Option<LookupTable>, anyhow::Error> {
+    let map_key = format!("{}/{}", org_id, name);
+    if let Some(data) = LOOKUP_TABLES.get(&map_key) {
+        return Ok(Some(data.table.clone()));
+    }
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/lookup_tables/{}/{}", org_id, name);
+    match db.get(&key).await {
+        Ok(val) => Ok(Some(json::from_slice(&val)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+pub async fn set(org_id: &str, table: &LookupTable) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/lookup_tables/{}/{}", org_id, table.name);
+    db.put(&key, json::to_vec(table).unwrap().into()).await?;
+    Ok(())
+}
+
+pub async fn delete(org_id: &str, name: &str) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/lookup_tables/{}/{}", org_id, name);
+    match db.delete(&key, false).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+pub async fn list(org_id: &str) -> Result<Vec<LookupTable>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/lookup_tables/{}/", org_id);
+    let ret = db.list_values(&key).await?;
+    let mut tables = Vec::with_capacity(ret.len());
+    for item_value in ret {
+        tables.push(json::from_slice(&item_value)?);
+    }
+    Ok(tables)
+}
+
+/// Downloads the table rows from storage and caches them on this node
+async fn load(org_id: &str, table: LookupTable) -> Result<(), anyhow::Error> {
+    let storage = &storage::DEFAULT;
+    let data = storage.get(&get_storage_key(org_id, &table.name)).await?;
+    let map_key = format!("{}/{}", org_id, table.name);
+    LOOKUP_TABLES.insert(map_key, LookupData::try_new(table, &data)?);
+    Ok(())
+}
+
+pub async fn watch() -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = "/lookup_tables/";
+    let mut events = db.watch(key).await?;
+    let events = Arc::get_mut(&mut events).unwrap();
+    log::info!("[TRACE] Start watching lookup tables");
+    loop {
+        let ev = match events.recv().await {
+            Some(ev) => ev,
+            None => {
+                log::error!("watch_lookup_tables: event channel closed");
+                break;
+            }
+        };
+        match ev {
+            Event::Put(ev) => {
+                let item_key = ev.key.strip_prefix(key).unwrap();
+                let org_id = &item_key[0..item_key.find('/').unwrap()];
+                let item_value: LookupTable = json::from_slice(&ev.value.unwrap()).unwrap();
+                if let Err(e) = load(org_id, item_value).await {
+                    log::error!("load lookup table {} error: {}", item_key, e);
+                }
+            }
+            Event::Delete(ev) => {
+                let item_key = ev.key.strip_prefix(key).unwrap();
+                LOOKUP_TABLES.remove(item_key);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn cache() -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = "/lookup_tables/";
+    let ret = db.list(key).await?;
+    for (item_key, item_value) in ret {
+        let item_key = item_key.strip_prefix(key).unwrap();
+        let org_id = &item_key[0..item_key.find('/').unwrap()];
+        let json_val: LookupTable = json::from_slice(&item_value).unwrap();
+        if let Err(e) = load(org_id, json_val).await {
+            log::error!("load lookup table {} error: {}", item_key, e);
+        }
+    }
+    log::info!("[TRACE] Lookup tables Cached");
+    Ok(())
+}
@@ -26,12 +26,21 @@ use crate::meta::stream::StreamStats;
 pub mod alerts;
 pub mod compact;
 pub mod dashboard;
+pub mod delete_by_query;
+pub mod deletion;
 pub mod file_list;
 pub mod functions;
+pub mod lookup_tables;
+pub mod organization;
+pub mod reindex;
 pub mod schema;
+pub mod stream_aliases;
+pub mod stream_rename;
+pub mod stream_templates;
 pub mod triggers;
 pub mod udf;
 pub mod user;
+pub mod volume;
 
 pub async fn get_stream_stats(
     org_id: &str,
@@ -52,6 +61,18 @@ pub async fn get_stream_stats(
     }
 }
 
+pub async fn delete_stream_stats(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: &str,
+) -> Result<(), anyhow::Error> {
+    stats::remove_stream_stats(org_id, stream_name, stream_type);
+    let key = format!("/stats/{}/{}/{}", org_id, stream_type, stream_name);
+    let db = &crate::infra::db::DEFAULT;
+    let _ = db.delete(&key, false).await; // stats are not always persisted
+    Ok(())
+}
+
 pub async fn set_prom_cluster_info(
     cluster: &str,
     members: Vec<String>,
@@ -0,0 +1,51 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::json;
+use crate::meta::organization::OrganizationSettings;
+
+pub async fn get_settings(org_id: &str) -> Result<OrganizationSettings, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/organization/settings/{}", org_id);
+    let value = match db.get(&key).await {
+        Ok(val) => json::from_slice(&val)?,
+        Err(_) => OrganizationSettings::default(),
+    };
+    Ok(value)
+}
+
+pub async fn set_settings(
+    org_id: &str,
+    settings: &OrganizationSettings,
+) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/organization/settings/{}", org_id);
+    db.put(&key, json::to_vec(settings).unwrap().into()).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_settings() {
+        let settings = OrganizationSettings { data_retention: 30 };
+        set_settings("nexus", &settings).await.unwrap();
+        let resp = get_settings("nexus").await.unwrap();
+        assert_eq!(resp.data_retention, 30);
+        let resp = get_settings("no_settings").await.unwrap();
+        assert_eq!(resp.data_retention, 0);
+    }
+}
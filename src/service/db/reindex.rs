@@ -0,0 +1,60 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::json;
+use crate::meta::reindex::ReindexJob;
+
+pub async fn get(org_id: &str, id: &str) -> Result<Option<ReindexJob>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/reindex/{}/{}", org_id, id);
+    let value: Option<ReindexJob> = match db.get(&key).await {
+        Ok(val) => json::from_slice(&val)?,
+        Err(_) => None,
+    };
+    Ok(value)
+}
+
+pub async fn set(org_id: &str, job: &ReindexJob) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/reindex/{}/{}", org_id, job.id);
+    db.put(&key, json::to_vec(job).unwrap().into()).await?;
+    Ok(())
+}
+
+pub async fn list(org_id: &str) -> Result<Vec<ReindexJob>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/reindex/{}/", org_id);
+    let ret = db.list_values(&key).await?;
+    let mut jobs = Vec::with_capacity(ret.len());
+    for item_value in ret {
+        jobs.push(json::from_slice(&item_value)?);
+    }
+    Ok(jobs)
+}
+
+/// lists the jobs of all the organizations, with their organization
+pub async fn list_all() -> Result<Vec<(String, ReindexJob)>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let ret = db.list("/reindex/").await?;
+    let mut jobs = Vec::with_capacity(ret.len());
+    for (item_key, item_value) in ret {
+        // eg: /reindex/default/7034227391473467392
+        let org_id = match item_key.strip_prefix("/reindex/") {
+            Some(v) => v.split('/').next().unwrap_or_default().to_string(),
+            None => continue,
+        };
+        jobs.push((org_id, json::from_slice(&item_value)?));
+    }
+    Ok(jobs)
+}
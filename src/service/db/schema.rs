@@ -90,13 +90,15 @@ pub async fn get_versions(
     Ok(value)
 }
 
+/// Returns false when the fields are the same as the latest version and no
+/// new version was written
 pub async fn set(
     org_id: &str,
     stream_name: &str,
     stream_type: StreamType,
     schema: &Schema,
     min_ts: Option<i64>,
-) -> Result<(), anyhow::Error> {
+) -> Result<bool, anyhow::Error> {
     let db = &crate::infra::db::DEFAULT;
     let key = format!("/schema/{}/{}/{}", org_id, stream_type, stream_name);
     let mut versions: Vec<Schema>;
@@ -116,6 +118,8 @@ pub async fn set(
                 metadata.insert("start_dt".to_string(), min_ts.unwrap().to_string());
                 versions.push(schema.clone().with_metadata(metadata));
                 let _ = db.put(&key, json::to_vec(&versions).unwrap().into()).await;
+            } else {
+                return Ok(false);
             }
         } else {
             versions.pop().unwrap();
@@ -143,6 +147,39 @@ pub async fn set(
             )
             .await;
     }
+    Ok(true)
+}
+
+pub async fn delete(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/schema/{}/{}/{}", org_id, stream_type, stream_name);
+    let map_key = key.strip_prefix("/schema/").unwrap();
+    STREAM_SCHEMAS.remove(map_key);
+    match db.delete(&key, false).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+/// Writes all the schema versions at once, used when a stream is renamed or
+/// cloned
+pub async fn set_versions(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    versions: &[Schema],
+) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/schema/{}/{}/{}", org_id, stream_type, stream_name);
+    db.put(&key, json::to_vec(&versions).unwrap().into())
+        .await?;
+    // the watch event may arrive after the next read on this node
+    let map_key = key.strip_prefix("/schema/").unwrap();
+    STREAM_SCHEMAS.insert(map_key.to_string(), versions.to_vec());
     Ok(())
 }
 
@@ -0,0 +1,114 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::common::json;
+use crate::infra::config::STREAM_ALIASES;
+use crate::infra::db::Event;
+use crate::meta::stream::StreamAlias;
+use crate::meta::StreamType;
+
+pub async fn get(
+    org_id: &str,
+    stream_type: StreamType,
+    name: &str,
+) -> Result<Option<StreamAlias>, anyhow::Error> {
+    let map_key = format!("{}/{}/{}", org_id, stream_type, name);
+    if let Some(alias) = STREAM_ALIASES.get(&map_key) {
+        return Ok(Some(alias.clone()));
+    }
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_aliases/{}", map_key);
+    match db.get(&key).await {
+        Ok(val) => Ok(Some(json::from_slice(&val)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+pub async fn set(org_id: &str, alias: &StreamAlias) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!(
+        "/stream_aliases/{}/{}/{}",
+        org_id, alias.stream_type, alias.name
+    );
+    db.put(&key, json::to_vec(alias).unwrap().into()).await?;
+    Ok(())
+}
+
+pub async fn delete(
+    org_id: &str,
+    stream_type: StreamType,
+    name: &str,
+) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_aliases/{}/{}/{}", org_id, stream_type, name);
+    match db.delete(&key, false).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+pub async fn list(org_id: &str) -> Result<Vec<StreamAlias>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_aliases/{}/", org_id);
+    let ret = db.list_values(&key).await?;
+    let mut aliases = Vec::with_capacity(ret.len());
+    for item_value in ret {
+        aliases.push(json::from_slice(&item_value)?);
+    }
+    Ok(aliases)
+}
+
+pub async fn watch() -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = "/stream_aliases/";
+    let mut events = db.watch(key).await?;
+    let events = Arc::get_mut(&mut events).unwrap();
+    log::info!("[TRACE] Start watching stream aliases");
+    loop {
+        let ev = match events.recv().await {
+            Some(ev) => ev,
+            None => {
+                log::error!("watch_stream_aliases: event channel closed");
+                break;
+            }
+        };
+        match ev {
+            Event::Put(ev) => {
+                let item_key = ev.key.strip_prefix(key).unwrap();
+                let item_value: StreamAlias = json::from_slice(&ev.value.unwrap()).unwrap();
+                STREAM_ALIASES.insert(item_key.to_owned(), item_value);
+            }
+            Event::Delete(ev) => {
+                let item_key = ev.key.strip_prefix(key).unwrap();
+                STREAM_ALIASES.remove(item_key);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn cache() -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = "/stream_aliases/";
+    let ret = db.list(key).await?;
+    for (item_key, item_value) in ret {
+        let item_key = item_key.strip_prefix(key).unwrap();
+        let json_val: StreamAlias = json::from_slice(&item_value).unwrap();
+        STREAM_ALIASES.insert(item_key.to_owned(), json_val);
+    }
+    log::info!("[TRACE] Stream aliases Cached");
+    Ok(())
+}
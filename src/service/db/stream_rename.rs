@@ -0,0 +1,75 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::common::json;
+use crate::meta::stream::StreamRename;
+use crate::meta::StreamType;
+
+pub async fn get(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<Option<StreamRename>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_rename/{}/{}/{}", org_id, stream_type, stream_name);
+    let value: Option<StreamRename> = match db.get(&key).await {
+        Ok(val) => json::from_slice(&val)?,
+        Err(_) => None,
+    };
+    Ok(value)
+}
+
+pub async fn set(org_id: &str, rename: &StreamRename) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!(
+        "/stream_rename/{}/{}/{}",
+        org_id, rename.stream_type, rename.stream_name
+    );
+    db.put(&key, json::to_vec(rename).unwrap().into()).await?;
+    Ok(())
+}
+
+pub async fn delete(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_rename/{}/{}/{}", org_id, stream_type, stream_name);
+    match db.delete(&key, false).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[actix_web::test]
+    async fn test_stream_rename() {
+        let rename = StreamRename {
+            stream_name: "olympics".to_string(),
+            stream_type: StreamType::Logs,
+            new_name: "olympics_v2".to_string(),
+            created_at: 1667978841110,
+        };
+        set("nexus", &rename).await.unwrap();
+        let resp = get("nexus", "olympics", StreamType::Logs).await.unwrap();
+        assert_eq!(resp.unwrap().new_name, "olympics_v2");
+        delete("nexus", "olympics", StreamType::Logs).await.unwrap();
+        let resp = get("nexus", "olympics", StreamType::Logs).await.unwrap();
+        assert!(resp.is_none());
+    }
+}
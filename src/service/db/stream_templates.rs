@@ -0,0 +1,102 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use crate::common::json;
+use crate::infra::config::STREAM_TEMPLATES;
+use crate::infra::db::Event;
+use crate::meta::template::StreamTemplate;
+
+pub async fn get(org_id: &str, name: &str) -> Result<Option<StreamTemplate>, anyhow::Error> {
+    let map_key = format!("{}/{}", org_id, name);
+    if let Some(template) = STREAM_TEMPLATES.get(&map_key) {
+        return Ok(Some(template.clone()));
+    }
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_templates/{}/{}", org_id, name);
+    match db.get(&key).await {
+        Ok(val) => Ok(Some(json::from_slice(&val)?)),
+        Err(_) => Ok(None),
+    }
+}
+
+pub async fn set(org_id: &str, template: &StreamTemplate) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_templates/{}/{}", org_id, template.name);
+    db.put(&key, json::to_vec(template).unwrap().into()).await?;
+    Ok(())
+}
+
+pub async fn delete(org_id: &str, name: &str) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_templates/{}/{}", org_id, name);
+    match db.delete(&key, false).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+pub async fn list(org_id: &str) -> Result<Vec<StreamTemplate>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/stream_templates/{}/", org_id);
+    let ret = db.list_values(&key).await?;
+    let mut templates = Vec::with_capacity(ret.len());
+    for item_value in ret {
+        templates.push(json::from_slice(&item_value)?);
+    }
+    Ok(templates)
+}
+
+pub async fn watch() -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = "/stream_templates/";
+    let mut events = db.watch(key).await?;
+    let events = Arc::get_mut(&mut events).unwrap();
+    log::info!("[TRACE] Start watching stream templates");
+    loop {
+        let ev = match events.recv().await {
+            Some(ev) => ev,
+            None => {
+                log::error!("watch_stream_templates: event channel closed");
+                break;
+            }
+        };
+        match ev {
+            Event::Put(ev) => {
+                let item_key = ev.key.strip_prefix(key).unwrap();
+                let item_value: StreamTemplate = json::from_slice(&ev.value.unwrap()).unwrap();
+                STREAM_TEMPLATES.insert(item_key.to_owned(), item_value);
+            }
+            Event::Delete(ev) => {
+                let item_key = ev.key.strip_prefix(key).unwrap();
+                STREAM_TEMPLATES.remove(item_key);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub async fn cache() -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = "/stream_templates/";
+    let ret = db.list(key).await?;
+    for (item_key, item_value) in ret {
+        let item_key = item_key.strip_prefix(key).unwrap();
+        let json_val: StreamTemplate = json::from_slice(&item_value).unwrap();
+        STREAM_TEMPLATES.insert(item_key.to_owned(), json_val);
+    }
+    log::info!("[TRACE] Stream templates Cached");
+    Ok(())
+}
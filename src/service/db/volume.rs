@@ -0,0 +1,95 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::{TimeZone, Utc};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::common::json;
+use crate::infra::cluster::LOCAL_NODE_UUID;
+use crate::meta::stream::StreamVolume;
+use crate::meta::StreamType;
+
+// eg: /volume/default/logs/olympics/2022-10-03/{node_uuid}, holds the hourly
+// volumes of the day written by one node, so nodes never update the same key
+fn day(timestamp: i64) -> String {
+    Utc.timestamp_nanos(timestamp * 1000)
+        .format("%Y-%m-%d")
+        .to_string()
+}
+
+/// Adds the hourly volumes to the stream, stream_key: org_id/stream_type/stream_name
+pub async fn add(stream_key: &str, volumes: &[StreamVolume]) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let mut days: HashMap<String, Vec<&StreamVolume>> = HashMap::new();
+    for volume in volumes {
+        days.entry(day(volume.timestamp)).or_default().push(volume);
+    }
+    for (day, items) in days {
+        let key = format!("/volume/{}/{}/{}", stream_key, day, *LOCAL_NODE_UUID);
+        let mut hours: Vec<StreamVolume> = match db.get(&key).await {
+            Ok(val) => json::from_slice(&val)?,
+            Err(_) => vec![],
+        };
+        for item in items {
+            match hours.iter_mut().find(|v| v.timestamp == item.timestamp) {
+                Some(hour) => hour.add(item),
+                None => hours.push(*item),
+            }
+        }
+        hours.sort_by_key(|v| v.timestamp);
+        db.put(&key, json::to_vec(&hours).unwrap().into()).await?;
+    }
+    Ok(())
+}
+
+/// Lists the hourly volumes of the stream summed over all nodes
+pub async fn list(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<Vec<StreamVolume>, anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let key = format!("/volume/{}/{}/{}/", org_id, stream_type, stream_name);
+    let ret = db.list_values(&key).await?;
+    let mut hours: BTreeMap<i64, StreamVolume> = BTreeMap::new();
+    for item_value in ret {
+        let items: Vec<StreamVolume> = json::from_slice(&item_value)?;
+        for item in items {
+            hours
+                .entry(item.timestamp)
+                .or_insert_with(|| StreamVolume {
+                    timestamp: item.timestamp,
+                    ..Default::default()
+                })
+                .add(&item);
+        }
+    }
+    Ok(hours.into_values().collect())
+}
+
+/// Deletes the volumes of the days before the cutoff
+pub async fn delete_before(cutoff: i64) -> Result<(), anyhow::Error> {
+    let db = &crate::infra::db::DEFAULT;
+    let cutoff_day = day(cutoff);
+    let keys = db.list_keys("/volume/").await?;
+    for key in keys {
+        // eg: /volume/default/logs/olympics/2022-10-03/{node_uuid}
+        let columns = key.split('/').collect::<Vec<&str>>();
+        if columns.len() != 7 || columns[5] >= cutoff_day.as_str() {
+            continue;
+        }
+        db.delete(&key, false).await?;
+    }
+    Ok(())
+}
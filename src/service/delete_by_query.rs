@@ -0,0 +1,344 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use chrono::Utc;
+use datafusion::arrow::datatypes::Schema;
+use sqlparser::ast::{SetExpr, Statement};
+use sqlparser::parser::Parser;
+use std::io::Error;
+use std::sync::Arc;
+use tracing::info_span;
+
+use crate::common::time::time_range_condition;
+use crate::infra::config::CONFIG;
+use crate::infra::db::etcd;
+use crate::infra::storage::generate_partioned_file_key;
+use crate::infra::{ider, storage};
+use crate::meta::common::{FileKey, FileMeta};
+use crate::meta::delete_by_query::{
+    DeleteByQueryFile, DeleteByQueryList, DeleteByQueryReport, DeleteByQueryRequest,
+    DeleteByQueryStatus,
+};
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::StreamType;
+use crate::service::search::datafusion::exec::merge_parquet_files;
+use crate::service::stream::get_stream_setting_partition_time_level;
+use crate::service::{db, file_list, sidecar};
+
+pub async fn delete_by_query(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    user: &str,
+    req: DeleteByQueryRequest,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:delete_by_query:create");
+    let _guard = loc_span.enter();
+    let sql_where = match validate_request(&req) {
+        Ok(v) => v,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                Some(e.to_string()),
+            )))
+        }
+    };
+    let schema = db::schema::get(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    if schema == Schema::empty() {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("stream not found".to_owned()),
+        )));
+    }
+
+    let now = Utc::now().timestamp_micros();
+    let report = DeleteByQueryReport {
+        id: ider::generate(),
+        stream_name: stream_name.to_string(),
+        stream_type,
+        start_time: req.start_time,
+        end_time: req.end_time,
+        sql_where,
+        user: user.to_string(),
+        status: DeleteByQueryStatus::Running,
+        files_scanned: 0,
+        files_rewritten: 0,
+        files_deleted: 0,
+        removed_records: 0,
+        files: vec![],
+        error: None,
+        created_at: now,
+        updated_at: now,
+    };
+    if let Err(e) = db::delete_by_query::set(org_id, &report).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        );
+    }
+    let org_id = org_id.to_string();
+    let resp = report.clone();
+    tokio::task::spawn(async move { run(org_id, report).await });
+
+    Ok(HttpResponse::Accepted().json(resp))
+}
+
+pub async fn get_report(org_id: &str, id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:delete_by_query:get");
+    let _guard = loc_span.enter();
+    match db::delete_by_query::get(org_id, id).await {
+        Ok(Some(report)) => Ok(HttpResponse::Ok().json(report)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("delete by query job not found".to_owned()),
+        ))),
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
+pub async fn list_reports(org_id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:delete_by_query:list");
+    let _guard = loc_span.enter();
+    match db::delete_by_query::list(org_id).await {
+        Ok(mut list) => {
+            list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            Ok(HttpResponse::Ok().json(DeleteByQueryList { list }))
+        }
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
+/// validates the request, returns the normalized WHERE predicate
+fn validate_request(req: &DeleteByQueryRequest) -> Result<String, anyhow::Error> {
+    if req.start_time <= 0 || req.end_time <= req.start_time {
+        return Err(anyhow::anyhow!("invalid time range"));
+    }
+    if req.sql_where.trim().is_empty() {
+        return Err(anyhow::anyhow!("sql_where is required"));
+    }
+    // the predicate must stay a single WHERE expression
+    let dialect = sqlparser::dialect::GenericDialect {};
+    let sql = format!("SELECT * FROM tbl WHERE {}", req.sql_where);
+    let statements = match Parser::parse_sql(&dialect, &sql) {
+        Ok(v) => v,
+        Err(e) => return Err(anyhow::anyhow!("sql_where is invalid: {}", e)),
+    };
+    if let [Statement::Query(query)] = statements.as_slice() {
+        if query.order_by.is_empty() && query.limit.is_none() && query.offset.is_none() {
+            if let SetExpr::Select(select) = query.body.as_ref() {
+                if let Some(selection) = &select.selection {
+                    return Ok(selection.to_string());
+                }
+            }
+        }
+    }
+    Err(anyhow::anyhow!("sql_where must be a single predicate"))
+}
+
+async fn run(org_id: String, mut report: DeleteByQueryReport) {
+    match delete_records(&org_id, &mut report).await {
+        Ok(_) => report.status = DeleteByQueryStatus::Completed,
+        Err(e) => {
+            log::error!("[DELETE_BY_QUERY] job {} error: {}", report.id, e);
+            report.status = DeleteByQueryStatus::Failed;
+            report.error = Some(e.to_string());
+        }
+    }
+    report.updated_at = Utc::now().timestamp_micros();
+    if let Err(e) = db::delete_by_query::set(&org_id, &report).await {
+        log::error!("[DELETE_BY_QUERY] save job {} error: {}", report.id, e);
+    }
+    log::info!(
+        "[DELETE_BY_QUERY] job {} on {}/{}/{} done, files rewritten: {}, deleted: {}, records removed: {}",
+        report.id,
+        org_id,
+        report.stream_type,
+        report.stream_name,
+        report.files_rewritten,
+        report.files_deleted,
+        report.removed_records
+    );
+}
+
+/// delete by query steps:
+/// 1. get a cluster lock for compactor stream, files must not be merged meanwhile
+/// 2. find the files overlapping the time range from the file list
+/// 3. rewrite each file without the matching records
+/// 4. upload the new file, swap the file keys in one file list, send broadcast
+/// 5. delete the old file from storage
+/// 6. release cluster lock
+async fn delete_records(
+    org_id: &str,
+    report: &mut DeleteByQueryReport,
+) -> Result<(), anyhow::Error> {
+    let stream_name = report.stream_name.clone();
+    let stream_type = report.stream_type;
+    let mut locker = None;
+    if !CONFIG.common.local_mode {
+        let lock_key = format!("compactor/files/{}/{}/{}", org_id, stream_type, stream_name);
+        let mut lock = etcd::Locker::new(&lock_key);
+        lock.lock(CONFIG.etcd.command_timeout).await?;
+        locker = Some(lock);
+    }
+    let ret = rewrite_files(org_id, report).await;
+    if locker.is_some() {
+        // release cluster lock
+        let mut lock = locker.unwrap();
+        lock.unlock().await?;
+    }
+    ret
+}
+
+async fn rewrite_files(
+    org_id: &str,
+    report: &mut DeleteByQueryReport,
+) -> Result<(), anyhow::Error> {
+    let stream_name = report.stream_name.clone();
+    let stream_type = report.stream_type;
+    let stream_schema = db::schema::get(org_id, &stream_name, Some(stream_type)).await?;
+    let partition_time_level = get_stream_setting_partition_time_level(&stream_schema);
+    let schema = Arc::new(
+        stream_schema
+            .clone()
+            .with_metadata(std::collections::HashMap::new()),
+    );
+    let exclude = format!(
+        "({}) AND {}",
+        report.sql_where,
+        time_range_condition(&schema, report.start_time, report.end_time)
+    );
+
+    let files = file_list::get_file_list(
+        org_id,
+        &stream_name,
+        Some(stream_type),
+        report.start_time,
+        report.end_time,
+    )
+    .await?;
+    let storage = &storage::DEFAULT;
+    for file in files {
+        tokio::task::yield_now().await; // yield to other tasks
+        let file_meta = file_list::get_file_meta(&file).await?;
+        if file_meta.max_ts < report.start_time || file_meta.min_ts >= report.end_time {
+            continue;
+        }
+        report.files_scanned += 1;
+
+        let mut buf = Vec::new();
+        let mut new_file_meta =
+            merge_parquet_files(&mut buf, schema.clone(), &[file.clone()], Some(&exclude)).await?;
+        if new_file_meta.records >= file_meta.records {
+            continue; // no record matched
+        }
+        let removed_records = file_meta.records - new_file_meta.records;
+
+        // eg: files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet
+        let hour = file
+            .split('/')
+            .skip(4)
+            .take(4)
+            .collect::<Vec<_>>()
+            .join("/");
+        let mut events = Vec::with_capacity(2);
+        let mut new_file = None;
+        if new_file_meta.records > 0 {
+            // the original size is not known after rewrite, estimate it by records
+            new_file_meta.original_size =
+                file_meta.original_size * new_file_meta.records / file_meta.records;
+            new_file_meta.compressed_size = buf.len() as u64;
+            let new_file_key = generate_partioned_file_key(
+                org_id,
+                &stream_name,
+                stream_type,
+                new_file_meta.min_ts,
+                partition_time_level,
+                &CONFIG.common.file_ext_parquet,
+            );
+            let new_file_key = format!("files/{}{}", new_file_key.0, new_file_key.1);
+            storage.put(&new_file_key, buf.into()).await?;
+            events.push(FileKey {
+                key: new_file_key.clone(),
+                meta: new_file_meta,
+                deleted: false,
+            });
+            new_file = Some(new_file_key);
+        }
+        events.push(FileKey {
+            key: file.clone(),
+            meta: FileMeta::default(),
+            deleted: true,
+        });
+        db::file_list::set_events(&hour, &events).await?;
+        if let Err(e) = storage.del(&file).await {
+            log::error!("[DELETE_BY_QUERY] delete file {} failed: {}", file, e);
+        }
+        sidecar::delete(&file).await;
+
+        match new_file {
+            Some(_) => report.files_rewritten += 1,
+            None => report.files_deleted += 1,
+        }
+        report.removed_records += removed_records;
+        report.files.push(DeleteByQueryFile {
+            file,
+            new_file,
+            records: file_meta.records,
+            removed_records,
+        });
+        report.updated_at = Utc::now().timestamp_micros();
+        db::delete_by_query::set(org_id, report).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_request() {
+        let mut req = DeleteByQueryRequest {
+            start_time: 1667978841110,
+            end_time: 1667978845354,
+            sql_where: "user_id = 'u-123'".to_string(),
+        };
+        assert_eq!(validate_request(&req).unwrap(), "user_id = 'u-123'");
+        req.sql_where = "1=1; DROP TABLE tbl".to_string();
+        assert!(validate_request(&req).is_err());
+        req.sql_where = "1=1 LIMIT 10".to_string();
+        assert!(validate_request(&req).is_err());
+        req.sql_where = "".to_string();
+        assert!(validate_request(&req).is_err());
+        req.sql_where = "user_id = 'u-123'".to_string();
+        req.end_time = req.start_time;
+        assert!(validate_request(&req).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use datafusion::arrow::datatypes::{DataType, Schema};
+use std::collections::HashMap;
+use std::io::Error;
+use std::sync::Arc;
+use tracing::info_span;
+
+use crate::common::json;
+use crate::common::str::hash64;
+use crate::common::time::time_range_condition;
+use crate::infra::config::CONFIG;
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::stream::{FieldStats, FieldStatsRequest, FieldValueCount};
+use crate::meta::StreamType;
+use crate::service::search::datafusion::exec::query_parquet_files;
+use crate::service::{db, file_list};
+
+const MAX_TOP_VALUES: usize = 100;
+const SAMPLE_BUCKETS: u64 = 10000;
+
+pub async fn get_field_stats(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    field: &str,
+    req: FieldStatsRequest,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:field_stats:get");
+    let _guard = loc_span.enter();
+    if let Err(e) = validate_request(&req) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(e.to_string()),
+        )));
+    }
+    let schema = db::schema::get(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    if schema == Schema::empty() {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("stream not found".to_owned()),
+        )));
+    }
+    let data_type = match schema.field_with_name(field) {
+        Ok(f) => f.data_type().clone(),
+        Err(_) => {
+            return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+                http::StatusCode::NOT_FOUND.into(),
+                Some(format!("field {} not found", field)),
+            )))
+        }
+    };
+
+    match compute_field_stats(
+        org_id,
+        stream_name,
+        stream_type,
+        field,
+        &data_type,
+        schema,
+        &req,
+    )
+    .await
+    {
+        Ok(stats) => Ok(HttpResponse::Ok().json(stats)),
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
+fn validate_request(req: &FieldStatsRequest) -> Result<(), anyhow::Error> {
+    if req.start_time <= 0 || req.end_time <= req.start_time {
+        return Err(anyhow::anyhow!("invalid time range"));
+    }
+    if req.top == 0 || req.top > MAX_TOP_VALUES {
+        return Err(anyhow::anyhow!(
+            "top must be between 1 and {}",
+            MAX_TOP_VALUES
+        ));
+    }
+    if !(req.sample_ratio > 0.0 && req.sample_ratio <= 1.0) {
+        return Err(anyhow::anyhow!("sample_ratio must be in (0, 1]"));
+    }
+    Ok(())
+}
+
+async fn compute_field_stats(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    field: &str,
+    data_type: &DataType,
+    schema: Schema,
+    req: &FieldStatsRequest,
+) -> Result<FieldStats, anyhow::Error> {
+    let mut stats = FieldStats {
+        field: field.to_string(),
+        field_type: data_type.to_string(),
+        start_time: req.start_time,
+        end_time: req.end_time,
+        files: 0,
+        sample_ratio: req.sample_ratio,
+        count: 0,
+        null_count: 0,
+        null_ratio: 0.0,
+        distinct_count: 0,
+        min: None,
+        max: None,
+        top_values: vec![],
+    };
+
+    let files = file_list::get_file_list(
+        org_id,
+        stream_name,
+        Some(stream_type),
+        req.start_time,
+        req.end_time,
+    )
+    .await?;
+    let mut matched_files = Vec::with_capacity(files.len());
+    for file in files {
+        let file_meta = file_list::get_file_meta(&file).await?;
+        if file_meta.min_ts > 0
+            && (file_meta.max_ts < req.start_time || file_meta.min_ts >= req.end_time)
+        {
+            continue;
+        }
+        matched_files.push(file);
+    }
+    let files = sample_files(matched_files, req.sample_ratio);
+    if files.is_empty() {
+        return Ok(stats);
+    }
+    stats.files = files.len();
+
+    let schema = Arc::new(schema.with_metadata(HashMap::new()));
+    let files_group = group_files_by_version(org_id, stream_name, stream_type, files).await?;
+    let queries = stats_queries(
+        field,
+        data_type,
+        &time_range_condition(&schema, req.start_time, req.end_time),
+        req,
+    );
+    let mut results = query_parquet_files(schema, &files_group, &queries).await?;
+    let top_rows = results.pop().unwrap_or_default();
+    let summary = results.pop().unwrap_or_default();
+    if let Some(row) = summary.first() {
+        stats.count = row["records"].as_u64().unwrap_or_default();
+        let non_null = row["non_null"].as_u64().unwrap_or_default();
+        stats.null_count = stats.count - non_null.min(stats.count);
+        if stats.count > 0 {
+            stats.null_ratio = stats.null_count as f64 / stats.count as f64;
+        }
+        stats.distinct_count = row["distinct_values"].as_u64().unwrap_or_default();
+        stats.min = row.get("min_value").filter(|v| !v.is_null()).cloned();
+        stats.max = row.get("max_value").filter(|v| !v.is_null()).cloned();
+    }
+    stats.top_values = top_rows
+        .into_iter()
+        .map(|row| FieldValueCount {
+            value: row.get("value").cloned().unwrap_or(json::Value::Null),
+            count: row["num"].as_u64().unwrap_or_default(),
+        })
+        .collect();
+    Ok(stats)
+}
+
+/// groups the files by the schema version they were written with
+async fn group_files_by_version(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    files: Vec<String>,
+) -> Result<Vec<(Arc<Schema>, Vec<String>)>, anyhow::Error> {
+    let schema_versions = db::schema::get_versions(org_id, stream_name, Some(stream_type)).await?;
+    let schema_latest_id = schema_versions.len() - 1;
+    let mut files_group: HashMap<usize, Vec<String>> = HashMap::new();
+    if !CONFIG.common.widening_schema_evoluation || schema_versions.len() == 1 {
+        files_group.insert(schema_latest_id, files);
+    } else {
+        for file in files {
+            let file_meta = file_list::get_file_meta(&file).await.unwrap_or_default();
+            let ver = db::schema::filter_schema_version_id(
+                &schema_versions,
+                file_meta.min_ts,
+                file_meta.max_ts,
+            )
+            .unwrap_or(schema_latest_id);
+            files_group.entry(ver).or_default().push(file);
+        }
+    }
+    Ok(files_group
+        .into_iter()
+        .map(|(ver, files)| {
+            let schema = schema_versions[ver].clone().with_metadata(HashMap::new());
+            (Arc::new(schema), files)
+        })
+        .collect())
+}
+
+/// keeps a stable subset of the files, at least one file is kept
+fn sample_files(files: Vec<String>, sample_ratio: f64) -> Vec<String> {
+    if sample_ratio >= 1.0 || files.is_empty() {
+        return files;
+    }
+    let threshold = (sample_ratio * SAMPLE_BUCKETS as f64) as u64;
+    let first = files[0].clone();
+    let sampled = files
+        .into_iter()
+        .filter(|file| hash64(file) % SAMPLE_BUCKETS < threshold)
+        .collect::<Vec<_>>();
+    if sampled.is_empty() {
+        vec![first]
+    } else {
+        sampled
+    }
+}
+
+/// the summary query and the top values query, `time_range` is the condition
+/// of the time column
+fn stats_queries(
+    field: &str,
+    data_type: &DataType,
+    time_range: &str,
+    req: &FieldStatsRequest,
+) -> Vec<String> {
+    let column = format!("\"{}\"", field.replace('"', "\"\""));
+    let distinct = match data_type {
+        DataType::Boolean => format!("COUNT(DISTINCT {})", column),
+        _ => format!("APPROX_DISTINCT({})", column),
+    };
+    let min_max = if is_numeric(data_type) {
+        format!(
+            ", MIN({}) AS \"min_value\", MAX({}) AS \"max_value\"",
+            column, column
+        )
+    } else {
+        String::new()
+    };
+    vec![
+        format!(
+            "SELECT COUNT(*) AS \"records\", COUNT({}) AS \"non_null\", {} AS \"distinct_values\"{} FROM tbl WHERE {}",
+            column, distinct, min_max, time_range
+        ),
+        format!(
+            "SELECT {} AS \"value\", COUNT(*) AS \"num\" FROM tbl WHERE {} AND {} IS NOT NULL GROUP BY {} ORDER BY \"num\" DESC LIMIT {}",
+            column, time_range, column, column, req.top
+        ),
+    ]
+}
+
+fn is_numeric(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Int8
+            | DataType::Int16
+            | DataType::Int32
+            | DataType::Int64
+            | DataType::UInt8
+            | DataType::UInt16
+            | DataType::UInt32
+            | DataType::UInt64
+            | DataType::Float16
+            | DataType::Float32
+            | DataType::Float64
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_files() {
+        let files = (0..1000)
+            .map(|i| format!("files/default/logs/olympics/2022/10/03/10/{}.parquet", i))
+            .collect::<Vec<_>>();
+        assert_eq!(sample_files(files.clone(), 1.0).len(), 1000);
+        let sampled = sample_files(files.clone(), 0.1);
+        assert!(!sampled.is_empty() && sampled.len() < 200);
+        assert_eq!(sampled, sample_files(files, 0.1));
+        let one = vec!["files/default/logs/olympics/2022/10/03/10/1.parquet".to_string()];
+        assert_eq!(sample_files(one.clone(), 0.0001), one);
+    }
+
+    #[test]
+    fn test_stats_queries() {
+        let req = FieldStatsRequest {
+            start_time: 1,
+            end_time: 2,
+            top: 5,
+            sample_ratio: 1.0,
+        };
+        let queries = stats_queries("code", &DataType::Int64, "_timestamp >= 1", &req);
+        assert!(queries[0].contains("MIN(\"code\")"));
+        assert!(queries[1].ends_with("LIMIT 5"));
+        let queries = stats_queries("level", &DataType::Utf8, "_timestamp >= 1", &req);
+        assert!(!queries[0].contains("MIN("));
+        assert!(queries[0].contains("APPROX_DISTINCT(\"level\")"));
+    }
+}
@@ -16,6 +16,8 @@ use crate::common;
 use crate::infra::cache::file_list;
 use crate::meta::common::FileMeta;
 use crate::meta::StreamType;
+use crate::service::db;
+use crate::service::stream::get_stream_setting_partition_time_level;
 
 #[inline]
 #[tracing::instrument(name = "service:file_list:get_file_list")]
@@ -30,7 +32,16 @@ pub async fn get_file_list(
         Some(v) => v,
         None => StreamType::Logs,
     };
-    file_list::get_file_list(org_id, stream_name, stream_type_loc, time_min, time_max).await
+    let schema = db::schema::get(org_id, stream_name, Some(stream_type_loc)).await?;
+    file_list::get_file_list(
+        org_id,
+        stream_name,
+        stream_type_loc,
+        time_min,
+        time_max,
+        get_stream_setting_partition_time_level(&schema),
+    )
+    .await
 }
 
 #[inline]
@@ -0,0 +1,278 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use datafusion::arrow::array::{Array, StringArray};
+use datafusion::arrow::compute::cast;
+use datafusion::arrow::datatypes::{DataType, Schema};
+use datafusion::arrow::record_batch::RecordBatch;
+use fst::automaton::{Automaton, Str};
+use fst::{IntoStreamer, Set, SetBuilder, Streamer};
+use sqlparser::ast::{Expr as SqlExpr, FunctionArg, FunctionArgExpr, Value as SqlValue};
+use std::collections::BTreeSet;
+
+use crate::common::json;
+use crate::common::sql::conjuncts;
+use crate::service::search::sql::{fulltext_search_fields, Sql};
+use crate::service::sidecar;
+use crate::service::stream::get_stream_setting_fulltext_index;
+
+/// longer suffixes are cut, a term token is then matched by its start only
+const MAX_SUFFIX_CHARS: usize = 64;
+
+/// Tokens of the full text search fields of a parquet file, stored next to
+/// the file as `<file>.idx`
+#[derive(Clone, Debug)]
+pub struct FulltextIndex {
+    /// the fields the tokens were taken from
+    pub fields: Vec<String>,
+    /// the suffixes of the lowercase tokens, a term token is part of a token
+    /// when it starts one of its suffixes
+    pub suffixes: Set<Bytes>,
+}
+
+impl FulltextIndex {
+    fn new(fields: Vec<String>, suffixes: BTreeSet<String>) -> Option<Self> {
+        let mut builder = SetBuilder::memory();
+        builder.extend_iter(suffixes).ok()?;
+        let data = builder.into_inner().ok()?;
+        Some(FulltextIndex {
+            fields,
+            suffixes: Set::new(Bytes::from(data)).ok()?,
+        })
+    }
+
+    /// Whether a file with this index may match `LIKE '%term%'` on the
+    /// indexed fields, every token of the term has to be part of a token of
+    /// the file
+    pub fn may_contain(&self, term: &str) -> bool {
+        let mut term_tokens = BTreeSet::new();
+        tokenize(term, &mut term_tokens);
+        term_tokens.iter().all(|term_token| {
+            let prefix = truncate(term_token);
+            let mut stream = self
+                .suffixes
+                .search(Str::new(prefix).starts_with())
+                .into_stream();
+            stream.next().is_some()
+        })
+    }
+
+    /// the length of the json of the fields, the fields and the fst
+    fn encode(&self) -> Result<Vec<u8>, anyhow::Error> {
+        let fields = json::to_vec(&self.fields)?;
+        let mut data = Vec::with_capacity(4 + fields.len() + self.suffixes.as_fst().size());
+        data.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+        data.extend_from_slice(&fields);
+        data.extend_from_slice(self.suffixes.as_fst().as_bytes());
+        Ok(data)
+    }
+
+    fn decode(data: Bytes) -> Option<Self> {
+        let len = u32::from_le_bytes(data.get(..4)?.try_into().ok()?) as usize;
+        let fields = json::from_slice(data.get(4..4 + len)?).ok()?;
+        let suffixes = Set::new(data.slice(4 + len..)).ok()?;
+        Some(FulltextIndex { fields, suffixes })
+    }
+}
+
+pub fn index_key(file: &str) -> String {
+    format!("{}.idx", file)
+}
+
+/// Builds the index of the records of a file, None when the index is not
+/// enabled for the stream
+pub fn build(schema: &Schema, batches: &[RecordBatch]) -> Option<FulltextIndex> {
+    if !get_stream_setting_fulltext_index(schema) {
+        return None;
+    }
+    let fields = fulltext_search_fields(schema);
+    let mut tokens = BTreeSet::new();
+    for batch in batches {
+        for field in fields.iter() {
+            let column = match batch.column_by_name(field) {
+                Some(column) => column,
+                None => continue,
+            };
+            // the field may have another type in the older records
+            let column = match cast(column, &DataType::Utf8) {
+                Ok(column) => column,
+                Err(_) => return None,
+            };
+            let values = column.as_any().downcast_ref::<StringArray>().unwrap();
+            for value in values.iter().flatten() {
+                tokenize(value, &mut tokens);
+            }
+        }
+    }
+    let mut suffixes = BTreeSet::new();
+    for token in tokens.iter() {
+        for (i, _) in token.char_indices() {
+            suffixes.insert(truncate(&token[i..]).to_string());
+        }
+    }
+    FulltextIndex::new(fields, suffixes)
+}
+
+/// Merges the indexes of the files compacted into one, None when a file has
+/// no index
+pub async fn merge(files: &[String]) -> Option<FulltextIndex> {
+    let mut fields: Option<BTreeSet<String>> = None;
+    let mut indexes = Vec::with_capacity(files.len());
+    for file in files {
+        let index = get(file).await?;
+        // a field is covered only when it is covered in every file
+        let index_fields = index.fields.iter().cloned().collect::<BTreeSet<_>>();
+        fields = Some(match fields {
+            Some(fields) => fields.intersection(&index_fields).cloned().collect(),
+            None => index_fields,
+        });
+        indexes.push(index);
+    }
+    let mut suffixes = BTreeSet::new();
+    let mut union = indexes
+        .iter()
+        .fold(fst::set::OpBuilder::new(), |op, index| {
+            op.add(&index.suffixes)
+        })
+        .union();
+    while let Some(suffix) = union.next() {
+        suffixes.insert(String::from_utf8_lossy(suffix).to_string());
+    }
+    FulltextIndex::new(fields.unwrap_or_default().into_iter().collect(), suffixes)
+}
+
+pub async fn get(file: &str) -> Option<FulltextIndex> {
+    sidecar::get(&index_key(file))
+        .await
+        .and_then(FulltextIndex::decode)
+}
+
+pub async fn put(file: &str, index: &FulltextIndex) {
+    match index.encode() {
+        Ok(data) => sidecar::put(&index_key(file), &data).await,
+        Err(e) => log::error!("[FULLTEXT_INDEX] encode index of {} failed: {}", file, e),
+    }
+}
+
+/// Drops the files the index of which rules out a term every result must
+/// contain, files without index are kept
+pub async fn filter_files(sql: &Sql, files: Vec<String>) -> Vec<String> {
+    if !get_stream_setting_fulltext_index(&sql.schema) {
+        return files;
+    }
+    let terms = match sql.meta.selection.as_ref() {
+        Some(selection) => required_terms(selection),
+        None => return files,
+    };
+    if terms.is_empty() {
+        return files;
+    }
+    let fields = fulltext_search_fields(&sql.schema);
+    let file_num = files.len();
+    let files = sidecar::filter_files(files, index_key, FulltextIndex::decode, |index| {
+        !fields.iter().all(|field| index.fields.contains(field))
+            || terms.iter().all(|term| index.may_contain(term))
+    })
+    .await;
+    log::info!(
+        "[FULLTEXT_INDEX] {} of {} files may contain {:?}",
+        files.len(),
+        file_num,
+        terms
+    );
+    files
+}
+
+/// The terms of the match_all calls the where clause is a conjunction of
+fn required_terms(expr: &SqlExpr) -> Vec<String> {
+    let mut terms = Vec::new();
+    for expr in conjuncts(expr) {
+        let f = match expr {
+            SqlExpr::Function(f) => f,
+            _ => continue,
+        };
+        let name = f.name.to_string().to_lowercase();
+        if name != "match_all" && name != "match_all_ignore_case" {
+            continue;
+        }
+        if let Some(FunctionArg::Unnamed(FunctionArgExpr::Expr(SqlExpr::Value(
+            SqlValue::SingleQuotedString(term),
+        )))) = f.args.first()
+        {
+            terms.push(term.to_string());
+        }
+    }
+    terms
+}
+
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(MAX_SUFFIX_CHARS) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+/// Splits the text into lowercase tokens of letters and digits
+fn tokenize(text: &str, tokens: &mut BTreeSet<String>) {
+    for token in text.split(|c: char| !c.is_alphanumeric()) {
+        if !token.is_empty() {
+            tokens.insert(token.to_lowercase());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::sql::parse_selection;
+    use datafusion::arrow::datatypes::Field;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_may_contain() {
+        let schema = Schema::new(vec![Field::new("log", DataType::Utf8, true)]).with_metadata(
+            HashMap::from([(
+                "settings".to_string(),
+                r#"{"fulltext_index":true}"#.to_string(),
+            )]),
+        );
+        let long = "x".repeat(100);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![Arc::new(StringArray::from(vec![
+                "GET /api/users?id=42 failed: Connection_Reset".to_string(),
+                long.clone(),
+            ]))],
+        )
+        .unwrap();
+        let index = build(&schema, &[batch]).unwrap();
+        let index = FulltextIndex::decode(Bytes::from(index.encode().unwrap())).unwrap();
+        assert_eq!(index.fields, vec!["log"]);
+        assert!(index.may_contain("connection"));
+        assert!(index.may_contain("api/user"));
+        assert!(index.may_contain("nection_res"));
+        assert!(index.may_contain("--"));
+        assert!(!index.may_contain("timeout"));
+        assert!(!index.may_contain("users timeout"));
+        assert!(index.may_contain(&long[1..]));
+    }
+
+    #[test]
+    fn test_required_terms() {
+        let selection = parse_selection("SELECT * FROM tbl WHERE match_all('error') AND (level = 'x' AND match_all_ignore_case('Timeout')) AND (match_all('a') OR match_all('b')) AND NOT match_all('c')");
+        assert_eq!(required_terms(&selection), vec!["error", "Timeout"]);
+    }
+}
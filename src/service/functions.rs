@@ -140,7 +140,7 @@ pub async fn delete_function(
     }
 }
 
-fn extract_num_args(trans: &mut Transform) {
+pub fn extract_num_args(trans: &mut Transform) {
     let js_func = trans.function.to_owned();
     let start_stream = js_func.find('(').unwrap();
     let end_stream = js_func.find(')').unwrap();
@@ -22,8 +22,15 @@ use mlua::{Function, Lua};
 use prometheus::GaugeVec;
 use serde_json::Value;
 use std::io::{BufRead, BufReader, Error};
+use std::sync::Arc;
 
-use super::StreamMeta;
+use super::defined_schema::DefinedSchema;
+use super::derived;
+use super::enrichment::Enrichments;
+use super::nested::NestedFields;
+use super::quality::{self, QualityResult, QualityRules, RoutedStream};
+use super::sampling::SamplingRules;
+use super::{PartitionKey, StreamMeta};
 use crate::common::json;
 use crate::infra::cluster;
 use crate::infra::config::CONFIG;
@@ -35,9 +42,15 @@ use crate::meta::http::HttpResponse as MetaHttpResponse;
 use crate::meta::ingestion::{
     IngestionResponse, RecordStatus, StreamData, StreamSchemaChk, StreamStatus,
 };
+use crate::meta::prom::DerivedMetric;
+use crate::meta::stream::PartitionTimeLevel;
 use crate::meta::StreamType;
 use crate::service::schema::stream_schema_exists;
-use crate::{common::time::parse_timestamp_micro_from_value, meta::alert::Trigger};
+use crate::service::stream::get_stream_setting_derived_metrics;
+use crate::{
+    common::time::{parse_timestamp_micro_from_value, wal_time_value},
+    meta::alert::Trigger,
+};
 
 pub async fn ingest(
     org_id: &str,
@@ -66,9 +79,19 @@ pub async fn ingest(
     let mut stream_data_map = AHashMap::new();
     #[cfg(feature = "zo_functions")]
     let mut stream_tansform_map: AHashMap<String, Vec<Transform>> = AHashMap::new();
-    let mut stream_partition_keys_map: AHashMap<String, (StreamSchemaChk, Vec<String>)> =
-        AHashMap::new();
+    let mut stream_partition_keys_map: AHashMap<
+        String,
+        (StreamSchemaChk, PartitionTimeLevel, Vec<PartitionKey>),
+    > = AHashMap::new();
     let mut stream_alerts_map: AHashMap<String, Vec<Alert>> = AHashMap::new();
+    let mut stream_enrichment_map: AHashMap<String, Enrichments> = AHashMap::new();
+    let mut stream_nested_map: AHashMap<String, NestedFields> = AHashMap::new();
+    let mut stream_defined_schema_map: AHashMap<String, DefinedSchema> = AHashMap::new();
+    let mut stream_derived_map: AHashMap<String, Vec<DerivedMetric>> = AHashMap::new();
+    let mut stream_sampling_map: AHashMap<String, SamplingRules> = AHashMap::new();
+    let mut stream_quality_map: AHashMap<String, Arc<QualityRules>> = AHashMap::new();
+    let mut stream_keep_nanos_map: AHashMap<String, bool> = AHashMap::new();
+    let mut routed: AHashMap<String, RoutedStream> = AHashMap::new();
 
     let mut stream_name = String::from("");
     let mut stream_trigger_map: AHashMap<String, Trigger> = AHashMap::new();
@@ -120,7 +143,7 @@ pub async fn ingest(
                     &mut stream_schema_map,
                 )
                 .await;
-                let mut partition_keys: Vec<String> = vec![];
+                let mut partition_keys: Vec<PartitionKey> = vec![];
                 if stream_schema.has_partition_keys {
                     partition_keys = super::get_stream_partition_keys(
                         stream_name.clone(),
@@ -128,8 +151,46 @@ pub async fn ingest(
                     )
                     .await;
                 }
-                stream_partition_keys_map
-                    .insert(stream_name.clone(), (stream_schema, partition_keys.clone()));
+                let partition_time_level =
+                    super::get_stream_partition_time_level(&stream_name, &stream_schema_map);
+                stream_partition_keys_map.insert(
+                    stream_name.clone(),
+                    (stream_schema, partition_time_level, partition_keys.clone()),
+                );
+                let enrichments =
+                    Enrichments::from_schema(org_id, stream_schema_map.get(&stream_name));
+                stream_defined_schema_map.insert(
+                    stream_name.clone(),
+                    DefinedSchema::from_schema(stream_schema_map.get(&stream_name))
+                        .with_enrichments(&enrichments),
+                );
+                stream_enrichment_map.insert(stream_name.clone(), enrichments);
+                stream_nested_map.insert(
+                    stream_name.clone(),
+                    NestedFields::from_schema(stream_schema_map.get(&stream_name)),
+                );
+                if let Some(schema) = stream_schema_map.get(&stream_name) {
+                    stream_derived_map.insert(
+                        stream_name.clone(),
+                        get_stream_setting_derived_metrics(schema),
+                    );
+                }
+                stream_sampling_map.insert(
+                    stream_name.clone(),
+                    SamplingRules::from_schema(stream_schema_map.get(&stream_name)),
+                );
+                stream_quality_map.insert(
+                    stream_name.clone(),
+                    QualityRules::from_schema(
+                        org_id,
+                        &stream_name,
+                        stream_schema_map.get(&stream_name),
+                    ),
+                );
+                stream_keep_nanos_map.insert(
+                    stream_name.clone(),
+                    super::get_stream_keep_nanos(&stream_name, &stream_schema_map),
+                );
             }
 
             stream_data_map
@@ -171,7 +232,10 @@ pub async fn ingest(
             //End row based transform
 
             //JSON Flattening
-            let mut value = json::flatten_json(&value);
+            let mut value = match stream_nested_map.get(&stream_name) {
+                Some(nested_fields) => nested_fields.flatten(&value),
+                None => json::flatten_json(&value),
+            };
             // get json object
             let local_val = value.as_object_mut().unwrap();
 
@@ -206,19 +270,88 @@ pub async fn ingest(
             if timestamp < min_ts {
                 min_ts = timestamp;
             }
+            let time_value = wal_time_value(
+                local_val.get(&CONFIG.common.time_stamp_col),
+                timestamp,
+                stream_keep_nanos_map
+                    .get(&stream_name)
+                    .copied()
+                    .unwrap_or_default(),
+            );
             local_val.insert(
                 CONFIG.common.time_stamp_col.clone(),
-                Value::Number(timestamp.into()),
+                Value::Number(time_value.into()),
             );
-            let partition_keys: Vec<String> = match stream_partition_keys_map.get(&stream_name) {
-                Some((_, partition_keys)) => partition_keys.to_vec(),
-                None => vec![],
-            };
+
+            // enrich from lookup tables
+            if let Some(enrichments) = stream_enrichment_map.get(&stream_name) {
+                if !enrichments.is_empty() {
+                    enrichments.apply(local_val);
+                }
+            }
+
+            // coerce values to the defined schema
+            if let Some(defined_schema) = stream_defined_schema_map.get(&stream_name) {
+                if !defined_schema.is_empty() {
+                    if let Err(reason) = defined_schema.apply(local_val) {
+                        status.failed += 1;
+                        status.error = reason;
+                        continue;
+                    }
+                }
+            }
+
+            // derived metrics see every record, before sampling
+            if let Some(derived_metrics) = stream_derived_map.get(&stream_name) {
+                if !derived_metrics.is_empty() {
+                    derived::observe(org_id, &stream_name, derived_metrics, local_val);
+                }
+            }
+
+            // apply sampling rules
+            if let Some(sampling_rules) = stream_sampling_map.get(&stream_name) {
+                if !sampling_rules.is_empty() && !sampling_rules.apply(local_val) {
+                    ingest_stats
+                        .with_label_values(&[org_id, &stream_name, "sampled_out"])
+                        .inc();
+                    continue;
+                }
+            }
+
+            // check data quality rules
+            if let Some(quality_rules) = stream_quality_map.get(&stream_name) {
+                match quality_rules.apply(org_id, &stream_name, local_val) {
+                    QualityResult::Valid => {}
+                    QualityResult::Reject(reason) => {
+                        status.failed += 1;
+                        status.error = reason;
+                        continue;
+                    }
+                    QualityResult::Route(target) => {
+                        quality::route_record(
+                            org_id,
+                            &target,
+                            &mut stream_schema_map,
+                            &mut routed,
+                            local_val,
+                        )
+                        .await;
+                        continue;
+                    }
+                }
+            }
+
+            let (partition_time_level, partition_keys) =
+                match stream_partition_keys_map.get(&stream_name) {
+                    Some((_, level, partition_keys)) => (*level, partition_keys.to_vec()),
+                    None => (PartitionTimeLevel::default(), vec![]),
+                };
 
             let local_trigger = super::add_valid_record(
                 StreamMeta {
                     org_id: org_id.to_string(),
                     stream_name: stream_name.clone(),
+                    partition_time_level,
                     partition_keys,
                     stream_alerts_map: stream_alerts_map.clone(),
                 },
@@ -281,6 +414,14 @@ pub async fn ingest(
         });
     }
 
+    // write records routed by data quality rules
+    response_vec.extend(quality::write_routed(
+        org_id,
+        *thread_id.as_ref(),
+        &ingest_stats,
+        routed,
+    ));
+
     // only one trigger per request, as it updates etcd
     for (_, entry) in &stream_trigger_map {
         let mut alerts = stream_alerts_map
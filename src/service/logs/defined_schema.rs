@@ -0,0 +1,200 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::{AHashMap, AHashSet};
+use datafusion::arrow::datatypes::Schema;
+use serde_json::{Map, Number, Value};
+
+use super::enrichment::Enrichments;
+use crate::infra::config::CONFIG;
+use crate::meta::schema::{FieldType, SchemaMode};
+use crate::service::stream::{get_stream_setting_defined_schema, get_stream_setting_schema_mode};
+
+pub struct DefinedSchema {
+    mode: SchemaMode,
+    fields: AHashMap<String, FieldType>,
+    /// fields added by the stream enrichments, allowed in strict mode
+    enriched: AHashSet<String>,
+}
+
+impl DefinedSchema {
+    pub fn from_schema(schema: Option<&Schema>) -> Self {
+        let (mode, fields) = match schema {
+            Some(schema) => (
+                get_stream_setting_schema_mode(schema),
+                get_stream_setting_defined_schema(schema),
+            ),
+            None => (SchemaMode::default(), vec![]),
+        };
+        DefinedSchema {
+            mode,
+            fields: fields
+                .into_iter()
+                .map(|field| (field.name, field.field_type))
+                .collect(),
+            enriched: AHashSet::new(),
+        }
+    }
+
+    pub fn with_enrichments(mut self, enrichments: &Enrichments) -> Self {
+        self.enriched = enrichments.output_fields().into_iter().collect();
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Coerces the values of the defined fields to their declared types,
+    /// returns the reason when the record has to be rejected.
+    pub fn apply(&self, row: &mut Map<String, Value>) -> Result<(), String> {
+        if self.mode == SchemaMode::Strict {
+            if let Some(key) = row.keys().find(|key| {
+                *key != &CONFIG.common.time_stamp_col
+                    && !self.fields.contains_key(*key)
+                    && !self.enriched.contains(*key)
+            }) {
+                return Err(format!("field {} is not defined in the stream schema", key));
+            }
+        }
+        for (name, field_type) in self.fields.iter() {
+            let value = match row.get_mut(name) {
+                Some(value) => value,
+                None => continue,
+            };
+            if value.is_null() {
+                continue;
+            }
+            match coerce(value, *field_type) {
+                Some(v) => *value = v,
+                None => {
+                    return Err(format!(
+                        "field {}: cannot convert {} to {}",
+                        name, value, field_type
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn coerce(value: &Value, field_type: FieldType) -> Option<Value> {
+    match field_type {
+        FieldType::Boolean => match value {
+            Value::Bool(_) => Some(value.clone()),
+            Value::String(s) => match s.trim().to_lowercase().as_str() {
+                "true" => Some(Value::Bool(true)),
+                "false" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        },
+        FieldType::Int64 => match value {
+            Value::Number(n) => match n.as_i64() {
+                Some(v) => Some(v.into()),
+                None => n
+                    .as_f64()
+                    .filter(|v| v.fract() == 0.0 && *v >= i64::MIN as f64 && *v < i64::MAX as f64)
+                    .map(|v| (v as i64).into()),
+            },
+            Value::String(s) => s.trim().parse::<i64>().ok().map(|v| v.into()),
+            _ => None,
+        },
+        FieldType::Uint64 => match value {
+            Value::Number(n) => match n.as_u64() {
+                Some(v) => Some(v.into()),
+                None => n
+                    .as_f64()
+                    .filter(|v| v.fract() == 0.0 && *v >= 0.0 && *v < u64::MAX as f64)
+                    .map(|v| (v as u64).into()),
+            },
+            Value::String(s) => s.trim().parse::<u64>().ok().map(|v| v.into()),
+            _ => None,
+        },
+        FieldType::Float64 => {
+            let v = match value {
+                Value::Number(n) => n.as_f64(),
+                Value::String(s) => s.trim().parse::<f64>().ok(),
+                _ => None,
+            };
+            v.and_then(Number::from_f64).map(Value::Number)
+        }
+        FieldType::Utf8 => match value {
+            Value::String(_) => Some(value.clone()),
+            Value::Bool(_) | Value::Number(_) => Some(Value::String(value.to_string())),
+            _ => None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn defined_schema(mode: SchemaMode) -> DefinedSchema {
+        let mut meta = std::collections::HashMap::new();
+        meta.insert(
+            "settings".to_string(),
+            serde_json::json!({
+                "defined_schema": [
+                    {"name": "status_code", "type": "int64"},
+                    {"name": "latency", "type": "float64"},
+                    {"name": "user_id", "type": "utf8"},
+                ],
+                "schema_mode": mode,
+            })
+            .to_string(),
+        );
+        DefinedSchema::from_schema(Some(&Schema::empty().with_metadata(meta)))
+    }
+
+    #[test]
+    fn test_defined_schema_apply() {
+        let rules = defined_schema(SchemaMode::Additive);
+        let mut row = serde_json::json!({
+            "status_code": "200",
+            "latency": 12,
+            "user_id": 42,
+            "message": "ok",
+        });
+        let row = row.as_object_mut().unwrap();
+        assert!(rules.apply(row).is_ok());
+        assert_eq!(row["status_code"], serde_json::json!(200));
+        assert_eq!(row["latency"], serde_json::json!(12.0));
+        assert_eq!(row["user_id"], serde_json::json!("42"));
+
+        let mut row = serde_json::json!({"status_code": "OK"});
+        let err = rules.apply(row.as_object_mut().unwrap()).unwrap_err();
+        assert_eq!(err, r#"field status_code: cannot convert "OK" to int64"#);
+
+        let rules = defined_schema(SchemaMode::Strict);
+        let mut row = serde_json::json!({"status_code": 200, "message": "ok"});
+        let err = rules.apply(row.as_object_mut().unwrap()).unwrap_err();
+        assert_eq!(err, "field message is not defined in the stream schema");
+
+        let enrichments = Enrichments::new(
+            "default",
+            vec![],
+            vec![crate::meta::enrichment::EnrichmentProcessor {
+                processor: crate::meta::enrichment::ProcessorType::UserAgent,
+                field: "user_agent".to_string(),
+                prefix: "ua_".to_string(),
+            }],
+        );
+        let rules = defined_schema(SchemaMode::Strict).with_enrichments(&enrichments);
+        let mut row = serde_json::json!({"status_code": 200, "ua_browser": "Firefox"});
+        assert!(rules.apply(row.as_object_mut().unwrap()).is_ok());
+    }
+}
@@ -0,0 +1,304 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap;
+use bytes::{BufMut, BytesMut};
+use chrono::{TimeZone, Utc};
+use dashmap::DashMap;
+use datafusion::arrow::datatypes::Schema;
+use serde_json::{Map, Value};
+use std::fs::OpenOptions;
+
+use crate::common::json;
+use crate::infra::config::CONFIG;
+use crate::infra::file_lock;
+use crate::meta::prom::{DerivedMetric, DerivedMetricType, Metric};
+use crate::meta::StreamType;
+use crate::service::schema::{add_stream_schema, stream_schema_exists};
+
+const COUNTER: &str = "Counter";
+const HISTOGRAM: &str = "Histogram";
+/// label telling apart the series derived from different log streams
+const STREAM_LABEL: &str = "source_stream";
+/// label telling apart the series of the ingesters, each one counts its records
+const INSTANCE_LABEL: &str = "instance";
+/// aggregates not updated for an hour are written once more and dropped
+const IDLE_MICROS: i64 = 3_600_000_000;
+
+lazy_static! {
+    static ref AGGREGATES: DashMap<String, Aggregate> = DashMap::new();
+}
+
+struct Aggregate {
+    org_id: String,
+    stream_name: String,
+    name: String,
+    metric_type: DerivedMetricType,
+    labels: AHashMap<String, String>,
+    count: u64,
+    sum: f64,
+    /// bucket upper bounds with non-cumulative counts
+    buckets: Vec<(f64, u64)>,
+    updated_at: i64,
+}
+
+/// Adds the record to the in-memory aggregates of every matching derived
+/// metric of the stream. Aggregates are written out by [`flush`].
+pub fn observe(
+    org_id: &str,
+    stream_name: &str,
+    metrics: &[DerivedMetric],
+    row: &Map<String, Value>,
+) {
+    for metric in metrics {
+        if !metric
+            .conditions
+            .iter()
+            .all(|cond| super::sampling::condition_matches(cond, row))
+        {
+            continue;
+        }
+        let value = match metric.metric_type {
+            DerivedMetricType::Counter => 1.0,
+            _ => match metric
+                .field
+                .as_ref()
+                .and_then(|field| row.get(field))
+                .and_then(|v| match v {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => s.parse::<f64>().ok(),
+                    _ => None,
+                }) {
+                Some(v) => v,
+                None => continue,
+            },
+        };
+
+        let mut labels = Vec::with_capacity(metric.labels.len());
+        for label in &metric.labels {
+            if let Some(v) = row.get(label) {
+                if !v.is_null() {
+                    labels.push((label.to_string(), super::get_value(v)));
+                }
+            }
+        }
+        labels.sort();
+        let key = format!("{}/{}/{}/{:?}", org_id, stream_name, metric.name, labels);
+
+        let mut entry = AGGREGATES.entry(key).or_insert_with(|| {
+            let bounds = if metric.buckets.is_empty() {
+                prometheus::DEFAULT_BUCKETS.to_vec()
+            } else {
+                metric.buckets.clone()
+            };
+            Aggregate {
+                org_id: org_id.to_string(),
+                stream_name: stream_name.to_string(),
+                name: metric.name.clone(),
+                metric_type: metric.metric_type,
+                labels: labels.into_iter().collect(),
+                count: 0,
+                sum: 0.0,
+                buckets: if metric.metric_type == DerivedMetricType::Histogram {
+                    bounds.into_iter().map(|b| (b, 0)).collect()
+                } else {
+                    vec![]
+                },
+                updated_at: 0,
+            }
+        });
+        entry.updated_at = Utc::now().timestamp_micros();
+        entry.count += 1;
+        entry.sum += value;
+        if let Some(bucket) = entry.buckets.iter_mut().find(|(bound, _)| value <= *bound) {
+            bucket.1 += 1;
+        }
+    }
+}
+
+/// Writes the values aggregated since the node started into metrics streams,
+/// in the same layout as prometheus remote write. Values are cumulative like
+/// any prometheus counter, so `rate()` works on them.
+pub async fn flush() -> Result<(), anyhow::Error> {
+    if AGGREGATES.is_empty() {
+        return Ok(());
+    }
+
+    let timestamp = Utc::now().timestamp_micros();
+    // org_id -> metric stream -> rows
+    let mut metric_data_map: AHashMap<String, AHashMap<String, Vec<String>>> = AHashMap::new();
+    for agg in AGGREGATES.iter() {
+        let mut rows = vec![];
+        match agg.metric_type {
+            DerivedMetricType::Counter => {
+                rows.push((agg.name.clone(), agg.count as f64, COUNTER, None));
+            }
+            DerivedMetricType::Sum => {
+                rows.push((agg.name.clone(), agg.sum, COUNTER, None));
+            }
+            DerivedMetricType::Histogram => {
+                let mut cumulative = 0;
+                for (bound, count) in &agg.buckets {
+                    cumulative += count;
+                    rows.push((
+                        format!("{}_bucket", agg.name),
+                        cumulative as f64,
+                        HISTOGRAM,
+                        Some(bound.to_string()),
+                    ));
+                }
+                rows.push((
+                    format!("{}_bucket", agg.name),
+                    agg.count as f64,
+                    HISTOGRAM,
+                    Some("+Inf".to_string()),
+                ));
+                rows.push((format!("{}_sum", agg.name), agg.sum, COUNTER, None));
+                rows.push((
+                    format!("{}_count", agg.name),
+                    agg.count as f64,
+                    COUNTER,
+                    None,
+                ));
+            }
+        }
+        let org_buf = metric_data_map.entry(agg.org_id.clone()).or_default();
+        for (name, value, metric_type, le) in rows {
+            let mut collection = agg.labels.clone();
+            collection.insert("__name__".to_string(), name.clone());
+            collection.insert(STREAM_LABEL.to_string(), agg.stream_name.clone());
+            collection.insert(
+                INSTANCE_LABEL.to_string(),
+                CONFIG.common.instance_name.clone(),
+            );
+            if let Some(le) = le {
+                collection.insert("le".to_string(), le);
+            }
+            let metric = Metric::new(
+                name.clone(),
+                value,
+                collection,
+                timestamp,
+                metric_type.to_string(),
+            );
+            org_buf
+                .entry(name)
+                .or_default()
+                .push(json::to_string(&metric).unwrap());
+        }
+    }
+    evict_idle(timestamp);
+
+    write_metrics(timestamp, metric_data_map).await
+}
+
+/// Drops the aggregates of label values not seen for a while, they come back
+/// from zero like after a restart
+fn evict_idle(now: i64) {
+    AGGREGATES.retain(|_, agg| agg.updated_at + IDLE_MICROS > now);
+}
+
+/// Writes the prometheus style rows into the metrics streams,
+/// metric_data_map: org_id -> metric stream -> rows
+pub async fn write_metrics(
+    timestamp: i64,
+    metric_data_map: AHashMap<String, AHashMap<String, Vec<String>>>,
+) -> Result<(), anyhow::Error> {
+    let hour_key = Utc
+        .timestamp_nanos(timestamp * 1000)
+        .format("%Y_%m_%d_%H")
+        .to_string();
+    let mut metric_schema_map: AHashMap<String, Schema> = AHashMap::new();
+    let mut write_buf = BytesMut::new();
+    for (org_id, metric_data) in metric_data_map {
+        for (metric_name, entry) in metric_data {
+            write_buf.clear();
+            for row in &entry {
+                write_buf.put(row.as_bytes());
+                write_buf.put("\n".as_bytes());
+            }
+            let file = file_lock::get_or_create(
+                0,
+                &org_id,
+                &metric_name,
+                StreamType::Metrics,
+                &hour_key,
+                false,
+            );
+            file.write(write_buf.as_ref());
+
+            let schema_exists = stream_schema_exists(
+                &org_id,
+                &metric_name,
+                StreamType::Metrics,
+                &mut metric_schema_map,
+            )
+            .await;
+            if !schema_exists.has_fields {
+                let file = OpenOptions::new().read(true).open(file.full_name())?;
+                add_stream_schema(
+                    &org_id,
+                    &metric_name,
+                    StreamType::Metrics,
+                    &file,
+                    &mut metric_schema_map,
+                    timestamp,
+                )
+                .await;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_observe() {
+        let metrics: Vec<DerivedMetric> = serde_json::from_value(json!([
+            {"name": "test_derived_errors", "metric_type": "counter", "labels": ["service"],
+             "conditions": [{"column": "level", "operator": "=", "value": "error"}]},
+            {"name": "test_derived_latency", "metric_type": "histogram", "field": "took", "buckets": [10.0, 100.0]}
+        ]))
+        .unwrap();
+        for (level, took) in [("error", 5), ("error", 50), ("info", 500)] {
+            let row = json!({"level": level, "service": "api", "took": took});
+            observe(
+                "test_org",
+                "test_stream",
+                &metrics,
+                row.as_object().unwrap(),
+            );
+        }
+
+        let errors = AGGREGATES
+            .get(r#"test_org/test_stream/test_derived_errors/[("service", "api")]"#)
+            .unwrap();
+        assert_eq!(errors.count, 2);
+        let latency = AGGREGATES
+            .get("test_org/test_stream/test_derived_latency/[]")
+            .unwrap();
+        assert_eq!(latency.count, 3);
+        assert_eq!(latency.sum, 555.0);
+        assert_eq!(latency.buckets, vec![(10.0, 1), (100.0, 1)]);
+        drop(errors);
+        drop(latency);
+
+        evict_idle(Utc::now().timestamp_micros() + IDLE_MICROS);
+        assert!(!AGGREGATES.contains_key("test_org/test_stream/test_derived_latency/[]"));
+    }
+}
@@ -0,0 +1,190 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap;
+use datafusion::arrow::datatypes::Schema;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use super::geoip;
+use crate::common::useragent;
+use crate::infra::config::LOOKUP_TABLES;
+use crate::meta::enrichment::{EnrichmentProcessor, ProcessorType};
+use crate::meta::lookup::LookupEnrichment;
+use crate::service::stream::{get_stream_setting_enrichments, get_stream_setting_processors};
+
+const USER_AGENT_FIELDS: [&str; 5] = ["browser", "browser_version", "os", "os_version", "device"];
+
+struct LookupRef {
+    field: String,
+    prefix: String,
+    key: String,
+    columns: Vec<String>,
+    rows: Arc<AHashMap<String, Map<String, Value>>>,
+}
+
+/// Enrichment rules and processors of a stream, lookup rules are resolved
+/// against the lookup tables cached on this node once per request.
+pub struct Enrichments {
+    lookups: Vec<LookupRef>,
+    processors: Vec<EnrichmentProcessor>,
+}
+
+impl Enrichments {
+    pub fn new(
+        org_id: &str,
+        rules: Vec<LookupEnrichment>,
+        processors: Vec<EnrichmentProcessor>,
+    ) -> Self {
+        let mut lookups = Vec::with_capacity(rules.len());
+        for rule in rules {
+            match LOOKUP_TABLES.get(&format!("{}/{}", org_id, rule.table)) {
+                Some(data) => lookups.push(LookupRef {
+                    field: rule.field,
+                    prefix: rule.prefix,
+                    key: data.table.key.clone(),
+                    columns: data.table.columns.clone(),
+                    rows: data.rows.clone(),
+                }),
+                None => log::warn!("lookup table {}/{} not found", org_id, rule.table),
+            }
+        }
+        Enrichments {
+            lookups,
+            processors,
+        }
+    }
+
+    pub fn from_schema(org_id: &str, schema: Option<&Schema>) -> Self {
+        match schema {
+            Some(schema) => Self::new(
+                org_id,
+                get_stream_setting_enrichments(schema),
+                get_stream_setting_processors(schema),
+            ),
+            None => Self::new(org_id, vec![], vec![]),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lookups.is_empty() && self.processors.is_empty()
+    }
+
+    /// The fields the lookups and processors may add to a record
+    pub fn output_fields(&self) -> Vec<String> {
+        let mut fields = Vec::new();
+        for lookup in &self.lookups {
+            for column in lookup.columns.iter().filter(|c| *c != &lookup.key) {
+                fields.push(format!("{}{}", lookup.prefix, column));
+            }
+        }
+        for processor in &self.processors {
+            let names = match processor.processor {
+                ProcessorType::Geoip => geoip::FIELDS.iter().map(|(name, _)| *name).collect(),
+                ProcessorType::UserAgent => USER_AGENT_FIELDS.to_vec(),
+            };
+            for name in names {
+                fields.push(format!("{}{}", processor.prefix, name));
+            }
+        }
+        fields
+    }
+
+    pub fn apply(&self, row: &mut Map<String, Value>) {
+        for lookup in &self.lookups {
+            let value = match row.get(&lookup.field) {
+                Some(v) if !v.is_null() => super::get_value(v),
+                _ => continue,
+            };
+            if let Some(lookup_row) = lookup.rows.get(&value) {
+                for (column, val) in lookup_row {
+                    if column.eq(&lookup.key) {
+                        continue;
+                    }
+                    row.insert(format!("{}{}", lookup.prefix, column), val.clone());
+                }
+            }
+        }
+        for processor in &self.processors {
+            let value = match row.get(&processor.field) {
+                Some(Value::String(v)) if !v.is_empty() => v.clone(),
+                _ => continue,
+            };
+            let fields = match processor.processor {
+                ProcessorType::Geoip => geoip::lookup(&value),
+                ProcessorType::UserAgent => {
+                    let ua = useragent::parse(&value);
+                    let mut fields = Map::with_capacity(5);
+                    fields.insert("browser".to_string(), Value::String(ua.browser));
+                    if !ua.browser_version.is_empty() {
+                        fields.insert(
+                            "browser_version".to_string(),
+                            Value::String(ua.browser_version),
+                        );
+                    }
+                    fields.insert("os".to_string(), Value::String(ua.os));
+                    if !ua.os_version.is_empty() {
+                        fields.insert("os_version".to_string(), Value::String(ua.os_version));
+                    }
+                    fields.insert("device".to_string(), Value::String(ua.device));
+                    fields
+                }
+            };
+            for (name, val) in fields {
+                row.insert(format!("{}{}", processor.prefix, name), val);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::meta::lookup::{LookupData, LookupTable};
+    use serde_json::json;
+
+    #[test]
+    fn test_enrichments_apply() {
+        let table = LookupTable {
+            name: "test_owners".to_string(),
+            key: "service".to_string(),
+            columns: vec!["service".to_string(), "team".to_string()],
+            rows: 1,
+            updated_at: 0,
+        };
+        let data =
+            LookupData::try_new(table, b"{\"service\":\"api\",\"team\":\"core\"}\n").unwrap();
+        LOOKUP_TABLES.insert("test_org/test_owners".to_string(), data);
+
+        let enrichments = Enrichments::new(
+            "test_org",
+            vec![LookupEnrichment {
+                table: "test_owners".to_string(),
+                field: "service".to_string(),
+                prefix: "owner_".to_string(),
+            }],
+            vec![EnrichmentProcessor {
+                processor: ProcessorType::UserAgent,
+                field: "user_agent".to_string(),
+                prefix: "ua_".to_string(),
+            }],
+        );
+        let mut row = json!({"service": "api", "user_agent": "curl/7.87.0"});
+        let row = row.as_object_mut().unwrap();
+        enrichments.apply(row);
+        assert_eq!(row.get("owner_team").unwrap().as_str(), Some("core"));
+        assert_eq!(row.get("ua_browser").unwrap().as_str(), Some("curl"));
+        assert_eq!(row.get("ua_device").unwrap().as_str(), Some("bot"));
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use parking_lot::RwLock;
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::common::mmdb::Reader;
+use crate::infra::config::CONFIG;
+
+// output field and its path in GeoIP2 / GeoLite2 records
+pub(crate) const FIELDS: [(&str, &[&str]); 9] = [
+    ("country_code", &["country", "iso_code"]),
+    ("country", &["country", "names", "en"]),
+    ("continent", &["continent", "names", "en"]),
+    ("region", &["subdivisions", "0", "names", "en"]),
+    ("city", &["city", "names", "en"]),
+    ("latitude", &["location", "latitude"]),
+    ("longitude", &["location", "longitude"]),
+    ("asn", &["autonomous_system_number"]),
+    ("as_org", &["autonomous_system_organization"]),
+];
+
+struct GeoDb {
+    path: String,
+    modified: Option<SystemTime>,
+    reader: Option<Arc<Reader>>,
+}
+
+lazy_static! {
+    static ref GEOIP_DBS: RwLock<Vec<GeoDb>> = RwLock::new(vec![]);
+}
+
+/// Loads the configured GeoIP databases, a database is read again only when
+/// its file modification time changed.
+pub fn reload() {
+    let paths: Vec<String> = CONFIG
+        .common
+        .geoip_db_path
+        .split(',')
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    let mut dbs = Vec::with_capacity(paths.len());
+    for path in paths {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        let current = GEOIP_DBS
+            .read()
+            .iter()
+            .find(|db| db.path == path && db.modified == modified)
+            .map(|db| db.reader.clone());
+        let reader = match current {
+            Some(reader) => reader,
+            None => match std::fs::read(&path)
+                .map_err(anyhow::Error::from)
+                .and_then(Reader::from_bytes)
+            {
+                Ok(reader) => {
+                    log::info!("geoip database loaded: {} ({})", path, reader.database_type);
+                    Some(Arc::new(reader))
+                }
+                Err(e) => {
+                    log::error!("geoip database {} load error: {}", path, e);
+                    None
+                }
+            },
+        };
+        dbs.push(GeoDb {
+            path,
+            modified,
+            reader,
+        });
+    }
+    *GEOIP_DBS.write() = dbs;
+}
+
+/// Looks up an IP address in all loaded databases, returns the flattened
+/// location and network fields.
+pub fn lookup(ip: &str) -> Map<String, Value> {
+    let mut fields = Map::new();
+    let ip: IpAddr = match ip.trim().parse() {
+        Ok(ip) => ip,
+        Err(_) => return fields,
+    };
+    let readers: Vec<Arc<Reader>> = GEOIP_DBS
+        .read()
+        .iter()
+        .filter_map(|db| db.reader.clone())
+        .collect();
+    for reader in readers {
+        let record = match reader.lookup(ip) {
+            Ok(Some(record)) => record,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("geoip lookup error: {}", e);
+                continue;
+            }
+        };
+        for (name, path) in FIELDS {
+            let mut value = &record;
+            for key in path.iter() {
+                value = match key.parse::<usize>() {
+                    Ok(i) => &value[i],
+                    Err(_) => &value[*key],
+                };
+            }
+            if !value.is_null() {
+                fields.insert(name.to_string(), value.clone());
+            }
+        }
+    }
+    fields
+}
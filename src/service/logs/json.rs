@@ -23,9 +23,15 @@ use prometheus::GaugeVec;
 use serde_json::Value;
 use std::io::Error;
 
-use super::StreamMeta;
+use super::defined_schema::DefinedSchema;
+use super::derived;
+use super::enrichment::Enrichments;
+use super::nested::NestedFields;
+use super::quality::{self, QualityResult, QualityRules, RoutedStream};
+use super::sampling::SamplingRules;
+use super::{PartitionKey, StreamMeta};
 use crate::common::json;
-use crate::common::time::parse_timestamp_micro_from_value;
+use crate::common::time::{parse_timestamp_micro_from_value, wal_time_value};
 use crate::infra::cluster;
 use crate::infra::config::CONFIG;
 #[cfg(feature = "zo_functions")]
@@ -38,6 +44,7 @@ use crate::meta::http::HttpResponse as MetaHttpResponse;
 use crate::meta::ingestion::{IngestionResponse, RecordStatus, StreamStatus};
 use crate::meta::StreamType;
 use crate::service::schema::stream_schema_exists;
+use crate::service::stream::get_stream_setting_derived_metrics;
 
 pub async fn ingest(
     org_id: &str,
@@ -57,6 +64,36 @@ pub async fn ingest(
         );
     }
 
+    let body_vec = body.to_vec();
+    let reader: Vec<Value> = json::from_slice(&body_vec)?;
+    let response_vec = ingest_values(
+        org_id,
+        stream_name,
+        &reader,
+        *thread_id.as_ref(),
+        &ingest_stats,
+        false,
+    )
+    .await;
+
+    //Ok(HttpResponse::Ok().json(stream_status))
+    Ok(HttpResponse::Ok().json(IngestionResponse::new(
+        http::StatusCode::OK.into(),
+        response_vec,
+    )))
+}
+
+/// Runs the records through the ingestion pipeline of the stream and writes
+/// them to the WAL. Replayed records were ingested once already, so the
+/// ingestion time limit, derived metrics, sampling and alerts are skipped.
+pub(crate) async fn ingest_values(
+    org_id: &str,
+    stream_name: &str,
+    reader: &[Value],
+    thread_id: usize,
+    ingest_stats: &GaugeVec,
+    replay: bool,
+) -> Vec<StreamStatus> {
     let mut min_ts = (Utc::now() + Duration::hours(CONFIG.limit.allowed_upto)).timestamp_micros();
     #[cfg(feature = "zo_functions")]
     let lua = Lua::new();
@@ -100,21 +137,37 @@ pub async fn ingest(
         &mut stream_schema_map,
     )
     .await;
-    let mut partition_keys: Vec<String> = vec![];
+    let mut partition_keys: Vec<PartitionKey> = vec![];
     if stream_schema.has_partition_keys {
         partition_keys =
             super::get_stream_partition_keys(stream_name.to_string(), stream_schema_map.clone())
                 .await;
     }
+    let partition_time_level =
+        super::get_stream_partition_time_level(stream_name, &stream_schema_map);
+    let keep_nanos = super::get_stream_keep_nanos(stream_name, &stream_schema_map);
+
+    let enrichments = Enrichments::from_schema(org_id, stream_schema_map.get(stream_name));
+    let nested_fields = NestedFields::from_schema(stream_schema_map.get(stream_name));
+    let defined_schema = DefinedSchema::from_schema(stream_schema_map.get(stream_name))
+        .with_enrichments(&enrichments);
+    let derived_metrics = match stream_schema_map.get(stream_name) {
+        Some(schema) => get_stream_setting_derived_metrics(schema),
+        None => vec![],
+    };
+    let sampling_rules = SamplingRules::from_schema(stream_schema_map.get(stream_name));
+    let quality_rules =
+        QualityRules::from_schema(org_id, stream_name, stream_schema_map.get(stream_name));
+    let mut routed: AHashMap<String, RoutedStream> = AHashMap::new();
 
     // Start get stream alerts
-    let key = format!("{}/{}", &org_id, &stream_name);
-    super::get_stream_alerts(key, &mut stream_alerts_map).await;
+    if !replay {
+        let key = format!("{}/{}", &org_id, &stream_name);
+        super::get_stream_alerts(key, &mut stream_alerts_map).await;
+    }
     // End get stream alert
 
     let mut buf: AHashMap<String, Vec<String>> = AHashMap::new();
-    let body_vec = body.to_vec();
-    let reader: Vec<Value> = json::from_slice(&body_vec)?;
     for item in reader.iter() {
         #[cfg(feature = "zo_functions")]
         let mut value = item.to_owned();
@@ -133,7 +186,7 @@ pub async fn ingest(
         //End row based transform
 
         //JSON Flattening
-        let mut value = json::flatten_json(&value);
+        let mut value = nested_fields.flatten(&value);
         // get json object
         let local_val = value.as_object_mut().unwrap();
 
@@ -160,7 +213,7 @@ pub async fn ingest(
         };
         // check ingestion time
         let earlest_time = Utc::now() + Duration::hours(0 - CONFIG.limit.allowed_upto);
-        if timestamp < earlest_time.timestamp_micros() {
+        if !replay && timestamp < earlest_time.timestamp_micros() {
             stream_status.status.failed += 1; // to old data, just discard
             stream_status.status.error = super::get_upto_discard_error();
             continue;
@@ -168,15 +221,71 @@ pub async fn ingest(
         if timestamp < min_ts {
             min_ts = timestamp;
         }
+        let time_value = wal_time_value(
+            local_val.get(&CONFIG.common.time_stamp_col),
+            timestamp,
+            keep_nanos,
+        );
         local_val.insert(
             CONFIG.common.time_stamp_col.clone(),
-            Value::Number(timestamp.into()),
+            Value::Number(time_value.into()),
         );
 
+        // enrich from lookup tables
+        if !enrichments.is_empty() {
+            enrichments.apply(local_val);
+        }
+
+        // coerce values to the defined schema
+        if !defined_schema.is_empty() {
+            if let Err(reason) = defined_schema.apply(local_val) {
+                stream_status.status.failed += 1;
+                stream_status.status.error = reason;
+                continue;
+            }
+        }
+
+        // derived metrics see every record, before sampling
+        if !replay && !derived_metrics.is_empty() {
+            derived::observe(org_id, stream_name, &derived_metrics, local_val);
+        }
+
+        // apply sampling rules
+        if !replay && !sampling_rules.is_empty() && !sampling_rules.apply(local_val) {
+            ingest_stats
+                .with_label_values(&[org_id, stream_name, "sampled_out"])
+                .inc();
+            continue;
+        }
+
+        // check data quality rules
+        if !quality_rules.is_empty() {
+            match quality_rules.apply(org_id, stream_name, local_val) {
+                QualityResult::Valid => {}
+                QualityResult::Reject(reason) => {
+                    stream_status.status.failed += 1;
+                    stream_status.status.error = reason;
+                    continue;
+                }
+                QualityResult::Route(target) => {
+                    quality::route_record(
+                        org_id,
+                        &target,
+                        &mut stream_schema_map,
+                        &mut routed,
+                        local_val,
+                    )
+                    .await;
+                    continue;
+                }
+            }
+        }
+
         let local_trigger = super::add_valid_record(
             StreamMeta {
                 org_id: org_id.to_string(),
                 stream_name: stream_name.to_string(),
+                partition_time_level,
                 partition_keys: partition_keys.clone(),
                 stream_alerts_map: stream_alerts_map.clone(),
             },
@@ -205,7 +314,7 @@ pub async fn ingest(
             write_buf.put("\n".as_bytes());
         }
         let file = file_lock::get_or_create(
-            *thread_id.as_ref(),
+            thread_id,
             org_id,
             stream_name,
             StreamType::Logs,
@@ -226,11 +335,17 @@ pub async fn ingest(
             .add(write_buf.len() as f64);
     }
 
+    // write records routed by data quality rules
+    let mut response_vec = vec![stream_status];
+    response_vec.extend(quality::write_routed(
+        org_id,
+        thread_id,
+        ingest_stats,
+        routed,
+    ));
+
     if stream_file_name.is_empty() {
-        return Ok(HttpResponse::Ok().json(IngestionResponse::new(
-            http::StatusCode::OK.into(),
-            vec![stream_status],
-        )));
+        return response_vec;
     }
 
     // only one trigger per request, as it updates etcd
@@ -251,9 +366,5 @@ pub async fn ingest(
         .with_label_values(&[org_id, stream_name, "req_num"])
         .inc();
 
-    //Ok(HttpResponse::Ok().json(stream_status))
-    Ok(HttpResponse::Ok().json(IngestionResponse::new(
-        http::StatusCode::OK.into(),
-        vec![stream_status],
-    )))
+    response_vec
 }
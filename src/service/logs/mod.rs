@@ -31,12 +31,24 @@ use crate::meta::alert::{Alert, Evaluate, Trigger};
 #[cfg(feature = "zo_functions")]
 use crate::meta::functions::Transform;
 use crate::meta::ingestion::RecordStatus;
+use crate::meta::stream::PartitionTimeLevel;
 use crate::meta::StreamType;
 use crate::service::schema::check_for_schema;
+use crate::service::stream::{
+    get_stream_setting_keep_nanos, get_stream_setting_partition_buckets,
+    get_stream_setting_partition_keys, get_stream_setting_partition_time_level,
+};
 
 pub mod bulk;
+pub mod defined_schema;
+pub mod derived;
+pub mod enrichment;
+pub mod geoip;
 pub mod json;
 pub mod multi;
+pub mod nested;
+pub mod quality;
+pub mod sampling;
 
 pub(crate) fn get_upto_discard_error() -> String {
     format!(
@@ -133,19 +145,37 @@ async fn get_stream_alerts<'a>(key: String, stream_alerts_map: &mut AHashMap<Str
 async fn get_stream_partition_keys(
     stream_name: String,
     stream_schema_map: AHashMap<String, Schema>,
-) -> Vec<String> {
-    let mut keys: Vec<String> = vec![];
-    if stream_schema_map.contains_key(&stream_name) {
-        let schema = stream_schema_map.get(&stream_name).unwrap();
-        let mut meta = schema.metadata().clone();
-        meta.remove("created_at");
-        let mut v: Vec<_> = meta.into_iter().collect();
-        v.sort();
-        for (_, value) in v {
-            keys.push(value);
-        }
+) -> Vec<PartitionKey> {
+    let schema = match stream_schema_map.get(&stream_name) {
+        Some(schema) => schema,
+        None => return vec![],
+    };
+    let buckets = get_stream_setting_partition_buckets(schema);
+    get_stream_setting_partition_keys(schema)
+        .into_iter()
+        .map(|field| PartitionKey {
+            buckets: buckets.get(&field).cloned().unwrap_or_default(),
+            field,
+        })
+        .collect()
+}
+
+fn get_stream_partition_time_level(
+    stream_name: &str,
+    stream_schema_map: &AHashMap<String, Schema>,
+) -> PartitionTimeLevel {
+    match stream_schema_map.get(stream_name) {
+        Some(schema) => get_stream_setting_partition_time_level(schema),
+        None => PartitionTimeLevel::default(),
+    }
+}
+
+/// Whether the time column of the stream keeps the nanoseconds
+fn get_stream_keep_nanos(stream_name: &str, stream_schema_map: &AHashMap<String, Schema>) -> bool {
+    match stream_schema_map.get(stream_name) {
+        Some(schema) => get_stream_setting_keep_nanos(schema),
+        None => false,
     }
-    keys
 }
 
 // generate partition key for the record
@@ -158,6 +188,11 @@ pub fn get_partition_key_str(s: &str) -> String {
     }
 }
 
+/// the bucket a hashed partition key value falls into
+pub fn get_partition_bucket(value: &str, buckets: u64) -> u64 {
+    common::str::hash64(value) % buckets
+}
+
 pub fn cast_to_type(mut value: Value, delta: Vec<Field>) -> Option<String> {
     let local_map = value.as_object_mut().unwrap();
     let mut parse_error = false;
@@ -309,13 +344,22 @@ async fn add_valid_record(
     local_val: &mut Map<String, Value>,
 ) -> Option<Trigger> {
     let mut trigger: Option<Trigger> = None;
-    let timestamp: i64 = local_val
-        .get(&CONFIG.common.time_stamp_col.clone())
-        .unwrap()
-        .as_i64()
-        .unwrap();
+    let keep_nanos = get_stream_keep_nanos(&stream_meta.stream_name, stream_schema_map);
+    let timestamp: i64 = common::time::time_value_to_nanos(
+        local_val
+            .get(&CONFIG.common.time_stamp_col.clone())
+            .unwrap()
+            .as_i64()
+            .unwrap(),
+        keep_nanos,
+    ) / 1000;
     // get hour key
-    let hour_key = get_hour_key(timestamp, stream_meta.partition_keys, local_val);
+    let hour_key = get_hour_key(
+        timestamp,
+        stream_meta.partition_time_level,
+        stream_meta.partition_keys,
+        local_val,
+    );
     let hour_buf = buf.entry(hour_key.clone()).or_default();
 
     let mut value_str = common::json::to_string(&local_val).unwrap();
@@ -353,7 +397,7 @@ async fn add_valid_record(
                 if let Some(alerts) = stream_meta.stream_alerts_map.get(&key) {
                     for alert in alerts {
                         if alert.is_real_time {
-                            let set_trigger = alert.condition.evaluate(local_val.clone());
+                            let set_trigger = alert.condition.evaluate(local_val);
                             if set_trigger {
                                 // let _ = triggers::save_trigger(alert.name.clone(), trigger).await;
                                 trigger = Some(Trigger {
@@ -383,22 +427,29 @@ async fn add_valid_record(
 
 fn get_hour_key(
     timestamp: i64,
-    partition_keys: Vec<String>,
+    partition_time_level: PartitionTimeLevel,
+    partition_keys: Vec<PartitionKey>,
     local_val: &mut Map<String, Value>,
 ) -> String {
     // get hour file name
     let mut hour_key = Utc
         .timestamp_nanos(timestamp * 1000)
-        .format("%Y_%m_%d_%H")
+        .format(partition_time_level.wal_key_format())
         .to_string();
 
     for key in &partition_keys {
-        match local_val.get(key) {
+        match local_val.get(&key.field) {
             Some(v) => {
-                let val = if v.is_string() {
-                    format!("{}={}", key, v.as_str().unwrap())
+                let value = if v.is_string() {
+                    v.as_str().unwrap().to_string()
                 } else {
-                    format!("{}={}", key, v)
+                    v.to_string()
+                };
+                let val = if key.buckets > 0 {
+                    let bucket = get_partition_bucket(&value, key.buckets);
+                    format!("{}.bucket={}", key.field, bucket)
+                } else {
+                    format!("{}={}", key.field, value)
                 };
                 hour_key.push_str(&format!("_{}", get_partition_key_str(&val)));
             }
@@ -408,10 +459,18 @@ fn get_hour_key(
     hour_key
 }
 
+#[derive(Clone, Debug)]
+struct PartitionKey {
+    field: String,
+    /// the value is hashed into this many buckets, 0 keeps the value
+    buckets: u64,
+}
+
 struct StreamMeta {
     org_id: String,
     stream_name: String,
-    partition_keys: Vec<String>,
+    partition_time_level: PartitionTimeLevel,
+    partition_keys: Vec<PartitionKey>,
     stream_alerts_map: AHashMap<String, Vec<Alert>>,
 }
 
@@ -428,3 +487,45 @@ pub async fn send_ingest_notification(mut trigger: Trigger, alert: Alert) {
     trigger.count += 1;
     let _ = triggers::save_trigger(trigger.alert_name.clone(), trigger.clone()).await;
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_hour_key() {
+        let mut local_val = Map::new();
+        local_val.insert("app_name".to_string(), json!("web"));
+        local_val.insert("customer_id".to_string(), json!("c-123"));
+        let partition_keys = vec![
+            PartitionKey {
+                field: "app_name".to_string(),
+                buckets: 0,
+            },
+            PartitionKey {
+                field: "customer_id".to_string(),
+                buckets: 16,
+            },
+        ];
+        let bucket = get_partition_bucket("c-123", 16);
+        assert!(bucket < 16);
+        assert_eq!(
+            get_hour_key(
+                1664791200000000,
+                PartitionTimeLevel::Hour,
+                partition_keys.clone(),
+                &mut local_val
+            ),
+            format!("2022_10_03_10_app.name=web_customer.id.bucket={}", bucket)
+        );
+        assert_eq!(
+            get_hour_key(
+                1664791200000000,
+                PartitionTimeLevel::Day,
+                partition_keys,
+                &mut local_val
+            ),
+            format!("2022_10_03_app.name=web_customer.id.bucket={}", bucket)
+        );
+    }
+}
@@ -24,7 +24,7 @@ use serde_json::Value;
 use std::io::{BufRead, BufReader, Error};
 
 use crate::common::json;
-use crate::common::time::parse_timestamp_micro_from_value;
+use crate::common::time::{parse_timestamp_micro_from_value, wal_time_value};
 use crate::infra::cluster;
 use crate::infra::config::CONFIG;
 #[cfg(feature = "zo_functions")]
@@ -36,8 +36,15 @@ use crate::meta::functions::Transform;
 use crate::meta::http::HttpResponse as MetaHttpResponse;
 use crate::meta::ingestion::{IngestionResponse, RecordStatus, StreamStatus};
 use crate::meta::StreamType;
-use crate::service::logs::StreamMeta;
+use crate::service::logs::defined_schema::DefinedSchema;
+use crate::service::logs::derived;
+use crate::service::logs::enrichment::Enrichments;
+use crate::service::logs::nested::NestedFields;
+use crate::service::logs::quality::{self, QualityResult, QualityRules, RoutedStream};
+use crate::service::logs::sampling::SamplingRules;
+use crate::service::logs::{PartitionKey, StreamMeta};
 use crate::service::schema::stream_schema_exists;
+use crate::service::stream::get_stream_setting_derived_metrics;
 
 pub async fn ingest(
     org_id: &str,
@@ -100,12 +107,28 @@ pub async fn ingest(
         &mut stream_schema_map,
     )
     .await;
-    let mut partition_keys: Vec<String> = vec![];
+    let mut partition_keys: Vec<PartitionKey> = vec![];
     if stream_schema.has_partition_keys {
         partition_keys =
             super::get_stream_partition_keys(stream_name.to_string(), stream_schema_map.clone())
                 .await;
     }
+    let partition_time_level =
+        super::get_stream_partition_time_level(stream_name, &stream_schema_map);
+    let keep_nanos = super::get_stream_keep_nanos(stream_name, &stream_schema_map);
+    let enrichments = Enrichments::from_schema(org_id, stream_schema_map.get(stream_name));
+    let nested_fields = NestedFields::from_schema(stream_schema_map.get(stream_name));
+    let defined_schema = DefinedSchema::from_schema(stream_schema_map.get(stream_name))
+        .with_enrichments(&enrichments);
+    let derived_metrics = match stream_schema_map.get(stream_name) {
+        Some(schema) => get_stream_setting_derived_metrics(schema),
+        None => vec![],
+    };
+    let sampling_rules = SamplingRules::from_schema(stream_schema_map.get(stream_name));
+    let quality_rules =
+        QualityRules::from_schema(org_id, stream_name, stream_schema_map.get(stream_name));
+    let mut routed: AHashMap<String, RoutedStream> = AHashMap::new();
+
     // Start get stream alerts
     let key = format!("{}/{}", &org_id, &stream_name);
     super::get_stream_alerts(key, &mut stream_alerts_map).await;
@@ -136,7 +159,7 @@ pub async fn ingest(
         // End row based transform
 
         // JSON Flattening
-        let mut value = json::flatten_json(&value);
+        let mut value = nested_fields.flatten(&value);
         // get json object
         let local_val = value.as_object_mut().unwrap();
 
@@ -171,16 +194,72 @@ pub async fn ingest(
         if timestamp < min_ts {
             min_ts = timestamp;
         }
+        let time_value = wal_time_value(
+            local_val.get(&CONFIG.common.time_stamp_col),
+            timestamp,
+            keep_nanos,
+        );
         local_val.insert(
             CONFIG.common.time_stamp_col.clone(),
-            Value::Number(timestamp.into()),
+            Value::Number(time_value.into()),
         );
 
+        // enrich from lookup tables
+        if !enrichments.is_empty() {
+            enrichments.apply(local_val);
+        }
+
+        // coerce values to the defined schema
+        if !defined_schema.is_empty() {
+            if let Err(reason) = defined_schema.apply(local_val) {
+                stream_status.status.failed += 1;
+                stream_status.status.error = reason;
+                continue;
+            }
+        }
+
+        // derived metrics see every record, before sampling
+        if !derived_metrics.is_empty() {
+            derived::observe(org_id, stream_name, &derived_metrics, local_val);
+        }
+
+        // apply sampling rules
+        if !sampling_rules.is_empty() && !sampling_rules.apply(local_val) {
+            ingest_stats
+                .with_label_values(&[org_id, stream_name, "sampled_out"])
+                .inc();
+            continue;
+        }
+
+        // check data quality rules
+        if !quality_rules.is_empty() {
+            match quality_rules.apply(org_id, stream_name, local_val) {
+                QualityResult::Valid => {}
+                QualityResult::Reject(reason) => {
+                    stream_status.status.failed += 1;
+                    stream_status.status.error = reason;
+                    continue;
+                }
+                QualityResult::Route(target) => {
+                    quality::route_record(
+                        org_id,
+                        &target,
+                        &mut stream_schema_map,
+                        &mut routed,
+                        local_val,
+                    )
+                    .await;
+                    continue;
+                }
+            }
+        }
+
         // write data
         let local_trigger = super::add_valid_record(
             StreamMeta {
                 org_id: org_id.to_string(),
                 stream_name: stream_name.to_string(),
+                partition_time_level,
                 partition_keys: partition_keys.clone(),
                 stream_alerts_map: stream_alerts_map.clone(),
             },
@@ -230,10 +309,19 @@ pub async fn ingest(
             .add(write_buf.len() as f64);
     }
 
+    // write records routed by data quality rules
+    let mut response_vec = vec![stream_status];
+    response_vec.extend(quality::write_routed(
+        org_id,
+        *thread_id.as_ref(),
+        &ingest_stats,
+        routed,
+    ));
+
     if stream_file_name.is_empty() {
         return Ok(HttpResponse::Ok().json(IngestionResponse::new(
             http::StatusCode::OK.into(),
-            vec![stream_status],
+            response_vec,
         )));
     }
 
@@ -257,6 +345,6 @@ pub async fn ingest(
 
     Ok(HttpResponse::Ok().json(IngestionResponse::new(
         http::StatusCode::OK.into(),
-        vec![stream_status],
+        response_vec,
     )))
 }
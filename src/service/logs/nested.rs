@@ -0,0 +1,148 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap;
+use datafusion::arrow::datatypes::Schema;
+use serde_json::{Map, Value};
+
+use crate::common::json;
+use crate::meta::schema::NestedFieldType;
+use crate::service::stream::get_stream_setting_nested_fields;
+
+pub struct NestedFields {
+    fields: AHashMap<String, NestedFieldType>,
+}
+
+impl NestedFields {
+    pub fn from_schema(schema: Option<&Schema>) -> Self {
+        let fields = match schema {
+            Some(schema) => get_stream_setting_nested_fields(schema),
+            None => vec![],
+        };
+        NestedFields {
+            fields: fields
+                .into_iter()
+                .map(|field| (field.name, field.field_type))
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// Flattens the record, the nested fields are kept as they are. Values not
+    /// matching the type of their field are dropped, a single value of a list
+    /// field is kept as a list of one.
+    pub fn flatten(&self, value: &Value) -> Value {
+        if self.fields.is_empty() || !value.is_object() {
+            return json::flatten_json(value);
+        }
+        let mut value = value.to_owned();
+        let local_val = value.as_object_mut().unwrap();
+        let mut nested = Vec::with_capacity(self.fields.len());
+        for (name, field_type) in self.fields.iter() {
+            if let Some(v) = local_val.remove(name) {
+                if let Some(v) = normalize(v, *field_type) {
+                    nested.push((name.to_string(), v));
+                }
+            }
+        }
+        let mut value = json::flatten_json(&value);
+        let local_val = value.as_object_mut().unwrap();
+        for (name, v) in nested {
+            local_val.insert(name, v);
+        }
+        value
+    }
+}
+
+fn normalize(value: Value, field_type: NestedFieldType) -> Option<Value> {
+    match (field_type, value) {
+        (NestedFieldType::Struct, Value::Object(obj)) if !obj.is_empty() => {
+            Some(Value::Object(obj))
+        }
+        (NestedFieldType::List, Value::Array(arr)) if !arr.is_empty() => Some(Value::Array(arr)),
+        (NestedFieldType::List, Value::Array(_) | Value::Null | Value::Object(_)) => None,
+        (NestedFieldType::List, v) => Some(Value::Array(vec![v])),
+        (NestedFieldType::Map, v @ Value::Object(_)) => {
+            let entries = json::flatten_json(&v)
+                .as_object()
+                .unwrap()
+                .iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| {
+                    let v = match v {
+                        Value::String(s) => s.to_string(),
+                        v => v.to_string(),
+                    };
+                    (k.to_string(), Value::String(v))
+                })
+                .collect::<Map<_, _>>();
+            if entries.is_empty() {
+                None
+            } else {
+                Some(Value::Object(entries))
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::schema::NestedField;
+    use serde_json::json;
+
+    #[test]
+    fn test_flatten() {
+        let nested = NestedFields {
+            fields: [
+                NestedField {
+                    name: "kubernetes".to_string(),
+                    field_type: NestedFieldType::Struct,
+                },
+                NestedField {
+                    name: "tags".to_string(),
+                    field_type: NestedFieldType::List,
+                },
+                NestedField {
+                    name: "labels".to_string(),
+                    field_type: NestedFieldType::Map,
+                },
+            ]
+            .into_iter()
+            .map(|field| (field.name, field.field_type))
+            .collect(),
+        };
+        let value = json!({
+            "log": {"level": "info"},
+            "kubernetes": {"pod": "web-1", "container": {"name": "nginx"}},
+            "tags": "prod",
+            "labels": {"app": "web", "replicas": 3, "team": {"name": "core"}},
+        });
+        assert_eq!(
+            nested.flatten(&value),
+            json!({
+                "log.level": "info",
+                "kubernetes": {"pod": "web-1", "container": {"name": "nginx"}},
+                "tags": ["prod"],
+                "labels": {"app": "web", "replicas": "3", "team.name": "core"},
+            })
+        );
+        let value = json!({"kubernetes": "web-1", "labels": {}});
+        assert_eq!(nested.flatten(&value), json!({}));
+    }
+}
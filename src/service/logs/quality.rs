@@ -0,0 +1,396 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use ahash::AHashMap;
+use bytes::{BufMut, BytesMut};
+use dashmap::DashMap;
+use datafusion::arrow::datatypes::Schema;
+use prometheus::{opts, GaugeVec, IntCounterVec};
+use regex::Regex;
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+use super::{PartitionKey, StreamMeta};
+use crate::infra::config::CONFIG;
+use crate::infra::file_lock;
+use crate::meta::ingestion::{RecordStatus, StreamData, StreamStatus};
+use crate::meta::quality::{
+    QualityAction, QualityRule, QualityRuleType, QualityValueType, DQ_VIOLATIONS_FIELD,
+};
+use crate::meta::stream::PartitionTimeLevel;
+use crate::meta::StreamType;
+use crate::service::schema::stream_schema_exists;
+use crate::service::stream::get_stream_setting_quality_rules;
+
+lazy_static! {
+    pub static ref DQ_VIOLATIONS: IntCounterVec = IntCounterVec::new(
+        opts!("dq_violations", "Data quality rule violations").namespace("zincobserve"),
+        &["org", "name", "rule", "action"],
+    )
+    .unwrap();
+    /// compiled rules of the streams, recompiled when their settings change
+    static ref RULES: DashMap<String, Arc<QualityRules>> = DashMap::new();
+}
+
+pub enum QualityResult {
+    Valid,
+    Reject(String),
+    Route(String),
+}
+
+pub struct QualityRules {
+    rules: Vec<QualityRule>,
+    patterns: AHashMap<String, Regex>,
+}
+
+impl QualityRules {
+    pub fn new(rules: Vec<QualityRule>) -> Self {
+        let mut patterns = AHashMap::new();
+        for rule in &rules {
+            if rule.rule_type != QualityRuleType::Regex {
+                continue;
+            }
+            if let Some(pattern) = &rule.pattern {
+                match Regex::new(pattern) {
+                    Ok(re) => {
+                        patterns.insert(rule.name.clone(), re);
+                    }
+                    Err(e) => log::error!("invalid pattern for quality rule {}: {}", rule.name, e),
+                }
+            }
+        }
+        QualityRules { rules, patterns }
+    }
+
+    pub fn from_schema(org_id: &str, stream_name: &str, schema: Option<&Schema>) -> Arc<Self> {
+        let rules = match schema {
+            Some(schema) => get_stream_setting_quality_rules(schema),
+            None => vec![],
+        };
+        let key = format!("{}/{}", org_id, stream_name);
+        if let Some(cached) = RULES.get(&key) {
+            if cached.rules == rules {
+                return cached.clone();
+            }
+        }
+        let compiled = Arc::new(Self::new(rules));
+        RULES.insert(key, compiled.clone());
+        compiled
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Evaluates every rule against the record. Names of violated `tag` rules
+    /// are written to `_dq_violations`, a violated `reject` rule wins over `route`.
+    pub fn apply(
+        &self,
+        org_id: &str,
+        stream_name: &str,
+        row: &mut Map<String, Value>,
+    ) -> QualityResult {
+        let mut tags = vec![];
+        let mut reject = None;
+        let mut route = None;
+        for rule in &self.rules {
+            let reason = match self.check(rule, row) {
+                Some(reason) => reason,
+                None => continue,
+            };
+            DQ_VIOLATIONS
+                .with_label_values(&[org_id, stream_name, &rule.name, &rule.action.to_string()])
+                .inc();
+            match rule.action {
+                QualityAction::Tag => tags.push(rule.name.clone()),
+                QualityAction::Reject => {
+                    if reject.is_none() {
+                        reject = Some(format!(
+                            "data quality rule [{}] violated: {}",
+                            rule.name, reason
+                        ));
+                    }
+                }
+                QualityAction::Route => {
+                    if route.is_none() {
+                        route = rule.route_to.clone();
+                    }
+                }
+            }
+        }
+        if !tags.is_empty() {
+            row.insert(
+                DQ_VIOLATIONS_FIELD.to_string(),
+                Value::String(tags.join(",")),
+            );
+        }
+        if let Some(reason) = reject {
+            QualityResult::Reject(reason)
+        } else if let Some(target) = route {
+            QualityResult::Route(target)
+        } else {
+            QualityResult::Valid
+        }
+    }
+
+    /// Returns the reason of the violation, `None` when the record passes.
+    fn check(&self, rule: &QualityRule, row: &Map<String, Value>) -> Option<String> {
+        let value = match row.get(&rule.field) {
+            Some(v) if !v.is_null() => v,
+            _ => {
+                return if rule.rule_type == QualityRuleType::Required {
+                    Some(format!("field {} is required", rule.field))
+                } else {
+                    None
+                };
+            }
+        };
+        match rule.rule_type {
+            QualityRuleType::Required => None,
+            QualityRuleType::Enum => {
+                let val = super::get_value(value);
+                if rule.values.contains(&val) {
+                    None
+                } else {
+                    Some(format!(
+                        "value {} of field {} is not one of {:?}",
+                        val, rule.field, rule.values
+                    ))
+                }
+            }
+            QualityRuleType::Regex => match self.patterns.get(&rule.name) {
+                Some(re) => {
+                    let val = super::get_value(value);
+                    if re.is_match(&val) {
+                        None
+                    } else {
+                        Some(format!(
+                            "value {} of field {} does not match {}",
+                            val,
+                            rule.field,
+                            re.as_str()
+                        ))
+                    }
+                }
+                None => None,
+            },
+            QualityRuleType::Range => {
+                let num = match value {
+                    Value::Number(n) => n.as_f64(),
+                    Value::String(s) => s.parse::<f64>().ok(),
+                    _ => None,
+                };
+                match num {
+                    None => Some(format!("field {} is not a number", rule.field)),
+                    Some(n) if rule.min.map_or(false, |min| n < min) => Some(format!(
+                        "value {} of field {} is less than {}",
+                        n,
+                        rule.field,
+                        rule.min.unwrap()
+                    )),
+                    Some(n) if rule.max.map_or(false, |max| n > max) => Some(format!(
+                        "value {} of field {} is greater than {}",
+                        n,
+                        rule.field,
+                        rule.max.unwrap()
+                    )),
+                    Some(_) => None,
+                }
+            }
+            QualityRuleType::Type => {
+                let matched = match rule.value_type {
+                    Some(QualityValueType::String) => value.is_string(),
+                    Some(QualityValueType::Number) => value.is_number(),
+                    Some(QualityValueType::Integer) => value.is_i64() || value.is_u64(),
+                    Some(QualityValueType::Boolean) => value.is_boolean(),
+                    None => true,
+                };
+                if matched {
+                    None
+                } else {
+                    Some(format!(
+                        "field {} is not of type {:?}",
+                        rule.field,
+                        rule.value_type.unwrap()
+                    ))
+                }
+            }
+        }
+    }
+}
+
+pub struct RoutedStream {
+    partition_time_level: PartitionTimeLevel,
+    partition_keys: Vec<PartitionKey>,
+    data: StreamData,
+}
+
+/// Writes a record that violated a `route` rule into the buffer of the target
+/// stream. Quality rules and alerts of the target stream are not evaluated.
+pub(crate) async fn route_record(
+    org_id: &str,
+    target: &str,
+    stream_schema_map: &mut AHashMap<String, Schema>,
+    routed: &mut AHashMap<String, RoutedStream>,
+    local_val: &mut Map<String, Value>,
+) {
+    if !routed.contains_key(target) {
+        let stream_schema =
+            stream_schema_exists(org_id, target, StreamType::Logs, stream_schema_map).await;
+        let mut partition_keys: Vec<PartitionKey> = vec![];
+        if stream_schema.has_partition_keys {
+            partition_keys =
+                super::get_stream_partition_keys(target.to_string(), stream_schema_map.clone())
+                    .await;
+        }
+        routed.insert(
+            target.to_string(),
+            RoutedStream {
+                partition_time_level: super::get_stream_partition_time_level(
+                    target,
+                    stream_schema_map,
+                ),
+                partition_keys,
+                data: StreamData {
+                    data: AHashMap::new(),
+                    status: RecordStatus {
+                        successful: 0,
+                        failed: 0,
+                        error: "".to_string(),
+                    },
+                },
+            },
+        );
+    }
+    let stream = routed.get_mut(target).unwrap();
+    super::add_valid_record(
+        StreamMeta {
+            org_id: org_id.to_string(),
+            stream_name: target.to_string(),
+            partition_time_level: stream.partition_time_level,
+            partition_keys: stream.partition_keys.clone(),
+            stream_alerts_map: AHashMap::new(),
+        },
+        stream_schema_map,
+        &mut stream.data.status,
+        &mut stream.data.data,
+        local_val,
+    )
+    .await;
+}
+
+pub(crate) fn write_routed(
+    org_id: &str,
+    thread_id: usize,
+    ingest_stats: &GaugeVec,
+    routed: AHashMap<String, RoutedStream>,
+) -> Vec<StreamStatus> {
+    let mut response_vec = Vec::with_capacity(routed.len());
+    let mut write_buf = BytesMut::new();
+    for (stream_name, stream) in routed {
+        for (key, entry) in stream.data.data {
+            if entry.is_empty() {
+                continue;
+            }
+            write_buf.clear();
+            for row in &entry {
+                write_buf.put(row.as_bytes());
+                write_buf.put("\n".as_bytes());
+            }
+            let file = file_lock::get_or_create(
+                thread_id,
+                org_id,
+                &stream_name,
+                StreamType::Logs,
+                &key,
+                CONFIG.common.wal_memory_mode_enabled,
+            );
+            file.write(write_buf.as_ref());
+
+            // metrics
+            ingest_stats
+                .with_label_values(&[org_id, &stream_name, "records"])
+                .add(entry.len() as f64);
+            ingest_stats
+                .with_label_values(&[org_id, &stream_name, "original_size"])
+                .add(write_buf.len() as f64);
+        }
+        response_vec.push(StreamStatus {
+            name: stream_name,
+            status: stream.data.status,
+        });
+    }
+    response_vec
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_quality_rules_apply() {
+        let rules: Vec<QualityRule> = serde_json::from_value(json!([
+            {"name": "level_required", "field": "level", "rule_type": "required"},
+            {"name": "code_range", "field": "code", "rule_type": "range", "min": 100, "max": 599, "action": "tag"},
+            {"name": "host_regex", "field": "host", "rule_type": "regex", "pattern": "^web-\\d+$", "action": "route", "route_to": "bad_hosts"}
+        ]))
+        .unwrap();
+        let rules = QualityRules::new(rules);
+
+        let mut row = json!({"level": "info", "code": 700, "host": "web-1"});
+        let row = row.as_object_mut().unwrap();
+        assert!(matches!(
+            rules.apply("default", "test", row),
+            QualityResult::Valid
+        ));
+        assert_eq!(
+            row.get(DQ_VIOLATIONS_FIELD).unwrap().as_str().unwrap(),
+            "code_range"
+        );
+
+        let mut row = json!({"code": 200, "host": "db-1"});
+        let row = row.as_object_mut().unwrap();
+        assert!(matches!(
+            rules.apply("default", "test", row),
+            QualityResult::Reject(_)
+        ));
+
+        let mut row = json!({"level": "info", "host": "db-1"});
+        let row = row.as_object_mut().unwrap();
+        match rules.apply("default", "test", row) {
+            QualityResult::Route(target) => assert_eq!(target, "bad_hosts"),
+            _ => panic!("record should be routed"),
+        }
+    }
+
+    #[test]
+    fn test_quality_rules_from_schema() {
+        let schema = |pattern: &str| {
+            let settings = json!({"data_quality_rules": [
+                {"name": "host_regex", "field": "host", "rule_type": "regex", "pattern": pattern}
+            ]});
+            Schema::empty().with_metadata(std::collections::HashMap::from([(
+                "settings".to_string(),
+                settings.to_string(),
+            )]))
+        };
+        let first = QualityRules::from_schema("default", "dq_cache", Some(&schema("^web")));
+        let again = QualityRules::from_schema("default", "dq_cache", Some(&schema("^web")));
+        assert!(Arc::ptr_eq(&first, &again));
+        let changed = QualityRules::from_schema("default", "dq_cache", Some(&schema("^db")));
+        assert!(!Arc::ptr_eq(&first, &changed));
+        assert_eq!(changed.patterns.len(), 1);
+    }
+}
@@ -0,0 +1,134 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datafusion::arrow::datatypes::Schema;
+use rand::Rng;
+use serde_json::{Map, Value};
+
+use crate::common::str::hash64;
+use crate::meta::alert::{Condition, Evaluate};
+use crate::meta::sampling::{SamplingAction, SamplingRule, SAMPLE_RATE_FIELD};
+use crate::service::stream::get_stream_setting_sampling_rules;
+
+pub struct SamplingRules {
+    rules: Vec<SamplingRule>,
+}
+
+impl SamplingRules {
+    pub fn new(rules: Vec<SamplingRule>) -> Self {
+        SamplingRules { rules }
+    }
+
+    pub fn from_schema(schema: Option<&Schema>) -> Self {
+        match schema {
+            Some(schema) => Self::new(get_stream_setting_sampling_rules(schema)),
+            None => Self::new(vec![]),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Applies the first matching rule, returns false when the record should
+    /// be dropped. Kept records get `_sample_rate` set to the inverse of the
+    /// keep ratio so counts can be scaled back at query time.
+    pub fn apply(&self, row: &mut Map<String, Value>) -> bool {
+        let rule = match self.rules.iter().find(|rule| {
+            rule.conditions
+                .iter()
+                .all(|cond| condition_matches(cond, row))
+        }) {
+            Some(rule) => rule,
+            None => return true,
+        };
+        match rule.action {
+            SamplingAction::Drop => false,
+            SamplingAction::OneInN => {
+                let rate = rule.rate.unwrap_or(1).max(1);
+                let keep = match rule.hash_key.as_ref().and_then(|key| row.get(key)) {
+                    Some(v) if !v.is_null() => hash64(&super::get_value(v)) % rate == 0,
+                    _ => rand::thread_rng().gen_range(0..rate) == 0,
+                };
+                if keep {
+                    row.insert(SAMPLE_RATE_FIELD.to_string(), Value::from(rate as f64));
+                }
+                keep
+            }
+            SamplingAction::Probability => {
+                let probability = rule.probability.unwrap_or(1.0);
+                if probability <= 0.0 {
+                    return false;
+                }
+                let keep = probability >= 1.0 || rand::thread_rng().gen_bool(probability);
+                if keep {
+                    row.insert(
+                        SAMPLE_RATE_FIELD.to_string(),
+                        Value::from(1.0 / probability.min(1.0)),
+                    );
+                }
+                keep
+            }
+        }
+    }
+}
+
+/// Guards `Condition::evaluate`, which panics when the row value does not
+/// have the type the condition expects.
+pub(crate) fn condition_matches(cond: &Condition, row: &Map<String, Value>) -> bool {
+    match row.get(&cond.column) {
+        Some(Value::Number(_)) if cond.is_numeric != Some(false) => cond.evaluate(row),
+        Some(Value::String(_)) if cond.is_numeric != Some(true) && cond.value.is_string() => {
+            cond.evaluate(row)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sampling_rules_apply() {
+        let rules: Vec<SamplingRule> = serde_json::from_value(json!([
+            {"name": "drop_trace", "conditions": [{"column": "level", "operator": "=", "value": "trace"}], "action": "drop"},
+            {"name": "debug", "conditions": [{"column": "level", "operator": "=", "value": "debug"}], "action": "one_in_n", "rate": 4, "hash_key": "trace_id"}
+        ]))
+        .unwrap();
+        let rules = SamplingRules::new(rules);
+
+        let mut row = json!({"level": "trace"});
+        assert!(!rules.apply(row.as_object_mut().unwrap()));
+
+        let mut row = json!({"level": "info", "code": 1});
+        let row = row.as_object_mut().unwrap();
+        assert!(rules.apply(row));
+        assert!(row.get(SAMPLE_RATE_FIELD).is_none());
+
+        let mut kept = 0;
+        for i in 0..100 {
+            let mut row = json!({"level": "debug", "trace_id": format!("t{}", i)});
+            let row = row.as_object_mut().unwrap();
+            let first = rules.apply(&mut row.clone());
+            assert_eq!(first, rules.apply(row));
+            if first {
+                kept += 1;
+                assert_eq!(row.get(SAMPLE_RATE_FIELD).unwrap().as_f64().unwrap(), 4.0);
+            }
+        }
+        assert!(kept > 0 && kept < 100);
+    }
+}
@@ -0,0 +1,248 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use bytes::Bytes;
+use chrono::Utc;
+use datafusion::arrow::csv::ReaderBuilder;
+use datafusion::arrow::json::writer::record_batches_to_json_rows;
+use serde_json::{Map, Value};
+use std::io::{Cursor, Error};
+use tracing::info_span;
+
+use crate::common::json;
+use crate::infra::storage;
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::lookup::{LookupData, LookupTable, LookupTableList};
+use crate::service::db;
+
+pub async fn save_lookup_table(
+    org_id: &str,
+    name: &str,
+    key: &str,
+    is_csv: bool,
+    body: Bytes,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:lookup_tables:save");
+    let _guard = loc_span.enter();
+
+    if !is_valid_lookup_table_name(name) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(format!(
+                "invalid lookup table name {}, use letters, digits and _ not starting with a digit, tbl is reserved",
+                name
+            )),
+        )));
+    }
+
+    let rows = if is_csv {
+        parse_csv(&body)
+    } else {
+        parse_json(&body)
+    };
+    let rows = match rows {
+        Ok(rows) => rows,
+        Err(e) => {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                Some(format!("invalid lookup table data: {}", e)),
+            )))
+        }
+    };
+    if rows.is_empty() || !rows.iter().any(|row| row.contains_key(key)) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(format!("key column {} not found in lookup table", key)),
+        )));
+    }
+
+    let mut columns: Vec<String> = vec![];
+    let mut data = Vec::new();
+    for row in &rows {
+        for column in row.keys() {
+            if !columns.contains(column) {
+                columns.push(column.to_string());
+            }
+        }
+        data.extend(json::to_vec(row).unwrap());
+        data.push(b'\n');
+    }
+    let table = LookupTable {
+        name: name.to_string(),
+        key: key.to_string(),
+        columns,
+        rows: rows.len(),
+        updated_at: Utc::now().timestamp_micros(),
+    };
+    // make sure the data can be loaded before publishing it
+    if let Err(e) = LookupData::try_new(table.clone(), &data) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(format!("invalid lookup table data: {}", e)),
+        )));
+    }
+
+    let existed = match db::lookup_tables::get(org_id, name).await {
+        Ok(existing) => existing.is_some(),
+        Err(e) => {
+            return Ok(
+                HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                    http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                    Some(e.to_string()),
+                )),
+            )
+        }
+    };
+    let storage = &storage::DEFAULT;
+    let storage_key = db::lookup_tables::get_storage_key(org_id, name);
+    if let Err(e) = storage.put(&storage_key, data.into()).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        );
+    }
+    if let Err(e) = db::lookup_tables::set(org_id, &table).await {
+        // the data of a new table is not referenced by any metadata
+        if !existed {
+            let _ = storage.del(&storage_key).await;
+        }
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(format!("save lookup table error: {}", e)),
+            )),
+        );
+    }
+
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        http::StatusCode::OK.into(),
+        "Lookup table saved".to_string(),
+    )))
+}
+
+pub async fn list_lookup_tables(org_id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:lookup_tables:list");
+    let _guard = loc_span.enter();
+    let list = db::lookup_tables::list(org_id).await.unwrap();
+    Ok(HttpResponse::Ok().json(LookupTableList { list }))
+}
+
+pub async fn delete_lookup_table(org_id: &str, name: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:lookup_tables:delete");
+    let _guard = loc_span.enter();
+    if db::lookup_tables::get(org_id, name)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("Lookup table not found".to_string()),
+        )));
+    }
+    db::lookup_tables::delete(org_id, name).await.unwrap();
+    let storage = &storage::DEFAULT;
+    if let Err(e) = storage
+        .del(&db::lookup_tables::get_storage_key(org_id, name))
+        .await
+    {
+        log::error!("delete lookup table {}/{} data error: {}", org_id, name, e);
+    }
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        http::StatusCode::OK.into(),
+        "Lookup table deleted".to_string(),
+    )))
+}
+
+/// Lookup tables are joined by name, so the name must be a plain identifier
+/// other than the table the stream is queried as
+fn is_valid_lookup_table_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !["tbl", "tbl_raw"]
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(name))
+}
+
+fn parse_csv(body: &[u8]) -> Result<Vec<Map<String, Value>>, anyhow::Error> {
+    let reader = ReaderBuilder::new()
+        .has_header(true)
+        .infer_schema(Some(1000))
+        .build(Cursor::new(body))?;
+    let mut batches = vec![];
+    for batch in reader {
+        batches.push(batch?);
+    }
+    Ok(record_batches_to_json_rows(&batches[..])?)
+}
+
+/// Accepts a json array of objects or newline delimited objects, nested
+/// objects are flattened the same way as ingested records
+fn parse_json(body: &[u8]) -> Result<Vec<Map<String, Value>>, anyhow::Error> {
+    let rows = match json::from_slice::<Vec<Value>>(body) {
+        Ok(rows) => rows,
+        Err(_) => {
+            let mut rows = vec![];
+            for line in body.split(|b| *b == b'\n') {
+                if line.iter().all(|b| b.is_ascii_whitespace()) {
+                    continue;
+                }
+                rows.push(json::from_slice(line)?);
+            }
+            rows
+        }
+    };
+    let mut result = Vec::with_capacity(rows.len());
+    for row in rows {
+        if !row.is_object() {
+            return Err(anyhow::anyhow!("lookup table rows should be objects"));
+        }
+        if let Value::Object(row) = json::flatten_json(&row) {
+            result.push(row);
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_lookup_data() {
+        let rows = parse_csv(b"service,team\napi,core\nweb,frontend\n").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get("team").unwrap().as_str(), Some("core"));
+
+        let rows = parse_json(br#"[{"service":"api","team":"core"}]"#).unwrap();
+        assert_eq!(rows.len(), 1);
+        let rows = parse_json(b"{\"service\":\"api\"}\n{\"service\":\"web\"}\n").unwrap();
+        assert_eq!(rows.len(), 2);
+    }
+
+    #[test]
+    fn test_is_valid_lookup_table_name() {
+        assert!(is_valid_lookup_table_name("owners"));
+        assert!(is_valid_lookup_table_name("_geo_2023"));
+        assert!(!is_valid_lookup_table_name("tbl"));
+        assert!(!is_valid_lookup_table_name("TBL"));
+        assert!(!is_valid_lookup_table_name("2023_geo"));
+        assert!(!is_valid_lookup_table_name("geo-ip"));
+        assert!(!is_valid_lookup_table_name(""));
+    }
+}
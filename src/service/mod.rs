@@ -14,18 +14,31 @@
 
 pub mod alert_manager;
 pub mod alerts;
+pub mod bloom_filter;
+pub mod column_stats;
 pub mod compact;
 pub mod dashboards;
 pub mod db;
+pub mod delete_by_query;
+pub mod field_stats;
 pub mod file_list;
+pub mod fulltext_index;
 pub mod functions;
 pub mod logs;
+pub mod lookup_tables;
 pub mod metrics;
 pub mod organization;
+pub mod reindex;
 pub mod router;
 pub mod schema;
+pub mod schema_changes;
 pub mod search;
+pub mod sidecar;
 pub mod stream;
+pub mod stream_aliases;
+pub mod stream_copy;
+pub mod stream_templates;
 pub mod traces;
 pub mod triggers;
 pub mod users;
+pub mod volume;
@@ -12,12 +12,16 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use actix_web::{http, HttpResponse};
 use rand::distributions::{Alphanumeric, DistString};
+use std::io::Error;
 use tracing::info_span;
 
 use super::stream::get_streams;
 use super::users;
-use crate::meta::organization::{IngestionPasscode, OrgSummary};
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::organization::{IngestionPasscode, OrgSummary, OrganizationSettings};
+use crate::meta::stream::MAX_DATA_RETENTION_DAYS;
 use crate::service::db;
 
 pub async fn get_summary(org_id: &str) -> OrgSummary {
@@ -60,6 +64,49 @@ pub async fn update_passcode(org_id: Option<&str>, user_id: &str) -> IngestionPa
     }
 }
 
+pub async fn get_settings(org_id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:organization:get_settings");
+    let _guard = loc_span.enter();
+    match db::organization::get_settings(org_id).await {
+        Ok(settings) => Ok(HttpResponse::Ok().json(settings)),
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
+pub async fn set_settings(
+    org_id: &str,
+    settings: OrganizationSettings,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:organization:set_settings");
+    let _guard = loc_span.enter();
+    if settings.data_retention < 0 || settings.data_retention > MAX_DATA_RETENTION_DAYS {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(format!(
+                "data_retention must be between 0 and {} days",
+                MAX_DATA_RETENTION_DAYS
+            )),
+        )));
+    }
+    match db::organization::set_settings(org_id, &settings).await {
+        Ok(_) => Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+            http::StatusCode::OK.into(),
+            "Organization settings saved".to_string(),
+        ))),
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
 #[cfg(test)]
 mod test_utils {
 
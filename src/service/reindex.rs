@@ -0,0 +1,588 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use chrono::Utc;
+use datafusion::arrow::datatypes::Schema;
+use prometheus::{opts, GaugeVec};
+use std::io::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::info_span;
+
+use crate::common::time::{time_column_literal, time_column_micros_expr, time_range_condition};
+use crate::infra::cluster;
+use crate::infra::config::CONFIG;
+use crate::infra::db::etcd;
+use crate::infra::storage::generate_partioned_file_key;
+use crate::infra::{ider, storage};
+use crate::meta::common::{FileKey, FileMeta};
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::reindex::{ReindexJob, ReindexJobList, ReindexRequest, ReindexStatus};
+use crate::meta::StreamType;
+use crate::service::search::datafusion::exec::{
+    merge_parquet_files, query_parquet_files_stream, ParquetQueryStream,
+};
+use crate::service::stream::get_stream_setting_partition_time_level;
+use crate::service::{db, file_list, logs, sidecar};
+
+/// records are written to the WAL in batches of this size
+const REPLAY_BATCH_SIZE: usize = 1000;
+/// a running job not updated for 10 minutes is taken over by another node
+const STALE_JOB_MICROS: i64 = 600_000_000;
+/// the time of each replayed record, used to checkpoint within a file
+const RECORD_TIME_FIELD: &str = "_reindex_time";
+
+pub async fn create_job(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    user: &str,
+    req: ReindexRequest,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:reindex:create");
+    let _guard = loc_span.enter();
+    if stream_type != StreamType::Logs {
+        return Ok(bad_request(
+            "reindex is only supported for logs streams".to_string(),
+        ));
+    }
+    let target_stream = match validate_request(stream_name, &req) {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e.to_string())),
+    };
+    let schema = db::schema::get(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    if schema == Schema::empty() {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("stream not found".to_owned()),
+        )));
+    }
+
+    // the files are listed again when the job starts
+    let files = match list_files(org_id, stream_name, req.start_time, req.end_time).await {
+        Ok(v) => v,
+        Err(e) => return Ok(internal_error(e.to_string())),
+    };
+    let now = Utc::now().timestamp_micros();
+    let job = ReindexJob {
+        id: ider::generate(),
+        stream_name: stream_name.to_string(),
+        target_stream,
+        start_time: req.start_time,
+        end_time: req.end_time,
+        max_records_per_second: req.max_records_per_second,
+        user: user.to_string(),
+        status: ReindexStatus::Pending,
+        node: String::new(),
+        files,
+        files_done: 0,
+        file_checkpoint: 0,
+        records: 0,
+        failed_records: 0,
+        error: None,
+        created_at: now,
+        updated_at: now,
+    };
+    if let Err(e) = db::reindex::set(org_id, &job).await {
+        return Ok(internal_error(e.to_string()));
+    }
+    Ok(HttpResponse::Accepted().json(job))
+}
+
+pub async fn get_job(org_id: &str, id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:reindex:get");
+    let _guard = loc_span.enter();
+    match db::reindex::get(org_id, id).await {
+        Ok(Some(job)) => Ok(HttpResponse::Ok().json(job)),
+        Ok(None) => Ok(not_found()),
+        Err(e) => Ok(internal_error(e.to_string())),
+    }
+}
+
+pub async fn list_jobs(org_id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:reindex:list");
+    let _guard = loc_span.enter();
+    match db::reindex::list(org_id).await {
+        Ok(mut list) => {
+            list.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+            Ok(HttpResponse::Ok().json(ReindexJobList { list }))
+        }
+        Err(e) => Ok(internal_error(e.to_string())),
+    }
+}
+
+/// A pending job is cancelled at once, a running job stops after the file in
+/// progress
+pub async fn cancel_job(org_id: &str, id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:reindex:cancel");
+    let _guard = loc_span.enter();
+    let mut job = match db::reindex::get(org_id, id).await {
+        Ok(Some(job)) => job,
+        Ok(None) => return Ok(not_found()),
+        Err(e) => return Ok(internal_error(e.to_string())),
+    };
+    job.status = match job.status {
+        ReindexStatus::Pending => ReindexStatus::Cancelled,
+        ReindexStatus::Running | ReindexStatus::Cancelling => ReindexStatus::Cancelling,
+        _ => return Ok(bad_request("reindex job is already finished".to_string())),
+    };
+    job.updated_at = Utc::now().timestamp_micros();
+    if let Err(e) = db::reindex::set(org_id, &job).await {
+        return Ok(internal_error(e.to_string()));
+    }
+    Ok(HttpResponse::Ok().json(job))
+}
+
+/// validates the request, returns the target stream
+fn validate_request(stream_name: &str, req: &ReindexRequest) -> Result<String, anyhow::Error> {
+    if req.start_time <= 0 || req.end_time <= req.start_time {
+        return Err(anyhow::anyhow!("invalid time range"));
+    }
+    let target_stream = req.target_stream.trim();
+    if target_stream.contains('/') {
+        return Err(anyhow::anyhow!("target_stream is not valid"));
+    }
+    if target_stream.is_empty() {
+        return Ok(stream_name.to_string());
+    }
+    Ok(target_stream.to_string())
+}
+
+async fn list_files(
+    org_id: &str,
+    stream_name: &str,
+    start_time: i64,
+    end_time: i64,
+) -> Result<Vec<String>, anyhow::Error> {
+    let files = file_list::get_file_list(
+        org_id,
+        stream_name,
+        Some(StreamType::Logs),
+        start_time,
+        end_time,
+    )
+    .await?;
+    let mut list = Vec::with_capacity(files.len());
+    for file in files {
+        let file_meta = file_list::get_file_meta(&file).await?;
+        if file_meta.max_ts < start_time || file_meta.min_ts >= end_time {
+            continue;
+        }
+        list.push(file);
+    }
+    list.sort();
+    Ok(list)
+}
+
+/// Runs the unfinished jobs one by one, called by the ingesters. A job is
+/// resumed by its node after a restart, or taken over by another node once
+/// it has not been updated for a while.
+pub async fn run_jobs() -> Result<(), anyhow::Error> {
+    for (org_id, job) in db::reindex::list_all().await? {
+        if job.status.is_finished() {
+            continue;
+        }
+        let job = match claim_job(&org_id, &job.id).await? {
+            Some(job) => job,
+            None => continue,
+        };
+        run(&org_id, job).await;
+    }
+    Ok(())
+}
+
+async fn claim_job(org_id: &str, id: &str) -> Result<Option<ReindexJob>, anyhow::Error> {
+    let mut locker = None;
+    if !CONFIG.common.local_mode {
+        let mut lock = etcd::Locker::new("reindex/claim");
+        lock.lock(CONFIG.etcd.command_timeout).await?;
+        locker = Some(lock);
+    }
+    let ret = claim_job_inner(org_id, id).await;
+    if locker.is_some() {
+        // release cluster lock
+        let mut lock = locker.unwrap();
+        lock.unlock().await?;
+    }
+    ret
+}
+
+async fn claim_job_inner(org_id: &str, id: &str) -> Result<Option<ReindexJob>, anyhow::Error> {
+    let mut job = match db::reindex::get(org_id, id).await? {
+        Some(job) => job,
+        None => return Ok(None),
+    };
+    let now = Utc::now().timestamp_micros();
+    let stale = now - job.updated_at > STALE_JOB_MICROS;
+    let claimable = match job.status {
+        ReindexStatus::Pending => true,
+        ReindexStatus::Running | ReindexStatus::Cancelling => {
+            job.node == *cluster::LOCAL_NODE_UUID || stale
+        }
+        _ => false,
+    };
+    if !claimable {
+        return Ok(None);
+    }
+    if job.status == ReindexStatus::Pending {
+        job.status = ReindexStatus::Running;
+    }
+    job.node = cluster::LOCAL_NODE_UUID.clone();
+    job.updated_at = now;
+    db::reindex::set(org_id, &job).await?;
+    Ok(Some(job))
+}
+
+async fn run(org_id: &str, mut job: ReindexJob) {
+    match replay(org_id, &mut job).await {
+        Ok(_) => {
+            if job.status == ReindexStatus::Running {
+                job.status = ReindexStatus::Completed;
+            }
+        }
+        Err(e) => {
+            log::error!("[REINDEX] job {} error: {}", job.id, e);
+            job.status = ReindexStatus::Failed;
+            job.error = Some(e.to_string());
+        }
+    }
+    job.updated_at = Utc::now().timestamp_micros();
+    if let Err(e) = db::reindex::set(org_id, &job).await {
+        log::error!("[REINDEX] save job {} error: {}", job.id, e);
+    }
+    log::info!(
+        "[REINDEX] job {} on {}/{} into {} {:?}, files: {}/{}, records: {}, failed: {}",
+        job.id,
+        org_id,
+        job.stream_name,
+        job.target_stream,
+        job.status,
+        job.files_done,
+        job.files.len(),
+        job.records,
+        job.failed_records
+    );
+}
+
+/// the state shared by the files of a job while replaying them
+struct Replay {
+    schema: Arc<Schema>,
+    stream_schema: Schema,
+    time_col: String,
+    time_range: String,
+    ingest_stats: GaugeVec,
+    started: Instant,
+    replayed: u64,
+}
+
+/// reindex steps:
+/// 1. list the files of the time range when the job starts, the records
+///    replayed in place are not picked up again
+/// 2. get a cluster lock for compactor stream, the file must not be merged
+///    meanwhile
+/// 3. read the records of the time range from the file in batches
+/// 4. run each batch through the ingestion pipeline of the target stream,
+///    saving the time replayed up to
+/// 5. in place, drop the records of the time range from the file
+/// 6. release cluster lock
+/// 7. save the progress, stop when the job is cancelled
+async fn replay(org_id: &str, job: &mut ReindexJob) -> Result<(), anyhow::Error> {
+    let stream_schema = db::schema::get(org_id, &job.stream_name, Some(StreamType::Logs)).await?;
+    let schema = Arc::new(
+        stream_schema
+            .clone()
+            .with_metadata(std::collections::HashMap::new()),
+    );
+    let mut state = Replay {
+        time_col: time_column_micros_expr(&schema),
+        time_range: time_range_condition(&schema, job.start_time, job.end_time),
+        schema,
+        stream_schema,
+        // replayed records are not part of the ingestion metrics
+        ingest_stats: GaugeVec::new(
+            opts!("reindex_stats", "Reindex stats metric"),
+            &["org", "name", "field"],
+        )
+        .unwrap(),
+        started: Instant::now(),
+        replayed: 0,
+    };
+
+    if job.files_done == 0 && job.file_checkpoint == 0 {
+        job.files = list_files(org_id, &job.stream_name, job.start_time, job.end_time).await?;
+    }
+
+    while job.files_done < job.files.len() {
+        // pick up cancellation, and stop if the job was taken over
+        if check_job(org_id, job).await? == ReindexStatus::Cancelling {
+            job.status = ReindexStatus::Cancelled;
+            return Ok(());
+        }
+
+        let file = job.files[job.files_done].clone();
+        let mut locker = None;
+        if !CONFIG.common.local_mode {
+            let lock_key = format!(
+                "compactor/files/{}/{}/{}",
+                org_id,
+                StreamType::Logs,
+                job.stream_name
+            );
+            let mut lock = etcd::Locker::new(&lock_key);
+            lock.lock(CONFIG.etcd.command_timeout).await?;
+            locker = Some(lock);
+        }
+        let ret = replay_file(org_id, job, &file, &mut state).await;
+        if locker.is_some() {
+            // release cluster lock
+            let mut lock = locker.unwrap();
+            lock.unlock().await?;
+        }
+        ret?;
+
+        job.files_done += 1;
+        job.file_checkpoint = 0;
+        job.updated_at = Utc::now().timestamp_micros();
+        db::reindex::set(org_id, job).await?;
+    }
+    Ok(())
+}
+
+async fn replay_file(
+    org_id: &str,
+    job: &mut ReindexJob,
+    file: &str,
+    state: &mut Replay,
+) -> Result<(), anyhow::Error> {
+    // the files listed when the job started may be merged after a restart,
+    // replaying the merged file would repeat the records replayed already
+    let file_meta = match file_list::get_file_meta(file).await {
+        Ok(v) => v,
+        Err(_) => {
+            return Err(anyhow::anyhow!(
+                "file {} of the job was merged or removed meanwhile",
+                file
+            ))
+        }
+    };
+    let mut query = format!(
+        "SELECT *, {} AS \"{}\" FROM tbl WHERE {}",
+        state.time_col, RECORD_TIME_FIELD, state.time_range
+    );
+    if job.file_checkpoint > 0 {
+        query.push_str(&format!(
+            " AND {} >= {}",
+            CONFIG.common.time_stamp_col,
+            time_column_literal(&state.schema, job.file_checkpoint + 1)
+        ));
+    }
+    query.push_str(&format!(" ORDER BY \"{}\"", RECORD_TIME_FIELD));
+    let mut stream = query_parquet_files_stream(
+        state.schema.clone(),
+        &[(state.schema.clone(), vec![file.to_string()])],
+        &query,
+        REPLAY_BATCH_SIZE,
+    )
+    .await?;
+    let ret = replay_batches(org_id, job, &mut stream, state).await;
+    stream.close().await?;
+    ret?;
+
+    if job.in_place() {
+        drop_time_range(
+            org_id,
+            job,
+            file,
+            file_meta,
+            state.schema.clone(),
+            &state.stream_schema,
+            &state.time_range,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+async fn replay_batches(
+    org_id: &str,
+    job: &mut ReindexJob,
+    stream: &mut ParquetQueryStream,
+    state: &mut Replay,
+) -> Result<(), anyhow::Error> {
+    while let Some(mut rows) = stream.next().await? {
+        for chunk in rows.chunks_mut(REPLAY_BATCH_SIZE) {
+            let last_time = chunk
+                .iter_mut()
+                .filter_map(|row| {
+                    row.as_object_mut()
+                        .and_then(|row| row.remove(RECORD_TIME_FIELD))
+                        .and_then(|v| v.as_i64())
+                })
+                .max()
+                .unwrap_or_default();
+            let statuses = logs::json::ingest_values(
+                org_id,
+                &job.target_stream,
+                chunk,
+                0,
+                &state.ingest_stats,
+                true,
+            )
+            .await;
+            for stream_status in statuses {
+                job.records += stream_status.status.successful as u64;
+                job.failed_records += stream_status.status.failed as u64;
+            }
+            // the records are read by time, those sharing the time of the
+            // last one may continue in the next batch
+            job.file_checkpoint = job.file_checkpoint.max(last_time - 1);
+            check_job(org_id, job).await?;
+            job.updated_at = Utc::now().timestamp_micros();
+            db::reindex::set(org_id, job).await?;
+
+            if job.max_records_per_second > 0 {
+                state.replayed += chunk.len() as u64;
+                let expected = Duration::from_secs_f64(
+                    state.replayed as f64 / job.max_records_per_second as f64,
+                );
+                let elapsed = state.started.elapsed();
+                if expected > elapsed {
+                    tokio::time::sleep(expected - elapsed).await;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// returns the saved status of the job, fails when it was taken over
+async fn check_job(org_id: &str, job: &ReindexJob) -> Result<ReindexStatus, anyhow::Error> {
+    match db::reindex::get(org_id, &job.id).await? {
+        Some(current) if current.node != job.node => {
+            Err(anyhow::anyhow!("job taken over by node {}", current.node))
+        }
+        Some(current) => Ok(current.status),
+        None => Err(anyhow::anyhow!("job {} not found", job.id)),
+    }
+}
+
+/// removes the replayed records from the file, the records out of the time
+/// range are kept in a rewritten file
+async fn drop_time_range(
+    org_id: &str,
+    job: &ReindexJob,
+    file: &str,
+    file_meta: FileMeta,
+    schema: Arc<Schema>,
+    stream_schema: &Schema,
+    time_range: &str,
+) -> Result<(), anyhow::Error> {
+    let storage = &storage::DEFAULT;
+    let mut events = Vec::with_capacity(2);
+    if file_meta.min_ts < job.start_time || file_meta.max_ts >= job.end_time {
+        let mut buf = Vec::new();
+        let mut new_file_meta =
+            merge_parquet_files(&mut buf, schema, &[file.to_string()], Some(time_range)).await?;
+        if new_file_meta.records > 0 {
+            // the original size is not known after rewrite, estimate it by records
+            new_file_meta.original_size = file_meta
+                .original_size
+                .checked_mul(new_file_meta.records)
+                .and_then(|v| v.checked_div(file_meta.records))
+                .unwrap_or(file_meta.original_size);
+            new_file_meta.compressed_size = buf.len() as u64;
+            let new_file_key = generate_partioned_file_key(
+                org_id,
+                &job.stream_name,
+                StreamType::Logs,
+                new_file_meta.min_ts,
+                get_stream_setting_partition_time_level(stream_schema),
+                &CONFIG.common.file_ext_parquet,
+            );
+            let new_file_key = format!("files/{}{}", new_file_key.0, new_file_key.1);
+            storage.put(&new_file_key, buf.into()).await?;
+            events.push(FileKey {
+                key: new_file_key,
+                meta: new_file_meta,
+                deleted: false,
+            });
+        }
+    }
+    events.push(FileKey {
+        key: file.to_string(),
+        meta: FileMeta::default(),
+        deleted: true,
+    });
+
+    // eg: files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet
+    let hour = file
+        .split('/')
+        .skip(4)
+        .take(4)
+        .collect::<Vec<_>>()
+        .join("/");
+    db::file_list::set_events(&hour, &events).await?;
+    if let Err(e) = storage.del(file).await {
+        log::error!("[REINDEX] delete file {} failed: {}", file, e);
+    }
+    sidecar::delete(file).await;
+    Ok(())
+}
+
+fn bad_request(message: String) -> HttpResponse {
+    HttpResponse::BadRequest().json(MetaHttpResponse::error(
+        http::StatusCode::BAD_REQUEST.into(),
+        Some(message),
+    ))
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound().json(MetaHttpResponse::error(
+        http::StatusCode::NOT_FOUND.into(),
+        Some("reindex job not found".to_owned()),
+    ))
+}
+
+fn internal_error(message: String) -> HttpResponse {
+    HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+        http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+        Some(message),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_request() {
+        let mut req = ReindexRequest {
+            start_time: 1667978841110,
+            end_time: 1667978845354,
+            target_stream: "".to_string(),
+            max_records_per_second: 0,
+        };
+        assert_eq!(validate_request("olympics", &req).unwrap(), "olympics");
+        req.target_stream = " olympics_v2 ".to_string();
+        assert_eq!(validate_request("olympics", &req).unwrap(), "olympics_v2");
+        req.target_stream = "a/b".to_string();
+        assert!(validate_request("olympics", &req).is_err());
+        req.target_stream = "".to_string();
+        req.end_time = req.start_time;
+        assert!(validate_request("olympics", &req).is_err());
+    }
+}
@@ -23,7 +23,10 @@ use tracing::info_span;
 
 use crate::infra::config::CONFIG;
 use crate::meta::{ingestion::StreamSchemaChk, StreamType};
-use crate::service::db;
+use crate::service::stream::{get_stream_setting_nested_fields, get_stream_setting_timestamp_unit};
+use crate::service::stream_aliases::resolve_alias;
+use crate::service::stream_templates::{apply_template, template_settings};
+use crate::service::{db, schema_changes};
 
 pub async fn schema_evolution(
     org_id: &str,
@@ -43,15 +46,18 @@ pub async fn schema_evolution(
         let mut metadata = HashMap::new();
         metadata.insert("created_at".to_string(), min_ts.to_string());
         log::info!("schema_evolution: setting schema for {:?}", stream_name);
-        db::schema::set(
+        let schema = apply_template(
             org_id,
             stream_name,
             stream_type,
-            &inferred_schema.clone().with_metadata(metadata),
-            Some(min_ts),
+            inferred_schema.clone().with_metadata(metadata),
         )
-        .await
-        .unwrap();
+        .await;
+        let schema = apply_timestamp_unit(schema);
+        let schema = apply_nested_types(schema.clone(), &schema);
+        db::schema::set(org_id, stream_name, stream_type, &schema, Some(min_ts))
+            .await
+            .unwrap();
     } else if !inferred_schema.fields().eq(schema.fields()) {
         let schema_fields: HashSet<_> = schema.fields().iter().collect();
         let field_datatype_delta: Vec<_> = inferred_schema
@@ -78,16 +84,21 @@ pub async fn schema_evolution(
                 );
             }
             Ok(merged) => {
-                db::schema::set(org_id, stream_name, stream_type, &merged, Some(min_ts))
-                    .await
-                    .unwrap();
+                let created =
+                    db::schema::set(org_id, stream_name, stream_type, &merged, Some(min_ts))
+                        .await
+                        .unwrap();
+                // only the node writing the new version reports it
+                if created {
+                    schema_changes::notify(org_id, stream_name, stream_type, &schema, &merged);
+                }
             }
         };
     }
 }
 
 // Hack to allow widening conversion , method overrides Schema::try_merge
-fn try_merge(schemas: impl IntoIterator<Item = Schema>) -> Result<Schema, ArrowError> {
+pub fn try_merge(schemas: impl IntoIterator<Item = Schema>) -> Result<Schema, ArrowError> {
     let mut merged_metadata: HashMap<String, String> = HashMap::new();
     let mut merged_fields: Vec<Field> = Vec::new();
     // TODO : this dummy initilization is to avoid compilar complaining for unintilized value
@@ -115,7 +126,12 @@ fn try_merge(schemas: impl IntoIterator<Item = Schema>) -> Result<Schema, ArrowE
                     continue;
                 }
                 new_field = false;
-                if merged_field.data_type() != field.data_type() {
+                // the children of struct fields are merged
+                let both_struct = matches!(
+                    (merged_field.data_type(), field.data_type()),
+                    (DataType::Struct(_), DataType::Struct(_))
+                );
+                if merged_field.data_type() != field.data_type() && !both_struct {
                     if !CONFIG.common.widening_schema_evoluation {
                         return Err(ArrowError::SchemaError(format!(
                             "Fail to merge schema due to conflicting data type[{}:{}].",
@@ -149,6 +165,84 @@ fn try_merge(schemas: impl IntoIterator<Item = Schema>) -> Result<Schema, ArrowE
     Ok(merged)
 }
 
+/// Sets the type of the time column, the other fields are kept
+pub fn set_time_column_type(schema: Schema, data_type: &DataType) -> Schema {
+    set_field_type(schema, &CONFIG.common.time_stamp_col, data_type)
+}
+
+/// Sets the type of a field, the other fields are kept
+pub fn set_field_type(schema: Schema, name: &str, data_type: &DataType) -> Schema {
+    let metadata = schema.metadata().clone();
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|field| {
+            if field.name() == name {
+                Field::new(field.name(), data_type.clone(), field.is_nullable())
+            } else {
+                field.clone()
+            }
+        })
+        .collect();
+    Schema::new_with_metadata(fields, metadata)
+}
+
+/// Types the time column as the timestamp unit of the stream settings
+pub fn apply_timestamp_unit(schema: Schema) -> Schema {
+    match get_stream_setting_timestamp_unit(&schema) {
+        Some(unit)
+            if schema
+                .field_with_name(&CONFIG.common.time_stamp_col)
+                .is_ok() =>
+        {
+            set_time_column_type(schema, &unit.data_type())
+        }
+        _ => schema,
+    }
+}
+
+/// Records hold the time column as Int64, the schema inferred from them
+/// takes its type from the stream schema
+pub fn align_time_column(inferred_schema: Schema, schema: &Schema) -> Schema {
+    match schema.field_with_name(&CONFIG.common.time_stamp_col) {
+        Ok(field) if matches!(field.data_type(), DataType::Timestamp(_, _)) => {
+            set_time_column_type(inferred_schema, field.data_type())
+        }
+        _ => inferred_schema,
+    }
+}
+
+/// Objects of map fields are inferred as structs, they get the map type of
+/// the nested fields of the stream settings in `schema`
+pub fn apply_nested_types(inferred_schema: Schema, schema: &Schema) -> Schema {
+    let has_struct = inferred_schema
+        .fields()
+        .iter()
+        .any(|f| matches!(f.data_type(), DataType::Struct(_)));
+    if !has_struct {
+        return inferred_schema;
+    }
+    let map_fields: HashMap<String, DataType> = get_stream_setting_nested_fields(schema)
+        .into_iter()
+        .filter_map(|f| f.field_type.data_type().map(|t| (f.name, t)))
+        .collect();
+    if map_fields.is_empty() {
+        return inferred_schema;
+    }
+    let metadata = inferred_schema.metadata().clone();
+    let fields = inferred_schema
+        .fields()
+        .iter()
+        .map(|field| match map_fields.get(field.name()) {
+            Some(data_type) if matches!(field.data_type(), DataType::Struct(_)) => {
+                Field::new(field.name(), data_type.clone(), field.is_nullable())
+            }
+            _ => field.clone(),
+        })
+        .collect();
+    Schema::new_with_metadata(fields, metadata)
+}
+
 fn is_widening_conversion(from: &DataType, to: &DataType) -> bool {
     let allowed_type = match from {
         DataType::Boolean => vec![DataType::Utf8],
@@ -198,11 +292,23 @@ pub async fn check_for_schema(
 
     let mut schema_reader = BufReader::new(val_str.as_bytes());
     let inferred_schema = infer_json_schema(&mut schema_reader, None).unwrap();
+    let inferred_schema = align_time_column(inferred_schema, &schema);
+    let inferred_schema = apply_nested_types(inferred_schema, &schema);
     if schema.fields.eq(&inferred_schema.fields) {
         return (true, None);
     }
 
-    if schema == Schema::empty() {
+    // the schema of a new stream may only hold the template settings, the
+    // template is applied when the schema is written
+    if schema.fields().is_empty() {
+        // writes to an alias would create a stream hiding it, eg: a renamed stream
+        if resolve_alias(org_id, stream_type, stream_name).is_some() {
+            return (false, None);
+        }
+        let inferred_schema =
+            apply_template(org_id, stream_name, stream_type, inferred_schema).await;
+        let inferred_schema = apply_timestamp_unit(inferred_schema);
+        let inferred_schema = apply_nested_types(inferred_schema.clone(), &inferred_schema);
         stream_schema_map.insert(stream_name.to_string(), inferred_schema.clone());
         db::schema::set(
             org_id,
@@ -231,9 +337,14 @@ pub async fn check_for_schema(
         Err(ref _e) => (true, Some(field_datatype_delta)), //return (false, None),
         Ok(merged) => {
             log::info!("Schema widening for stream {:?}", stream_name);
-            db::schema::set(org_id, stream_name, stream_type, &merged, Some(record_ts))
-                .await
-                .unwrap();
+            let created =
+                db::schema::set(org_id, stream_name, stream_type, &merged, Some(record_ts))
+                    .await
+                    .unwrap();
+            // only the node writing the new version reports it
+            if created {
+                schema_changes::notify(org_id, stream_name, stream_type, &schema, &merged);
+            }
             let item_set: HashSet<_> = schema.fields.iter().collect();
             let field_datatype_delta: Vec<_> = inferred_schema
                 .clone()
@@ -258,13 +369,17 @@ pub async fn stream_schema_exists(
         has_fields: false,
         has_partition_keys: false,
     };
-    let schema;
+    let mut schema;
     if stream_schema_map.contains_key(stream_name) {
         schema = stream_schema_map.get(stream_name).unwrap().clone();
     } else {
         schema = db::schema::get(org_id, stream_name, Some(stream_type))
             .await
             .unwrap();
+        // a new stream picks up the settings of the matching template
+        if schema == Schema::empty() {
+            schema = template_settings(org_id, stream_name, stream_type, schema);
+        }
         stream_schema_map.insert(stream_name.to_string(), schema.clone());
     }
     let fields = schema.fields();
@@ -304,11 +419,12 @@ pub async fn add_stream_schema(
         None => HashMap::new(),
     };
     metadata.insert("created_at".to_string(), min_ts.to_string());
+    let inferred_schema = apply_timestamp_unit(inferred_schema.with_metadata(metadata));
     db::schema::set(
         org_id,
         stream_name,
         stream_type,
-        &inferred_schema.clone().with_metadata(metadata),
+        &inferred_schema,
         Some(min_ts),
     )
     .await
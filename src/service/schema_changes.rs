@@ -0,0 +1,165 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use chrono::Utc;
+use datafusion::arrow::datatypes::Schema;
+use prometheus::{opts, GaugeVec};
+use serde_json::{json, Value};
+
+use crate::common::notification::send_notification;
+use crate::infra::config::CONFIG;
+use crate::meta::schema::SchemaChangeEvent;
+use crate::meta::StreamType;
+use crate::service::logs;
+use crate::service::stream::{get_stream_setting_notify_schema_changes, schema_diff};
+
+/// Reports the fields added or widened by a new schema version of a stream
+/// opted in, to the webhook and the audit stream of the org
+pub fn notify(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    old_schema: &Schema,
+    new_schema: &Schema,
+) {
+    if !get_stream_setting_notify_schema_changes(new_schema)
+        || is_audit_stream(stream_name, stream_type)
+    {
+        return;
+    }
+    let diff = schema_diff(0, old_schema, 0, new_schema);
+    if diff.added.is_empty() && diff.changed.is_empty() {
+        return;
+    }
+    let event = SchemaChangeEvent {
+        org_id: org_id.to_string(),
+        stream_name: stream_name.to_string(),
+        stream_type,
+        timestamp: Utc::now().timestamp_micros(),
+        added: diff.added,
+        changed: diff.changed,
+    };
+    log::info!(
+        "schema change of stream {}/{}/{}: added {:?}, changed {:?}",
+        org_id,
+        stream_type,
+        stream_name,
+        event.added,
+        event.changed
+    );
+    tokio::task::spawn(async move { send(event).await });
+}
+
+/// The audit stream is a logs stream, its own changes are not reported
+fn is_audit_stream(stream_name: &str, stream_type: StreamType) -> bool {
+    stream_type == StreamType::Logs && stream_name == CONFIG.common.schema_change_stream
+}
+
+async fn send(event: SchemaChangeEvent) {
+    if !CONFIG.common.schema_change_webhook.is_empty() {
+        if let Err(e) = send_notification(
+            &CONFIG.common.schema_change_webhook,
+            serde_json::to_value(&event).unwrap(),
+        )
+        .await
+        {
+            log::error!("schema change webhook failed: {}", e);
+        }
+    }
+    if !CONFIG.common.schema_change_stream.is_empty() {
+        // the audit records are not part of the ingestion metrics
+        let ingest_stats = GaugeVec::new(
+            opts!("schema_change_stats", "Schema change stats metric"),
+            &["org", "name", "field"],
+        )
+        .unwrap();
+        let statuses = logs::json::ingest_values(
+            &event.org_id,
+            &CONFIG.common.schema_change_stream,
+            &[event_record(&event)],
+            0,
+            &ingest_stats,
+            false,
+        )
+        .await;
+        for stream_status in statuses {
+            if stream_status.status.failed > 0 {
+                log::error!(
+                    "schema change audit record failed: {}",
+                    stream_status.status.error
+                );
+            }
+        }
+    }
+}
+
+/// The record of the event in the audit stream, the fields are kept as text
+/// so that they don't turn into one column per field
+fn event_record(event: &SchemaChangeEvent) -> Value {
+    json!({
+        CONFIG.common.time_stamp_col.as_str(): event.timestamp,
+        "org_id": event.org_id,
+        "stream_name": event.stream_name,
+        "stream_type": event.stream_type.to_string(),
+        "added_fields": event
+            .added
+            .iter()
+            .map(|field| format!("{}:{}", field.name, field.prop_type))
+            .collect::<Vec<_>>()
+            .join(","),
+        "changed_fields": event
+            .changed
+            .iter()
+            .map(|field| format!("{}:{}->{}", field.name, field.from_type, field.to_type))
+            .collect::<Vec<_>>()
+            .join(","),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meta::schema::SchemaFieldChange;
+    use crate::meta::stream::StreamProperty;
+
+    #[test]
+    fn test_is_audit_stream() {
+        let name = CONFIG.common.schema_change_stream.as_str();
+        assert!(is_audit_stream(name, StreamType::Logs));
+        assert!(!is_audit_stream(name, StreamType::Metrics));
+    }
+
+    #[test]
+    fn test_event_record() {
+        let event = SchemaChangeEvent {
+            org_id: "default".to_string(),
+            stream_name: "web".to_string(),
+            stream_type: StreamType::Logs,
+            timestamp: 1,
+            added: vec![StreamProperty {
+                name: "route".to_string(),
+                prop_type: "Utf8".to_string(),
+            }],
+            changed: vec![SchemaFieldChange {
+                name: "status".to_string(),
+                from_type: "Int64".to_string(),
+                to_type: "Float64".to_string(),
+            }],
+        };
+        let record = event_record(&event);
+        assert_eq!(record["stream_type"], "logs");
+        assert_eq!(record["added_fields"], "route:Utf8");
+        assert_eq!(record["changed_fields"], "status:Int64->Float64");
+    }
+}
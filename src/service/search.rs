@@ -12,7 +12,11 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use ::datafusion::arrow::{datatypes::Schema, ipc, json as arrow_json, record_batch::RecordBatch};
+use ::datafusion::arrow::{
+    datatypes::{Schema, TimeUnit},
+    ipc, json as arrow_json,
+    record_batch::RecordBatch,
+};
 use ahash::AHashMap as HashMap;
 use http_auth_basic::Credentials;
 use std::io::Cursor;
@@ -23,6 +27,7 @@ use tracing::{info_span, Instrument};
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use uuid::Uuid;
 
+use crate::common::time::time_column_to_int64;
 use crate::handler::grpc::cluster_rpc;
 use crate::infra::cluster;
 use crate::infra::config::{CONFIG, ROOT_USER};
@@ -103,7 +108,10 @@ async fn search_in_local(req: cluster_rpc::SearchRequest) -> Result<Response, an
             "search_in_local: query num_batches: {:?}",
             reader.num_batches()
         );
-        let batches = reader.into_iter().map(Result::unwrap).collect::<Vec<_>>();
+        let batches = reader
+            .into_iter()
+            .map(|batch| time_column_to_int64(batch.unwrap(), TimeUnit::Microsecond))
+            .collect::<Result<Vec<_>, _>>()?;
         let json_rows = arrow_json::writer::record_batches_to_json_rows(&batches[..])?;
         let sources: Vec<serde_json::Value> = json_rows
             .into_iter()
@@ -232,39 +240,33 @@ async fn search_in_cluster(req: cluster_rpc::SearchRequest) -> Result<Response,
     let span3 = info_span!("service:search:cluster:prepare_filelist").entered();
 
     // partition by file_list
-    let mut file_list = match file_list::get_file_list(
-        &req.org_id,
-        &meta.stream_name,
-        Some(stream_type),
-        time_min,
-        time_max,
-    )
-    .await
-    {
-        Ok(file_list) => match (time_max - time_min) >= 3600_1000_1000 {
-            true => {
-                // over than 1 hour, just filter by partition key
-                let mut files = Vec::with_capacity(file_list.len());
-                for file in file_list {
-                    if meta.filter_source_by_partition_key(&file).await {
-                        files.push(file.clone());
-                    }
+    let mut file_list = Vec::new();
+    for stream_name in meta.sources.iter() {
+        let files = file_list::get_file_list(
+            &req.org_id,
+            stream_name,
+            Some(stream_type),
+            time_min,
+            time_max,
+        )
+        .await
+        .unwrap_or_default();
+        if (time_max - time_min) >= 3600_1000_1000 {
+            // over than 1 hour, just filter by partition key
+            for file in files {
+                if meta.filter_source_by_partition_key(&file).await {
+                    file_list.push(file);
                 }
-                files
             }
-            false => {
-                // less than 1 hour, use file meta reduce file list
-                let mut files = Vec::with_capacity(file_list.len());
-                for file in file_list {
-                    if meta.match_source(&file, false, stream_type).await {
-                        files.push(file.clone());
-                    }
+        } else {
+            // less than 1 hour, use file meta reduce file list
+            for file in files {
+                if meta.match_source(&file, false, stream_type).await {
+                    file_list.push(file);
                 }
-                files
             }
-        },
-        Err(_) => Vec::new(),
-    };
+        }
+    }
     file_list.sort();
     let file_num = file_list.len();
     let offset = match querier_num >= file_num {
@@ -490,7 +492,11 @@ async fn search_in_cluster(req: cluster_rpc::SearchRequest) -> Result<Response,
         None => Vec::new(),
     };
     if !batches_query.is_empty() {
-        let json_rows = arrow_json::writer::record_batches_to_json_rows(&batches_query[0][..])?;
+        let batches_query = batches_query[0]
+            .iter()
+            .map(|batch| time_column_to_int64(batch.clone(), TimeUnit::Microsecond))
+            .collect::<Result<Vec<_>, _>>()?;
+        let json_rows = arrow_json::writer::record_batches_to_json_rows(&batches_query[..])?;
         let sources: Vec<serde_json::Value> = json_rows
             .into_iter()
             .map(serde_json::Value::Object)
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use ahash::AHashMap as HashMap;
+use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::datasource::file_format::file_type::FileType;
 use std::path::Path;
 use std::sync::atomic::Ordering;
@@ -35,6 +36,31 @@ pub async fn search(
     session_id: &str,
     sql: Arc<Sql>,
     stream_type: meta::StreamType,
+) -> super::SearchResult {
+    if sql.sources.len() <= 1 {
+        return search_stream(session_id, sql, stream_type).await;
+    }
+
+    // an alias is searched stream by stream
+    let mut results: HashMap<String, Vec<RecordBatch>> = HashMap::new();
+    let mut file_count = 0;
+    let mut scan_size = 0;
+    for sql in sql.split_sources() {
+        let session_id = format!("{}-{}", session_id, sql.stream_name);
+        let (batches, count, size) = search_stream(&session_id, Arc::new(sql), stream_type).await?;
+        for (key, batch) in batches {
+            results.entry(key).or_default().extend(batch);
+        }
+        file_count += count;
+        scan_size += size;
+    }
+    Ok((results, file_count, scan_size))
+}
+
+async fn search_stream(
+    session_id: &str,
+    sql: Arc<Sql>,
+    stream_type: meta::StreamType,
 ) -> super::SearchResult {
     let span1 = info_span!("service:search:cache:get_file_list");
     let guard1 = span1.enter();
@@ -72,7 +98,7 @@ pub async fn search(
         &session,
         stream_type,
         None,
-        HashMap::new(),
+        Some(Arc::new(sql.schema.clone())),
         &sql,
         &files,
         FileType::JSON,
@@ -0,0 +1,104 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use datafusion::{
+    arrow::{
+        array::{Array, ArrayRef, BooleanArray, ListArray, StringArray},
+        compute::cast,
+        datatypes::DataType,
+    },
+    error::{DataFusionError, Result},
+    logical_expr::{
+        ReturnTypeFunction, ScalarFunctionImplementation, ScalarUDF, Signature, Volatility,
+    },
+    physical_plan::functions::make_scalar_function,
+};
+use once_cell::sync::Lazy;
+use std::sync::Arc;
+
+/// The name of the array_contains UDF given to DataFusion.
+pub const ARRAY_CONTAINS_UDF_NAME: &str = "array_contains";
+
+/// Implementation of array_contains
+pub(crate) static ARRAY_CONTAINS_UDF: Lazy<ScalarUDF> = Lazy::new(|| {
+    let return_type: ReturnTypeFunction = Arc::new(|_| Ok(Arc::new(DataType::Boolean)));
+    ScalarUDF::new(
+        ARRAY_CONTAINS_UDF_NAME,
+        // takes two arguments: list, value
+        &Signature::any(2, Volatility::Immutable),
+        &return_type,
+        &array_contains_expr_impl(),
+    )
+});
+
+/// array_contains(list, value) is true when the list has an element equal to
+/// the value, both are compared as strings
+pub fn array_contains_expr_impl() -> ScalarFunctionImplementation {
+    make_scalar_function(array_contains)
+}
+
+fn array_contains(args: &[ArrayRef]) -> Result<ArrayRef> {
+    if args.len() != 2 {
+        return Err(DataFusionError::Execution(
+            "array_contains UDF expects a list and a value".to_string(),
+        ));
+    }
+    let list = args[0]
+        .as_any()
+        .downcast_ref::<ListArray>()
+        .ok_or_else(|| {
+            DataFusionError::Execution(format!(
+                "array_contains expects a list, got {}",
+                args[0].data_type()
+            ))
+        })?;
+    let values = cast(&args[1], &DataType::Utf8)?;
+    let values = values.as_any().downcast_ref::<StringArray>().unwrap();
+
+    let mut results = Vec::with_capacity(list.len());
+    for i in 0..list.len() {
+        if list.is_null(i) || values.is_null(i) {
+            results.push(None);
+            continue;
+        }
+        let items = cast(&list.value(i), &DataType::Utf8)?;
+        let items = items.as_any().downcast_ref::<StringArray>().unwrap();
+        let value = values.value(i);
+        results.push(Some(items.iter().any(|item| item == Some(value))));
+    }
+    Ok(Arc::new(BooleanArray::from(results)) as ArrayRef)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use datafusion::arrow::array::Int64Array;
+    use datafusion::arrow::datatypes::Int64Type;
+
+    #[test]
+    fn test_array_contains() {
+        let list = ListArray::from_iter_primitive::<Int64Type, _, _>(vec![
+            Some(vec![Some(1), Some(2)]),
+            Some(vec![Some(3)]),
+            None,
+        ]);
+        let values = Int64Array::from(vec![2, 2, 2]);
+        let result = array_contains(&[Arc::new(list), Arc::new(values)]).unwrap();
+        let result = result.as_any().downcast_ref::<BooleanArray>().unwrap();
+        assert_eq!(
+            result.iter().collect::<Vec<_>>(),
+            vec![Some(true), Some(false), None]
+        );
+    }
+}
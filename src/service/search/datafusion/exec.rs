@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use ahash::AHashMap as HashMap;
-use datafusion::arrow::datatypes::{DataType, Schema};
+use datafusion::arrow::array::{Array, ListArray, UInt32Builder};
+use datafusion::arrow::compute::take;
+use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
 use datafusion::arrow::json as arrowJson;
 use datafusion::arrow::record_batch::RecordBatch;
 use datafusion::datasource::file_format::file_type::{FileType, GetExt};
@@ -22,16 +24,23 @@ use datafusion::datasource::file_format::parquet::ParquetFormat;
 use datafusion::datasource::listing::{ListingOptions, ListingTable};
 use datafusion::datasource::listing::{ListingTableConfig, ListingTableUrl};
 use datafusion::datasource::object_store::ObjectStoreRegistry;
+use datafusion::datasource::MemTable;
 use datafusion::error::Result;
 use datafusion::execution::context::SessionConfig;
 use datafusion::execution::runtime_env::{RuntimeConfig, RuntimeEnv};
-use datafusion::prelude::{cast, col, Expr, SessionContext};
-use datafusion_common::DataFusionError;
+use datafusion::physical_plan::SendableRecordBatchStream;
+use datafusion::prelude::{cast, col, lit, DataFrame, Expr, SessionContext};
+use datafusion_common::{Column, DataFusionError, ScalarValue};
+use futures::StreamExt;
 use object_store::limit::LimitStore;
 use parquet::arrow::ArrowWriter;
 use parquet::file::properties::WriterProperties;
 use parquet::format::SortingColumn;
 use regex::Regex;
+use sqlparser::ast::{visit_expressions_mut, Expr as SqlExpr, SetExpr, Statement, TableFactor};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
 use std::sync::Arc;
 use std::time::Instant;
 use uuid::Uuid;
@@ -39,11 +48,14 @@ use uuid::Uuid;
 use super::storage::file_list;
 #[cfg(feature = "zo_functions")]
 use super::transform_udf::get_all_transform;
+use crate::common::time::{time_column_micros_expr, time_column_to_int64};
 use crate::infra::cache::tmpfs;
-use crate::infra::config::{get_parquet_compression, CONFIG};
+use crate::infra::config::{get_parquet_compression, CONFIG, LOOKUP_TABLES};
 use crate::meta::common::FileMeta;
 use crate::meta::{self, StreamType};
+use crate::service::schema::apply_nested_types;
 use crate::service::search::sql::Sql;
+use crate::service::stream::get_stream_setting_keep_nanos;
 
 const AGGREGATE_UDF_LIST: [&str; 6] = ["min", "max", "count", "avg", "sum", "array_agg"];
 
@@ -52,7 +64,7 @@ pub async fn sql(
     session: &meta::search::Session,
     stream_type: StreamType,
     schema: Option<Arc<Schema>>,
-    rules: HashMap<String, DataType>,
+    latest_schema: Option<Arc<Schema>>,
     sql: &Arc<Sql>,
     files: &Vec<String>,
     file_type: FileType,
@@ -119,6 +131,13 @@ pub async fn sql(
         ListingTableConfig::new_with_multi_paths(prefixes).with_listing_options(listing_options);
     if schema.is_none() {
         config = config.infer_schema(&ctx.state()).await.unwrap();
+        // map fields can't be told from struct fields by inference
+        if let (Some(latest_schema), Some(file_schema)) = (&latest_schema, &config.file_schema) {
+            config.file_schema = Some(Arc::new(apply_nested_types(
+                file_schema.as_ref().clone(),
+                latest_schema,
+            )));
+        }
     } else {
         config = config.with_schema(schema.as_ref().unwrap().clone());
     }
@@ -127,88 +146,103 @@ pub async fn sql(
         now.elapsed().as_secs_f64()
     );
 
+    let reconcile_exprs = match (&schema, &latest_schema, &config.file_schema) {
+        (Some(schema), Some(latest_schema), _) => reconcile_schema_exprs(schema, latest_schema)?,
+        // the WAL holds the time column as Int64 whatever its type in the stream
+        (None, Some(latest_schema), Some(file_schema)) => {
+            reconcile_time_column_exprs(file_schema, latest_schema)?
+        }
+        _ => None,
+    };
+    let (origin_sql, mut unnest_fields) = strip_unnest(&sql.origin_sql);
+    let mut aggs = Vec::with_capacity(sql.aggs.len());
+    for (name, agg) in sql.aggs.iter() {
+        let (agg_sql, fields) = strip_unnest(&agg.0);
+        unnest_fields.extend(fields);
+        aggs.push((name, agg_sql));
+    }
+    unnest_fields.sort();
+    unnest_fields.dedup();
+
     let table = ListingTable::try_new(config)?;
-    ctx.register_table("tbl", Arc::new(table))?;
+    if reconcile_exprs.is_none() && unnest_fields.is_empty() {
+        ctx.register_table("tbl", Arc::new(table))?;
+    } else {
+        // files of an older schema version are read through a view
+        // presenting them with the latest schema
+        ctx.register_table("tbl_raw", Arc::new(table))?;
+        let mut df = ctx.table("tbl_raw").await?;
+        if let Some(exprs) = reconcile_exprs {
+            df = df.select(exprs)?;
+        }
+        if unnest_fields.is_empty() {
+            ctx.register_table("tbl", df.into_view())?;
+        } else {
+            // list fields used with unnest are expanded to one row per
+            // element, the records are read in memory to do so
+            let mut schema = Schema::from(df.schema());
+            let mut batches = df.collect().await?;
+            for field in unnest_fields.iter() {
+                schema = unnest_schema(&schema, field)?;
+                batches = batches
+                    .iter()
+                    .map(|batch| unnest_batch(batch, field))
+                    .collect::<Result<Vec<_>>>()?;
+            }
+            let mem_table = MemTable::try_new(Arc::new(schema), vec![batches])?;
+            ctx.register_table("tbl", Arc::new(mem_table))?;
+        }
+    }
 
     // register UDF
     register_udf(&mut ctx, &sql.org_id).await;
+    // register lookup tables joined by the query
+    let mut joins = sql.meta.joins.clone();
+    for (_, agg) in sql.aggs.values() {
+        joins.extend(agg.joins.iter().cloned());
+    }
+    register_lookup_tables(&ctx, &sql.org_id, &joins)?;
     log::info!(
         "Register table took {:.3} seconds.",
         now.elapsed().as_secs_f64()
     );
 
     // Debug SQL
-    log::info!("Query sql: {}", sql.origin_sql);
+    log::info!("Query sql: {}", origin_sql);
 
     let mut result: HashMap<String, Vec<RecordBatch>> = HashMap::new();
     // query
-    let mut df = match ctx.sql(&sql.origin_sql).await {
+    let df = match ctx.sql(&origin_sql).await {
         Ok(df) => df,
         Err(e) => {
             log::error!(
                 "query sql execute failed, session: {:?}, sql: {}, err: {:?}",
                 session,
-                sql.origin_sql,
+                origin_sql,
                 e
             );
             return Err(e);
         }
     };
-    if !rules.is_empty() {
-        let fields = df.schema().fields();
-        let mut exprs = Vec::with_capacity(fields.len());
-        for field in fields {
-            if field.qualifier().is_none() {
-                exprs.push(col(field.name()));
-                continue;
-            }
-            exprs.push(match rules.get(field.name()) {
-                Some(rule) => Expr::Alias(
-                    Box::new(cast(col(field.name()), rule.clone())),
-                    field.name().to_string(),
-                ),
-                None => col(field.name()),
-            });
-        }
-        df = df.select(exprs)?;
-    }
     let batches = df.collect().await?;
     result.insert("query".to_string(), batches);
     log::info!("Query took {:.3} seconds.", now.elapsed().as_secs_f64());
     // aggs
-    for (name, sql) in sql.aggs.iter() {
+    for (name, sql) in aggs.iter() {
         // Debug SQL
-        log::info!("Query agg sql: {}", sql.0);
-        let mut df = match ctx.sql(&sql.0).await {
+        log::info!("Query agg sql: {}", sql);
+        let df = match ctx.sql(sql).await {
             Ok(df) => df,
             Err(e) => {
                 log::error!(
                     "aggs sql execute failed, session: {:?}, sql: {}, err: {:?}",
                     session,
-                    sql.0,
+                    sql,
                     e
                 );
                 return Err(e);
             }
         };
-        if !rules.is_empty() {
-            let fields = df.schema().fields();
-            let mut exprs = Vec::with_capacity(fields.len());
-            for field in fields {
-                if field.qualifier().is_none() {
-                    exprs.push(col(field.name()));
-                    continue;
-                }
-                exprs.push(match rules.get(field.name()) {
-                    Some(rule) => Expr::Alias(
-                        Box::new(cast(col(field.name()), rule.clone())),
-                        field.name().to_string(),
-                    ),
-                    None => col(field.name()),
-                });
-            }
-            df = df.select(exprs)?;
-        }
         let batches = df.collect().await?;
         result.insert(format!("agg_{}", name), batches);
         log::info!(
@@ -220,11 +254,105 @@ pub async fn sql(
 
     // drop table
     ctx.deregister_table("tbl")?;
+    ctx.deregister_table("tbl_raw")?;
     log::info!("Query all took {:.3} seconds.", now.elapsed().as_secs_f64());
 
     Ok(result)
 }
 
+/// Builds the projection presenting files written with `schema` as the latest
+/// schema: fields with a changed type are cast, missing fields are nulls and
+/// fields dropped from the latest schema are left out. Returns None when the
+/// schemas match.
+fn reconcile_schema_exprs(schema: &Schema, latest_schema: &Schema) -> Result<Option<Vec<Expr>>> {
+    if schema.fields() == latest_schema.fields() {
+        return Ok(None);
+    }
+    let mut exprs = Vec::with_capacity(latest_schema.fields().len());
+    for field in latest_schema.fields() {
+        let name = field.name();
+        let column = Expr::Column(Column::from_name(name));
+        exprs.push(match schema.field_with_name(name) {
+            Ok(v) if v.data_type() == field.data_type() => column,
+            Ok(v) if name == &CONFIG.common.time_stamp_col => Expr::Alias(
+                Box::new(time_column_cast(
+                    column,
+                    v.data_type(),
+                    field.data_type(),
+                    false,
+                )?),
+                name.to_string(),
+            ),
+            Ok(_) => Expr::Alias(
+                Box::new(cast(column, field.data_type().clone())),
+                name.to_string(),
+            ),
+            Err(_) => Expr::Alias(
+                Box::new(lit(ScalarValue::try_from(field.data_type())?)),
+                name.to_string(),
+            ),
+        });
+    }
+    Ok(Some(exprs))
+}
+
+/// Builds the projection of the files read from the WAL, where the time column
+/// is Int64 whatever its type in the latest schema. Returns None when no cast is
+/// needed.
+fn reconcile_time_column_exprs(
+    schema: &Schema,
+    latest_schema: &Schema,
+) -> Result<Option<Vec<Expr>>> {
+    let time_col = &CONFIG.common.time_stamp_col;
+    let (field, latest_field) = match (
+        schema.field_with_name(time_col),
+        latest_schema.field_with_name(time_col),
+    ) {
+        (Ok(field), Ok(latest_field)) if field.data_type() != latest_field.data_type() => {
+            (field, latest_field)
+        }
+        _ => return Ok(None),
+    };
+    let mut exprs = Vec::with_capacity(schema.fields().len());
+    for f in schema.fields() {
+        let column = Expr::Column(Column::from_name(f.name()));
+        exprs.push(if f.name() == time_col {
+            Expr::Alias(
+                Box::new(time_column_cast(
+                    column,
+                    field.data_type(),
+                    latest_field.data_type(),
+                    get_stream_setting_keep_nanos(latest_schema),
+                )?),
+                time_col.to_string(),
+            )
+        } else {
+            column
+        });
+    }
+    Ok(Some(exprs))
+}
+
+/// Casts the time column to `data_type`, Int64 values hold microseconds, or
+/// nanoseconds in the WAL of the streams keeping them
+fn time_column_cast(
+    column: Expr,
+    from: &DataType,
+    data_type: &DataType,
+    keep_nanos: bool,
+) -> Result<Expr> {
+    if from != &DataType::Int64 || !matches!(data_type, DataType::Timestamp(_, _)) {
+        return Ok(cast(column, data_type.clone()));
+    }
+    let unit = if keep_nanos {
+        TimeUnit::Nanosecond
+    } else {
+        TimeUnit::Microsecond
+    };
+    let expr = cast(column, DataType::Timestamp(unit, None));
+    Ok(cast(expr, data_type.clone()))
+}
+
 pub async fn merge(
     org_id: &str,
     offset: usize,
@@ -354,7 +482,7 @@ fn merge_write_recordbatch(batches: &[Vec<RecordBatch>]) -> Result<String> {
 }
 
 fn merge_rewrite_sql(sql: &str, schema: Arc<Schema>) -> Result<String> {
-    let mut sql = sql.to_string();
+    let (mut sql, _) = strip_unnest(&strip_join_for_merge(sql));
     let mut fields = Vec::new();
     let mut from_pos = 0;
     let sql_chars = sql.chars().collect::<Vec<char>>();
@@ -603,7 +731,12 @@ pub async fn convert_parquet_file(
             }
             exprs.push(match rules.get(field.name()) {
                 Some(rule) => Expr::Alias(
-                    Box::new(cast(col(field.name()), rule.clone())),
+                    Box::new(time_column_cast(
+                        col(field.name()),
+                        field.data_type(),
+                        rule,
+                        false,
+                    )?),
                     field.name().to_string(),
                 ),
                 None => col(field.name()),
@@ -637,10 +770,13 @@ pub async fn convert_parquet_file(
     Ok(())
 }
 
+/// merge the files into one parquet file, the rows matching `exclude` are
+/// left out when it is given
 pub async fn merge_parquet_files(
     buf: &mut Vec<u8>,
     schema: Arc<Schema>,
     files: &[String],
+    exclude: Option<&str>,
 ) -> Result<FileMeta> {
     let now = Instant::now();
 
@@ -678,10 +814,17 @@ pub async fn merge_parquet_files(
     let table = ListingTable::try_new(config)?;
     ctx.register_table("tbl", Arc::new(table))?;
 
+    // rows with a NULL predicate are kept
+    let where_str = match exclude {
+        Some(exclude) => format!(" WHERE ({}) IS NOT TRUE", exclude),
+        None => String::new(),
+    };
+
     // get meta data
+    let time_col = time_column_micros_expr(&schema);
     let meta_sql = format!(
-        "SELECT MIN({}) as min_ts, MAX({}) as max_ts, COUNT(1) as num_records FROM tbl",
-        CONFIG.common.time_stamp_col, CONFIG.common.time_stamp_col
+        "SELECT MIN({}) as min_ts, MAX({}) as max_ts, COUNT(1) as num_records FROM tbl{}",
+        time_col, time_col, where_str
     );
     let df = ctx.sql(&meta_sql).await?;
     let batches = df.collect().await.unwrap();
@@ -692,17 +835,18 @@ pub async fn merge_parquet_files(
         .collect();
     let record = result.pop().unwrap();
     let file_meta = FileMeta {
-        min_ts: record["min_ts"].as_i64().unwrap(),
-        max_ts: record["max_ts"].as_i64().unwrap(),
+        min_ts: record["min_ts"].as_i64().unwrap_or_default(),
+        max_ts: record["max_ts"].as_i64().unwrap_or_default(),
         records: record["num_records"].as_u64().unwrap(),
         original_size: 0,
         compressed_size: 0,
+        column_stats: vec![],
     };
 
     // get all sorted data
     let query_sql = format!(
-        "SELECT * FROM tbl ORDER BY {} DESC",
-        CONFIG.common.time_stamp_col
+        "SELECT * FROM tbl{} ORDER BY {} DESC",
+        where_str, CONFIG.common.time_stamp_col
     );
     let df = ctx.sql(&query_sql).await?;
     let schema: Schema = df.schema().into();
@@ -735,6 +879,229 @@ pub async fn merge_parquet_files(
     Ok(file_meta)
 }
 
+/// run the queries over the parquet files registered as `tbl`, returns the
+/// rows of each query. The files are grouped by the schema they were written
+/// with, each group is presented with the latest `schema`.
+pub async fn query_parquet_files(
+    schema: Arc<Schema>,
+    files_group: &[(Arc<Schema>, Vec<String>)],
+    queries: &[String],
+) -> Result<Vec<Vec<serde_json::Value>>> {
+    let now = Instant::now();
+
+    // query data
+    let runtime_env = create_runtime_env()?;
+    let session_config = SessionConfig::new()
+        .with_information_schema(false)
+        .with_batch_size(8192);
+    let ctx = SessionContext::with_config_rt(session_config.clone(), Arc::new(runtime_env));
+
+    let mut session_ids = Vec::with_capacity(files_group.len());
+    let ret =
+        query_parquet_files_inner(&ctx, &schema, files_group, queries, &mut session_ids).await;
+
+    // clear session
+    clear_parquet_files(&ctx, files_group.len(), session_ids).await?;
+
+    log::info!(
+        "query_parquet_files took {:.3} seconds.",
+        now.elapsed().as_secs_f64()
+    );
+
+    ret
+}
+
+async fn query_parquet_files_inner(
+    ctx: &SessionContext,
+    schema: &Arc<Schema>,
+    files_group: &[(Arc<Schema>, Vec<String>)],
+    queries: &[String],
+    session_ids: &mut Vec<String>,
+) -> Result<Vec<Vec<serde_json::Value>>> {
+    if !register_parquet_files(ctx, schema, files_group, session_ids).await? {
+        return Ok(vec![vec![]; queries.len()]);
+    }
+
+    let mut results = Vec::with_capacity(queries.len());
+    for query_sql in queries {
+        let batches = match ctx.sql(query_sql).await {
+            Ok(df) => df.collect().await,
+            Err(e) => Err(e),
+        };
+        let batches = match batches {
+            Ok(batches) => batches,
+            Err(e) => {
+                log::error!("query sql execute failed, sql: {}, err: {:?}", query_sql, e);
+                return Err(e);
+            }
+        };
+        let mut json_rows = Vec::new();
+        for batch in batches {
+            json_rows.extend(record_batch_to_json_rows(batch)?);
+        }
+        results.push(json_rows);
+    }
+    Ok(results)
+}
+
+/// The result of a query over parquet files, read batch by batch instead of
+/// being collected in memory.
+pub struct ParquetQueryStream {
+    ctx: SessionContext,
+    tables: usize,
+    session_ids: Vec<String>,
+    stream: Option<SendableRecordBatchStream>,
+}
+
+impl ParquetQueryStream {
+    /// returns the rows of the next record batch, `None` once the result is
+    /// read
+    pub async fn next(&mut self) -> Result<Option<Vec<serde_json::Value>>> {
+        let stream = match self.stream.as_mut() {
+            Some(stream) => stream,
+            None => return Ok(None),
+        };
+        match stream.next().await {
+            Some(batch) => Ok(Some(record_batch_to_json_rows(batch?)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub async fn close(self) -> Result<()> {
+        drop(self.stream);
+        clear_parquet_files(&self.ctx, self.tables, self.session_ids).await
+    }
+}
+
+/// Runs the query over the parquet files, the result is returned in record
+/// batches of `batch_size` rows.
+pub async fn query_parquet_files_stream(
+    schema: Arc<Schema>,
+    files_group: &[(Arc<Schema>, Vec<String>)],
+    query_sql: &str,
+    batch_size: usize,
+) -> Result<ParquetQueryStream> {
+    let runtime_env = create_runtime_env()?;
+    let session_config = SessionConfig::new()
+        .with_information_schema(false)
+        .with_batch_size(batch_size);
+    let ctx = SessionContext::with_config_rt(session_config, Arc::new(runtime_env));
+
+    let mut session_ids = Vec::with_capacity(files_group.len());
+    let ret =
+        query_parquet_files_stream_inner(&ctx, &schema, files_group, query_sql, &mut session_ids)
+            .await;
+    match ret {
+        Ok(stream) => Ok(ParquetQueryStream {
+            ctx,
+            tables: files_group.len(),
+            session_ids,
+            stream,
+        }),
+        Err(e) => {
+            clear_parquet_files(&ctx, files_group.len(), session_ids).await?;
+            Err(e)
+        }
+    }
+}
+
+async fn query_parquet_files_stream_inner(
+    ctx: &SessionContext,
+    schema: &Arc<Schema>,
+    files_group: &[(Arc<Schema>, Vec<String>)],
+    query_sql: &str,
+    session_ids: &mut Vec<String>,
+) -> Result<Option<SendableRecordBatchStream>> {
+    if !register_parquet_files(ctx, schema, files_group, session_ids).await? {
+        return Ok(None);
+    }
+    match ctx.sql(query_sql).await {
+        Ok(df) => Ok(Some(df.execute_stream().await?)),
+        Err(e) => {
+            log::error!("query sql execute failed, sql: {}, err: {:?}", query_sql, e);
+            Err(e)
+        }
+    }
+}
+
+/// registers the files as table `tbl`, returns false when there are no files
+async fn register_parquet_files(
+    ctx: &SessionContext,
+    schema: &Arc<Schema>,
+    files_group: &[(Arc<Schema>, Vec<String>)],
+    session_ids: &mut Vec<String>,
+) -> Result<bool> {
+    let mut df: Option<DataFrame> = None;
+    for (i, (file_schema, files)) in files_group.iter().enumerate() {
+        // Configure listing options
+        let file_format = ParquetFormat::default().with_enable_pruning(Some(false));
+        let listing_options = ListingOptions::new(Arc::new(file_format))
+            .with_file_extension(FileType::PARQUET.get_ext())
+            .with_target_partitions(CONFIG.limit.cpu_num);
+
+        let session_id = Uuid::new_v4().to_string();
+        file_list::set(&session_id, files).await.unwrap();
+        session_ids.push(session_id.clone());
+
+        let prefix = match ListingTableUrl::parse(format!("mem:///{}/", session_id)) {
+            Ok(url) => url,
+            Err(e) => {
+                return Err(datafusion::error::DataFusionError::Execution(format!(
+                    "ListingTableUrl error: {}",
+                    e
+                )));
+            }
+        };
+        let config = ListingTableConfig::new_with_multi_paths(vec![prefix])
+            .with_listing_options(listing_options)
+            .with_schema(file_schema.clone());
+        let table = ListingTable::try_new(config)?;
+        let table_name = format!("tbl_raw_{}", i);
+        ctx.register_table(table_name.as_str(), Arc::new(table))?;
+
+        // files of an older schema version are reconciled with the latest schema
+        let mut group_df = ctx.table(table_name.as_str()).await?;
+        if let Some(exprs) = reconcile_schema_exprs(file_schema, schema)? {
+            group_df = group_df.select(exprs)?;
+        }
+        df = Some(match df {
+            Some(df) => df.union(group_df)?,
+            None => group_df,
+        });
+    }
+    let df = match df {
+        Some(df) => df,
+        None => return Ok(false),
+    };
+    ctx.register_table("tbl", df.into_view())?;
+    Ok(true)
+}
+
+async fn clear_parquet_files(
+    ctx: &SessionContext,
+    tables: usize,
+    session_ids: Vec<String>,
+) -> Result<()> {
+    for i in 0..tables {
+        ctx.deregister_table(format!("tbl_raw_{}", i).as_str())?;
+    }
+    ctx.deregister_table("tbl")?;
+    for session_id in session_ids {
+        file_list::clear(&session_id).await.unwrap();
+    }
+    Ok(())
+}
+
+fn record_batch_to_json_rows(batch: RecordBatch) -> Result<Vec<serde_json::Value>> {
+    // a native time column is returned as Int64 nanoseconds
+    let batch = time_column_to_int64(batch, TimeUnit::Nanosecond)?;
+    let json_rows = arrowJson::writer::record_batches_to_json_rows(&[batch])?;
+    Ok(json_rows
+        .into_iter()
+        .map(serde_json::Value::Object)
+        .collect())
+}
+
 fn create_runtime_env() -> Result<RuntimeEnv> {
     let object_store_registry = ObjectStoreRegistry::new();
 
@@ -751,7 +1118,146 @@ fn create_runtime_env() -> Result<RuntimeEnv> {
     RuntimeEnv::new(rn_config)
 }
 
+// Hack for join: partial results already contain the joined columns, so the
+// joins are dropped and the lookup table qualifiers removed before merging
+fn strip_join_for_merge(sql: &str) -> String {
+    let mut statements = match Parser::parse_sql(&GenericDialect {}, sql) {
+        Ok(statements) => statements,
+        Err(_) => return sql.to_string(),
+    };
+    let joins = match statements.first_mut() {
+        Some(Statement::Query(query)) => match query.body.as_mut() {
+            SetExpr::Select(select) => select
+                .from
+                .iter_mut()
+                .flat_map(|table| std::mem::take(&mut table.joins))
+                .collect::<Vec<_>>(),
+            _ => return sql.to_string(),
+        },
+        _ => return sql.to_string(),
+    };
+    if joins.is_empty() {
+        return sql.to_string();
+    }
+    // the names the columns of the lookup tables may be qualified with
+    let mut tables = Vec::new();
+    for join in joins {
+        if let TableFactor::Table { name, alias, .. } = join.relation {
+            tables.extend(name.0.into_iter().map(|ident| ident.value));
+            tables.extend(alias.map(|alias| alias.name.value));
+        }
+    }
+    let _ = visit_expressions_mut(&mut statements, |expr| {
+        if let SqlExpr::CompoundIdentifier(idents) = expr {
+            if idents.len() == 2
+                && tables
+                    .iter()
+                    .any(|t| t.eq_ignore_ascii_case(&idents[0].value))
+            {
+                *expr = SqlExpr::Identifier(idents[1].clone());
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    statements[0].to_string()
+}
+
+/// The schema with the list field replaced by its element type
+fn unnest_schema(schema: &Schema, field: &str) -> Result<Schema> {
+    let fields = schema
+        .fields()
+        .iter()
+        .map(|f| match f.data_type() {
+            DataType::List(item) if f.name() == field => {
+                Ok(Field::new(f.name(), item.data_type().clone(), true))
+            }
+            _ if f.name() == field => Err(DataFusionError::Plan(format!(
+                "unnest: field {} is not a list",
+                field
+            ))),
+            _ => Ok(f.clone()),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Schema::new_with_metadata(fields, schema.metadata().clone()))
+}
+
+/// Expands the list field of the batch to one row per element, the rows with
+/// a null or empty list are dropped
+fn unnest_batch(batch: &RecordBatch, field: &str) -> Result<RecordBatch> {
+    let schema = batch.schema();
+    let index = schema.index_of(field)?;
+    let list = match batch.column(index).as_any().downcast_ref::<ListArray>() {
+        Some(list) => list,
+        None => {
+            return Err(DataFusionError::Plan(format!(
+                "unnest: field {} is not a list",
+                field
+            )))
+        }
+    };
+    let mut rows = UInt32Builder::with_capacity(list.values().len());
+    let mut values = UInt32Builder::with_capacity(list.values().len());
+    let offsets = list.value_offsets();
+    for row in 0..list.len() {
+        if list.is_null(row) {
+            continue;
+        }
+        for value in offsets[row]..offsets[row + 1] {
+            rows.append_value(row as u32);
+            values.append_value(value as u32);
+        }
+    }
+    let rows = rows.finish();
+    let values = values.finish();
+    let columns = batch
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| match i == index {
+            true => take(list.values().as_ref(), &values, None),
+            false => take(column.as_ref(), &rows, None),
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    Ok(RecordBatch::try_new(
+        Arc::new(unnest_schema(&schema, field)?),
+        columns,
+    )?)
+}
+
+/// Rewrites `unnest(field)` to the plain field and returns the unnested fields,
+/// the table is expanded by the fields before the query runs
+fn strip_unnest(sql: &str) -> (String, Vec<String>) {
+    let re_unnest = Regex::new(r#"(?i)\bunnest\(\s*"?([a-zA-Z0-9_]+)"?\s*\)"#).unwrap();
+    let fields = re_unnest
+        .captures_iter(sql)
+        .map(|cap| cap[1].to_string())
+        .collect::<Vec<_>>();
+    if fields.is_empty() {
+        return (sql.to_string(), fields);
+    }
+    (re_unnest.replace_all(sql, "\"$1\"").to_string(), fields)
+}
+
+fn register_lookup_tables(ctx: &SessionContext, org_id: &str, joins: &[String]) -> Result<()> {
+    if joins.is_empty() {
+        return Ok(());
+    }
+    for item in LOOKUP_TABLES.iter() {
+        let name = match item.key().strip_prefix(&format!("{}/", org_id)) {
+            Some(name) => name,
+            None => continue,
+        };
+        if !joins.iter().any(|join| join.eq_ignore_ascii_case(name)) {
+            continue;
+        }
+        let provider = MemTable::try_new(item.schema.clone(), vec![item.batches.clone()])?;
+        ctx.register_table(name, Arc::new(provider))?;
+    }
+    Ok(())
+}
+
 async fn register_udf(ctx: &mut SessionContext, _org_id: &str) {
+    ctx.register_udf(super::array_udf::ARRAY_CONTAINS_UDF.clone());
     ctx.register_udf(super::match_udf::MATCH_UDF.clone());
     ctx.register_udf(super::match_udf::MATCH_IGNORE_CASE_UDF.clone());
     ctx.register_udf(super::regexp_udf::REGEX_MATCH_UDF.clone());
@@ -768,11 +1274,41 @@ async fn register_udf(ctx: &mut SessionContext, _org_id: &str) {
 
 #[cfg(test)]
 mod test {
-    use arrow::array::Int32Array;
+    use arrow::array::{Array, Int32Array, Int64Array};
     use arrow_schema::Field;
     use datafusion::from_slice::FromSlice;
 
     use super::*;
+    #[actix_web::test]
+    async fn test_strip_join_for_merge() {
+        let sql = "SELECT tbl.level, owners.team FROM tbl LEFT JOIN owners ON tbl.service = owners.service ORDER BY _timestamp DESC LIMIT 10";
+        assert_eq!(
+            strip_join_for_merge(sql),
+            "SELECT tbl.level, team FROM tbl ORDER BY _timestamp DESC LIMIT 10"
+        );
+        let sql = "SELECT tbl.level, o.team FROM tbl JOIN owners AS o ON tbl.service = o.service WHERE o.team = 'core' LIMIT 10";
+        assert_eq!(
+            strip_join_for_merge(sql),
+            "SELECT tbl.level, team FROM tbl WHERE team = 'core' LIMIT 10"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_strip_unnest() {
+        let sql = "SELECT UNNEST(tags) AS tag, count(*) AS num FROM tbl GROUP BY tag";
+        assert_eq!(
+            strip_unnest(sql),
+            (
+                "SELECT \"tags\" AS tag, count(*) AS num FROM tbl GROUP BY tag".to_string(),
+                vec!["tags".to_string()]
+            )
+        );
+        assert_eq!(
+            strip_unnest("SELECT * FROM tbl"),
+            ("SELECT * FROM tbl".to_string(), vec![])
+        );
+    }
+
     #[actix_web::test]
     async fn test_register_udf() {
         let mut ctx = SessionContext::new();
@@ -780,6 +1316,42 @@ mod test {
         //assert!(res)
     }
 
+    #[actix_web::test]
+    async fn test_time_column_cast() {
+        let schema = Arc::new(Schema::new(vec![Field::new("t", DataType::Int64, false)]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int64Array::from_slice([
+                1667978841110123,
+                1667978841110456,
+            ]))],
+        )
+        .unwrap();
+        let ctx = SessionContext::new();
+        let provider = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        ctx.register_table("tbl", Arc::new(provider)).unwrap();
+        let expr = time_column_cast(
+            col("t"),
+            &DataType::Int64,
+            &DataType::Timestamp(TimeUnit::Millisecond, None),
+            false,
+        )
+        .unwrap();
+        let df = ctx
+            .table("tbl")
+            .await
+            .unwrap()
+            .select(vec![cast(expr, DataType::Int64)])
+            .unwrap();
+        let batches = df.collect().await.unwrap();
+        let values = batches[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(values.values(), &[1667978841110, 1667978841110]);
+    }
+
     #[actix_web::test]
     async fn test_merge_write_recordbatch() {
         // define a schema.
@@ -831,4 +1403,39 @@ mod test {
 
         assert!(!res.is_empty())
     }
+
+    #[actix_web::test]
+    async fn test_reconcile_schema_exprs() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("f", DataType::Int32, false),
+            Field::new("old", DataType::Int32, false),
+        ]));
+        let latest_schema = Schema::new(vec![
+            Field::new("f", DataType::Int64, true),
+            Field::new("new", DataType::Utf8, true),
+        ]);
+        assert!(reconcile_schema_exprs(&schema, &schema).unwrap().is_none());
+
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from_slice([1, 10])),
+                Arc::new(Int32Array::from_slice([2, 20])),
+            ],
+        )
+        .unwrap();
+        let ctx = SessionContext::new();
+        let provider = MemTable::try_new(schema.clone(), vec![vec![batch]]).unwrap();
+        ctx.register_table("tbl_raw", Arc::new(provider)).unwrap();
+        let exprs = reconcile_schema_exprs(&schema, &latest_schema)
+            .unwrap()
+            .unwrap();
+        let df = ctx.table("tbl_raw").await.unwrap().select(exprs).unwrap();
+        let batches = df.collect().await.unwrap();
+        let result_schema = batches[0].schema();
+        assert_eq!(result_schema.fields().len(), 2);
+        assert_eq!(result_schema.field(0).data_type(), &DataType::Int64);
+        assert_eq!(result_schema.field(1).name(), "new");
+        assert_eq!(batches[0].column(1).null_count(), 2);
+    }
 }
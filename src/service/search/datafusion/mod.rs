@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod array_udf;
 pub mod exec;
 mod match_udf;
 mod regexp_udf;
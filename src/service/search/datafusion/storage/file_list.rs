@@ -70,6 +70,7 @@ mod tests {
             records: 10000,
             original_size: 1024,
             compressed_size: 1,
+            column_stats: vec![],
         };
         let file_name = "files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet";
         let _ret = crate::infra::cache::file_list::set_file_to_cache(file_name, Some(meta), false)
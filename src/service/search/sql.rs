@@ -17,16 +17,25 @@ use chrono::Duration;
 use datafusion::arrow::datatypes::{DataType, Schema};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use sqlparser::ast::{BinaryOperator, Expr as SqlExpr, SetExpr, Statement};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
 use crate::common::str;
+use crate::common::time::{time_column_literal, time_range_condition};
 use crate::handler::grpc::cluster_rpc;
 use crate::infra::config::CONFIG;
+use crate::meta::common::FileMeta;
+use crate::meta::schema::VirtualField;
 use crate::meta::sql::Sql as MetaSql;
 use crate::meta::StreamType;
-use crate::service::stream::get_stream_setting_fts_fields;
-use crate::service::{db, file_list, logs};
+use crate::service::stream::{
+    get_stream_setting_fts_fields, get_stream_setting_partition_buckets,
+    get_stream_setting_virtual_fields,
+};
+use crate::service::{column_stats, db, file_list, logs, schema, stream_aliases};
 
 const SQL_KEYWORDS: [&str; 32] = [
     "SELECT", "FROM", "WHERE", "TABLE", "LIMIT", "OFFSET", "AND", "OR", "NOT", "IN", "ANY", "IS",
@@ -42,6 +51,8 @@ pub struct Sql {
     pub origin_sql: String,
     pub org_id: String,
     pub stream_name: String,
+    /// the streams to search, more than one when searching an alias
+    pub sources: Vec<String>,
     pub meta: MetaSql,
     pub fulltext: Vec<(String, String)>,
     pub aggs: AHashMap<String, (String, MetaSql)>,
@@ -204,23 +215,40 @@ impl Sql {
         }
         let mut meta = meta.unwrap();
 
-        // fetch schema
-        let schema = match db::schema::get(&org_id, &meta.source, Some(stream_type)).await {
+        // fetch schema, an alias is resolved to its streams
+        let mut stream_name = meta.source.clone();
+        let mut sources = vec![meta.source.clone()];
+        let mut schema = match db::schema::get(&org_id, &meta.source, Some(stream_type)).await {
             Ok(schema) => schema,
             Err(_) => Schema::empty(),
         };
+        if schema == Schema::empty() {
+            if let Some(streams) = stream_aliases::resolve_alias(&org_id, stream_type, &meta.source)
+            {
+                schema = get_sources_schema(&org_id, stream_type, &streams).await?;
+                if streams.len() == 1 {
+                    stream_name = streams[0].clone();
+                }
+                sources = streams;
+            }
+        }
         let schema_fields = schema.fields().to_vec();
 
         // Hack for DataFusion
         // DataFusion disallow use `k8s-logs-2022.09.11` as table name
-        let stream_name = meta.source.clone();
-        let re = Regex::new(&format!(r##"(?i) from[ '"]+{}[ '"]?"##, stream_name)).unwrap();
+        let re = Regex::new(&format!(r##"(?i) from[ '"]+{}[ '"]?"##, meta.source)).unwrap();
         let caps = match re.captures(origin_sql.as_str()) {
             Some(caps) => caps,
             None => return Err(anyhow::anyhow!("SQL should likes [select * from table]")),
         };
         origin_sql = origin_sql.replace(caps.get(0).unwrap().as_str(), " FROM tbl ");
 
+        // expand virtual fields to their expressions
+        // a field ingested later under the same name takes precedence
+        let mut virtual_fields = get_stream_setting_virtual_fields(&schema);
+        virtual_fields.retain(|field| schema.field_with_name(&field.name).is_err());
+        origin_sql = expand_virtual_fields(&origin_sql, &virtual_fields);
+
         // Hack time range
         if req_time_range.0 > 0 || req_time_range.1 > 0 {
             meta.time_range = Some(req_time_range);
@@ -230,22 +258,31 @@ impl Sql {
             meta.time_range = Some((partition.time_min, partition.time_max));
         }
         if let Some(time_range) = meta.time_range {
+            // the time range is in microseconds whatever the column type
+            let time_col = &CONFIG.common.time_stamp_col;
             let time_range_sql = if req_time_range.0 > 0 && req_time_range.1 > 0 {
                 format!(
-                    "({} >= {} AND {} < {})",
-                    CONFIG.common.time_stamp_col,
-                    time_range.0,
-                    CONFIG.common.time_stamp_col,
-                    time_range.1
+                    "({})",
+                    time_range_condition(&schema, time_range.0, time_range.1)
                 )
             } else if req_time_range.0 > 0 {
-                format!("{} >= {}", CONFIG.common.time_stamp_col, time_range.0)
+                format!(
+                    "{} >= {}",
+                    time_col,
+                    time_column_literal(&schema, time_range.0)
+                )
             } else if req_time_range.1 > 0 {
-                format!("{} < {}", CONFIG.common.time_stamp_col, time_range.1)
+                format!(
+                    "{} < {}",
+                    time_col,
+                    time_column_literal(&schema, time_range.1)
+                )
             } else {
                 "".to_string()
             };
-            if !time_range_sql.is_empty() {
+            if !time_range_sql.is_empty() && !meta.joins.is_empty() {
+                origin_sql = add_where_for_join(&origin_sql, &time_range_sql);
+            } else if !time_range_sql.is_empty() {
                 let re = Regex::new(r"(?i) WHERE (.*)").unwrap();
                 match re.captures(origin_sql.as_str()) {
                     Some(caps) => {
@@ -315,31 +352,15 @@ impl Sql {
             fulltext.push((cap[0].to_string(), cap[1].to_lowercase()));
         }
         // fetch fts fields
-        let fts_fiels = get_stream_setting_fts_fields(&schema).unwrap();
-        let match_all_fields = if !fts_fiels.is_empty() {
-            fts_fiels.iter().map(|v| v.to_lowercase()).collect()
-        } else {
-            SQL_FULL_TEXT_SEARCH_FIELDS
-                .iter()
-                .map(|v| v.to_string())
-                .collect::<String>()
-        };
+        let fts_fields = fulltext_search_fields(&schema);
         for item in fulltext.iter() {
             let mut fulltext_search = Vec::new();
-            for field in &schema_fields {
-                if !CONFIG.common.feature_fulltext_on_all_fields
-                    && !match_all_fields.contains(&field.name().to_lowercase())
-                {
-                    continue;
-                }
-                if !field.data_type().eq(&DataType::Utf8) || field.name().starts_with('@') {
-                    continue;
-                }
+            for field in &fts_fields {
                 let mut func = "LIKE";
                 if item.0.to_lowercase().contains("_ignore_case") {
                     func = "ILIKE";
                 }
-                fulltext_search.push(format!("\"{}\" {} '%{}%'", field.name(), func, item.1));
+                fulltext_search.push(format!("\"{}\" {} '%{}%'", field, func, item.1));
             }
             if fulltext_search.is_empty() {
                 return Err(anyhow::anyhow!("No full text search field found"));
@@ -392,9 +413,9 @@ impl Sql {
             origin_sql = origin_sql.replace(
                 cap.get(0).unwrap().as_str(),
                 format!(
-                    "date_bin(interval '{}', to_timestamp_micros(\"{}\"), to_timestamp('2001-01-01T00:00:00'))",
+                    "date_bin(interval '{}', {}, to_timestamp('2001-01-01T00:00:00'))",
                     interval,
-                   field,
+                    histogram_field_expr(field, &schema),
                 )
                 .as_str(),
             );
@@ -427,7 +448,7 @@ impl Sql {
         let re_where = Regex::new(r"(?i) where ").unwrap();
         let re_from = Regex::new(r"(?i) from[ ]+query").unwrap();
         for (key, sql) in &req_aggs {
-            let mut sql = sql.to_string();
+            let mut sql = expand_virtual_fields(sql, &virtual_fields);
             if let Some(caps) = re_from.captures(&sql) {
                 sql = sql.replace(&caps[0].to_string(), " FROM tbl ");
             }
@@ -464,9 +485,9 @@ impl Sql {
                 sql = sql.replace(
                     cap.get(0).unwrap().as_str(),
                     format!(
-                        "date_bin(interval '{}', to_timestamp_micros(\"{}\"), to_timestamp('2001-01-01T00:00:00'))",
+                        "date_bin(interval '{}', {}, to_timestamp('2001-01-01T00:00:00'))",
                         interval,
-                       field,
+                        histogram_field_expr(field, &schema),
                     )
                     .as_str(),
                 );
@@ -482,6 +503,7 @@ impl Sql {
             origin_sql,
             org_id,
             stream_name,
+            sources,
             meta,
             fulltext,
             aggs,
@@ -512,6 +534,20 @@ impl Sql {
         Ok(sql)
     }
 
+    /// Splits the search of an alias into one search per stream, the schema
+    /// merged from all the streams is kept
+    pub fn split_sources(&self) -> Vec<Sql> {
+        self.sources
+            .iter()
+            .map(|source| {
+                let mut sql = self.clone();
+                sql.stream_name = source.clone();
+                sql.sources = vec![source.clone()];
+                sql
+            })
+            .collect()
+    }
+
     /// match a source is a valid file or not
     pub async fn match_source(
         &self,
@@ -520,13 +556,11 @@ impl Sql {
         stream_type: StreamType,
     ) -> bool {
         // match org_id & table
-        if !source.starts_with(
-            format!(
-                "files/{}/{}/{}/",
-                &self.org_id, stream_type, &self.stream_name
+        if !self.sources.iter().any(|stream_name| {
+            source.starts_with(
+                format!("files/{}/{}/{}/", &self.org_id, stream_type, stream_name).as_str(),
             )
-            .as_str(),
-        ) {
+        }) {
             return false;
         }
 
@@ -537,6 +571,26 @@ impl Sql {
 
         // check time range
         let file_meta = file_list::get_file_meta(source).await.unwrap_or_default();
+        if !self.filter_source_by_time_range(source, &file_meta, match_min_ts_only) {
+            return false;
+        }
+
+        // check column stats
+        if let Some(selection) = self.meta.selection.as_ref() {
+            if !column_stats::may_match(selection, &self.schema, &file_meta) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// filter source by time range
+    fn filter_source_by_time_range(
+        &self,
+        source: &str,
+        file_meta: &FileMeta,
+        match_min_ts_only: bool,
+    ) -> bool {
         if file_meta.min_ts == 0 || file_meta.max_ts == 0 {
             return true;
         }
@@ -578,10 +632,54 @@ impl Sql {
             // println!("source: {}", source);
             // println!("field: {}, value: {}", field, value);
         }
+        // check hashed partition key, only equality can be mapped to a bucket
+        if self.meta.equal_items.is_empty() {
+            return true;
+        }
+        let partition_buckets = get_stream_setting_partition_buckets(&self.schema);
+        for (key, val) in &self.meta.equal_items {
+            let buckets = match partition_buckets.get(key) {
+                Some(buckets) if *buckets > 0 => *buckets,
+                _ => continue,
+            };
+            let field = logs::get_partition_key_str(format!("{}.bucket=", key).as_str());
+            let bucket = logs::get_partition_bucket(val, buckets);
+            let value = logs::get_partition_key_str(format!("{}.bucket={}", key, bucket).as_str());
+            if str::find(source, format!("/{}", field).as_str())
+                && !str::find(source, format!("/{}/", value).as_str())
+            {
+                return false;
+            }
+        }
         true
     }
 }
 
+/// merges the latest schemas of the streams, the stream settings are dropped
+async fn get_sources_schema(
+    org_id: &str,
+    stream_type: StreamType,
+    streams: &[String],
+) -> Result<Schema, anyhow::Error> {
+    if streams.len() == 1 {
+        return db::schema::get(org_id, &streams[0], Some(stream_type)).await;
+    }
+    let mut schemas = Vec::with_capacity(streams.len());
+    for stream_name in streams {
+        let schema = db::schema::get(org_id, stream_name, Some(stream_type)).await?;
+        schemas.push(schema.with_metadata(HashMap::new()));
+    }
+    schema::try_merge(schemas).map_err(|e| anyhow::anyhow!("alias schema error: {}", e))
+}
+
+/// The histogram field as a timestamp, Int64 fields hold microseconds
+fn histogram_field_expr(field: &str, schema: &Schema) -> String {
+    match schema.field_with_name(field).map(|f| f.data_type()) {
+        Ok(DataType::Timestamp(_, _)) => format!("CAST(\"{}\" AS TIMESTAMP)", field),
+        _ => format!("to_timestamp_micros(\"{}\")", field),
+    }
+}
+
 // Hack for double quote
 fn add_quote_for_sql(text: &str) -> String {
     let mut new_text = Vec::new();
@@ -628,6 +726,114 @@ fn add_quote_for_sql(text: &str) -> String {
     new_text.join("")
 }
 
+// Hack for join: the condition is and-ed to the where clause of the query,
+// which can't be found by text once join constraints are involved
+fn add_where_for_join(sql: &str, where_str: &str) -> String {
+    let dialect = GenericDialect {};
+    let mut statements = match Parser::parse_sql(&dialect, sql) {
+        Ok(statements) => statements,
+        Err(_) => return sql.to_string(),
+    };
+    let condition = match Parser::new(&dialect)
+        .try_with_sql(where_str)
+        .and_then(|mut parser| parser.parse_expr())
+    {
+        Ok(condition) => condition,
+        Err(_) => return sql.to_string(),
+    };
+    let select = match statements.first_mut() {
+        Some(Statement::Query(query)) => match query.body.as_mut() {
+            SetExpr::Select(select) => select,
+            _ => return sql.to_string(),
+        },
+        _ => return sql.to_string(),
+    };
+    select.selection = Some(match select.selection.take() {
+        Some(selection) => SqlExpr::BinaryOp {
+            left: Box::new(condition),
+            op: BinaryOperator::And,
+            right: Box::new(SqlExpr::Nested(Box::new(selection))),
+        },
+        None => condition,
+    });
+    statements[0].to_string()
+}
+
+/// The fields searched by match_all
+pub fn fulltext_search_fields(schema: &Schema) -> Vec<String> {
+    let fts_fiels = get_stream_setting_fts_fields(schema).unwrap();
+    let match_all_fields = if !fts_fiels.is_empty() {
+        fts_fiels.iter().map(|v| v.to_lowercase()).collect()
+    } else {
+        SQL_FULL_TEXT_SEARCH_FIELDS
+            .iter()
+            .map(|v| v.to_string())
+            .collect::<String>()
+    };
+    schema
+        .fields()
+        .iter()
+        .filter(|field| {
+            CONFIG.common.feature_fulltext_on_all_fields
+                || match_all_fields.contains(&field.name().to_lowercase())
+        })
+        .filter(|field| field.data_type().eq(&DataType::Utf8) && !field.name().starts_with('@'))
+        .map(|field| field.name().to_string())
+        .collect()
+}
+
+/// Replaces the virtual fields used in the query with their expressions, a
+/// virtual field selected on its own keeps its name as alias
+fn expand_virtual_fields(sql: &str, fields: &[VirtualField]) -> String {
+    if fields.is_empty() {
+        return sql.to_string();
+    }
+    let re_token = Regex::new(r#"'(?:[^']|'')*'|"[^"]*"|[a-zA-Z_][a-zA-Z0-9_]*"#).unwrap();
+    let re_alias = Regex::new(r"(?i)\bas\s*$").unwrap();
+    let re_item_start = Regex::new(r"(?i)(\bselect|\bdistinct|,)\s*$").unwrap();
+    let re_item_end = Regex::new(r"(?i)^\s*(,|\bfrom\b)").unwrap();
+    let re_from = Regex::new(r"(?i)\bfrom\b").unwrap();
+    let mut new_sql = String::with_capacity(sql.len());
+    let mut append_pos = 0;
+    for token in re_token.find_iter(sql) {
+        if token.as_str().starts_with('\'') {
+            continue;
+        }
+        let name = token.as_str().trim_matches('"');
+        let field = match fields.iter().find(|field| field.name == name) {
+            Some(field) => field,
+            None => continue,
+        };
+        let before = &sql[..token.start()];
+        let after = &sql[token.end()..];
+        // aliases, qualified names and functions are left as they are
+        if re_alias.is_match(before) || before.ends_with('.') || after.trim_start().starts_with('(')
+        {
+            continue;
+        }
+        let before_lower = before.to_lowercase();
+        let select_item = match before_lower.rfind("select") {
+            Some(pos) => {
+                let clause = &before[pos..];
+                !re_from.is_match(clause)
+                    && clause.matches('(').count() == clause.matches(')').count()
+                    && re_item_start.is_match(before)
+                    && re_item_end.is_match(after)
+            }
+            None => false,
+        };
+        new_sql.push_str(&sql[append_pos..token.start()]);
+        if select_item {
+            new_sql.push_str(&format!("({}) AS \"{}\"", field.expr, field.name));
+        } else {
+            new_sql.push_str(&format!("({})", field.expr));
+        }
+        append_pos = token.end();
+    }
+    new_sql.push_str(&sql[append_pos..]);
+    new_sql
+}
+
 fn check_field_in_use(sql: &Sql, field: &str) -> bool {
     let re = Regex::new(&format!(r"\b{}\b", field)).unwrap();
     if str::find(sql.origin_sql.as_str(), field) && re.is_match(sql.origin_sql.as_str()) {
@@ -679,6 +885,61 @@ mod tests {
         assert_eq!(field_used, true);
     }
 
+    #[actix_web::test]
+    async fn test_add_where_for_join() {
+        let sql =
+            "SELECT * FROM tbl JOIN owners ON tbl.service = owners.service ORDER BY _timestamp";
+        assert_eq!(
+            add_where_for_join(sql, "_timestamp >= 1"),
+            "SELECT * FROM tbl JOIN owners ON tbl.service = owners.service WHERE _timestamp >= 1 ORDER BY _timestamp"
+        );
+        let sql = "SELECT * FROM tbl JOIN owners ON tbl.service = owners.service WHERE level = 'error' OR owners.team = 'core'";
+        assert_eq!(
+            add_where_for_join(sql, "_timestamp >= 1"),
+            "SELECT * FROM tbl JOIN owners ON tbl.service = owners.service WHERE _timestamp >= 1 AND (level = 'error' OR owners.team = 'core')"
+        );
+    }
+
+    #[test]
+    fn test_histogram_field_expr() {
+        use datafusion::arrow::datatypes::{Field, TimeUnit};
+        let schema = Schema::new(vec![
+            Field::new(
+                "_timestamp",
+                DataType::Timestamp(TimeUnit::Nanosecond, None),
+                false,
+            ),
+            Field::new("start_time", DataType::Int64, false),
+        ]);
+        assert_eq!(
+            histogram_field_expr("_timestamp", &schema),
+            "CAST(\"_timestamp\" AS TIMESTAMP)"
+        );
+        assert_eq!(
+            histogram_field_expr("start_time", &schema),
+            "to_timestamp_micros(\"start_time\")"
+        );
+    }
+
+    #[test]
+    fn test_expand_virtual_fields() {
+        let fields = vec![VirtualField {
+            name: "route".to_string(),
+            expr: "split_part(path, '/', 2)".to_string(),
+        }];
+        assert_eq!(
+            expand_virtual_fields(
+                "SELECT route, count(*) AS num FROM tbl WHERE route != 'route' GROUP BY route",
+                &fields
+            ),
+            "SELECT (split_part(path, '/', 2)) AS \"route\", count(*) AS num FROM tbl WHERE (split_part(path, '/', 2)) != 'route' GROUP BY (split_part(path, '/', 2))"
+        );
+        assert_eq!(
+            expand_virtual_fields("SELECT upper(route) AS route FROM tbl", &fields),
+            "SELECT upper((split_part(path, '/', 2))) AS route FROM tbl"
+        );
+    }
+
     #[actix_web::test]
     async fn test_add_quote_for_sql() {
         let sqls = [
@@ -24,7 +24,7 @@ use super::sql::Sql;
 use crate::infra::cache::file_data;
 use crate::infra::config::CONFIG;
 use crate::meta;
-use crate::service::{db, file_list};
+use crate::service::{bloom_filter, db, file_list, fulltext_index};
 
 /// search in remote object storage
 #[tracing::instrument(
@@ -36,6 +36,42 @@ pub async fn search(
     sql: Arc<Sql>,
     file_list: &[String],
     stream_type: meta::StreamType,
+) -> super::SearchResult {
+    if sql.sources.len() <= 1 {
+        return search_stream(session_id, sql, file_list, stream_type).await;
+    }
+
+    // an alias is searched stream by stream
+    let mut results: HashMap<String, Vec<RecordBatch>> = HashMap::new();
+    let mut file_count = 0;
+    let mut scan_size = 0;
+    for sql in sql.split_sources() {
+        let prefix = format!("files/{}/{}/{}/", sql.org_id, stream_type, sql.stream_name);
+        let files = file_list
+            .iter()
+            .filter(|file| file.starts_with(&prefix))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !file_list.is_empty() && files.is_empty() {
+            continue; // the files of this stream are searched by other nodes
+        }
+        let session_id = format!("{}-{}", session_id, sql.stream_name);
+        let (batches, count, size) =
+            search_stream(&session_id, Arc::new(sql), &files, stream_type).await?;
+        for (key, batch) in batches {
+            results.entry(key).or_default().extend(batch);
+        }
+        file_count += count;
+        scan_size += size;
+    }
+    Ok((results, file_count, scan_size))
+}
+
+async fn search_stream(
+    session_id: &str,
+    sql: Arc<Sql>,
+    file_list: &[String],
+    stream_type: meta::StreamType,
 ) -> super::SearchResult {
     let span1 = info_span!("service:search:storage:get_file_list");
     let guard1 = span1.enter();
@@ -45,6 +81,9 @@ pub async fn search(
         true => get_file_list(&sql, stream_type).await?,
         false => file_list.to_vec(),
     };
+    // skip the files the full text index or the bloom filters rule out
+    let files = fulltext_index::filter_files(&sql, files).await;
+    let files = bloom_filter::filter_files(&sql, files).await;
     let file_count = files.len();
     drop(guard1);
 
@@ -94,7 +133,22 @@ pub async fn search(
     // fetch all schema versions, group files by version
     let schema_versions =
         db::schema::get_versions(&sql.org_id, &sql.stream_name, Some(stream_type)).await?;
-    let schema_latest = schema_versions.last().unwrap();
+    // the schema of an alias merges all of its streams
+    let schema_latest = if sql.schema.fields() != schema_versions.last().unwrap().fields() {
+        Arc::new(
+            sql.schema
+                .clone()
+                .with_metadata(std::collections::HashMap::new()),
+        )
+    } else {
+        Arc::new(
+            schema_versions
+                .last()
+                .unwrap()
+                .clone()
+                .with_metadata(std::collections::HashMap::new()),
+        )
+    };
     let schema_latest_id = schema_versions.len() - 1;
     let mut files_group: HashMap<usize, Vec<String>> =
         HashMap::with_capacity(schema_versions.len());
@@ -150,25 +204,20 @@ pub async fn search(
             id: format!("{}-{}", session_id, ver),
             data_type: SessionType::Remote,
         };
-        // cacluate the diff between latest schema and group schema
-        let mut diff_fields = HashMap::new();
-        if CONFIG.common.widening_schema_evoluation && ver != schema_latest_id {
-            let group_fields = schema.fields();
-            for field in group_fields {
-                if let Ok(v) = schema_latest.field_with_name(field.name()) {
-                    if v.data_type() != field.data_type() {
-                        diff_fields.insert(v.name().clone(), v.data_type().clone());
-                    }
-                }
-            }
-        }
+        // files of older versions are reconciled with the latest schema
+        let latest_schema = if ver != schema_latest_id || schema.fields() != schema_latest.fields()
+        {
+            Some(schema_latest.clone())
+        } else {
+            None
+        };
         let task = tokio::task::spawn(
             async move {
                 super::datafusion::exec::sql(
                     &session,
                     stream_type,
                     Some(schema),
-                    diff_fields,
+                    latest_schema,
                     &sql,
                     &files,
                     FileType::PARQUET,
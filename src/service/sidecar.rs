@@ -0,0 +1,94 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use bytes::Bytes;
+use futures::StreamExt;
+
+use crate::infra::cache::file_data;
+use crate::infra::config::CONFIG;
+use crate::infra::storage;
+use crate::service::{bloom_filter, fulltext_index};
+
+/// Gets an object stored next to a parquet file, eg: its full text index,
+/// None when the file has none. The objects are cached with the files, an
+/// empty entry records that the file has none.
+pub async fn get(key: &str) -> Option<Bytes> {
+    let data = match file_data::get(key) {
+        Ok(data) => data,
+        Err(_) => {
+            let data = match storage::DEFAULT.get(key).await {
+                Ok(data) => zstd::decode_all(data.as_ref()).unwrap_or_default(),
+                Err(_) => vec![],
+            };
+            let data = Bytes::from(data);
+            let _ = file_data::set(key, data.clone());
+            data
+        }
+    };
+    (!data.is_empty()).then_some(data)
+}
+
+pub async fn put(key: &str, data: &[u8]) {
+    let data = match zstd::encode_all(data, 3) {
+        Ok(data) => data,
+        Err(e) => {
+            log::error!("[SIDECAR] encode {} failed: {}", key, e);
+            return;
+        }
+    };
+    if let Err(e) = storage::DEFAULT.put(key, data.into()).await {
+        log::error!("[SIDECAR] upload {} failed: {}", key, e);
+    }
+}
+
+/// Deletes the objects stored next to a deleted file, the settings of the
+/// stream may have changed since they were created
+pub async fn delete(file: &str) {
+    for key in [fulltext_index::index_key, bloom_filter::bloom_filter_key] {
+        // most files have none
+        let _ = storage::DEFAULT.del(&key(file)).await;
+    }
+}
+
+/// Copies the objects stored next to a file to a copy of the file, the
+/// objects of the whole file still hold for a part of it
+pub async fn copy(file: &str, new_file: &str) {
+    for key in [fulltext_index::index_key, bloom_filter::bloom_filter_key] {
+        // most files have none
+        let _ = storage::DEFAULT.copy(&key(file), &key(new_file)).await;
+    }
+}
+
+/// Keeps the files the object of which, at `key(file)`, doesn't rule them
+/// out, the objects are fetched concurrently and files without one are kept
+pub async fn filter_files<T>(
+    files: Vec<String>,
+    key: fn(&str) -> String,
+    decode: fn(Bytes) -> Option<T>,
+    keep: impl Fn(&T) -> bool,
+) -> Vec<String> {
+    let keep = &keep;
+    futures::stream::iter(files)
+        .map(|file| async move {
+            let keep = match get(&key(&file)).await.and_then(decode) {
+                Some(object) => keep(&object),
+                None => true,
+            };
+            (file, keep)
+        })
+        .buffered(CONFIG.limit.query_thread_num)
+        .filter_map(|(file, keep)| async move { keep.then_some(file) })
+        .collect::<Vec<_>>()
+        .await
+}
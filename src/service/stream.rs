@@ -14,17 +14,41 @@
 
 use actix_web::http;
 use actix_web::{http::StatusCode, HttpResponse};
-use datafusion::arrow::datatypes::Schema;
+use chrono::Utc;
+use datafusion::arrow::compute::can_cast_types;
+use datafusion::arrow::datatypes::{DataType, Schema};
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use sqlparser::ast::{visit_expressions, Expr as SqlExpr};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use sqlparser::tokenizer::Token;
+use std::collections::HashMap;
 use std::io::Error;
+use std::ops::ControlFlow;
 use tracing::info_span;
 
 use crate::common::json;
 use crate::common::utils::is_local_disk_storage;
+use crate::infra::config::CONFIG;
+use crate::infra::{cache, storage};
+use crate::meta::enrichment::EnrichmentProcessor;
 use crate::meta::http::HttpResponse as MetaHttpResponse;
-use crate::meta::stream::{ListStream, Stream, StreamProperty, StreamSettings, StreamStats};
+use crate::meta::lookup::LookupEnrichment;
+use crate::meta::prom::{DerivedMetric, DerivedMetricType};
+use crate::meta::quality::{QualityAction, QualityRule, QualityRuleType};
+use crate::meta::sampling::{SamplingAction, SamplingRule};
+use crate::meta::schema::{
+    DefinedField, NestedField, SchemaDiff, SchemaFieldChange, SchemaMode, SchemaVersion,
+    SchemaVersionList, VirtualField,
+};
+use crate::meta::stream::{
+    ListStream, PartitionTimeLevel, Stream, StreamDeletion, StreamDeletionStatus, StreamProperty,
+    StreamSettings, StreamStats, TimestampUnit, MAX_DATA_RETENTION_DAYS,
+};
 use crate::meta::StreamType;
-use crate::service::db;
+use crate::service::schema::{set_field_type, set_time_column_type};
+use crate::service::{column_stats, db, sidecar};
 
 const SIZE_IN_MB: f64 = 1024.0 * 1024.0;
 const LOCAL: &str = "disk";
@@ -121,7 +145,24 @@ fn stream_res(
     }
     meta.remove("created_at");
     let mut partition_keys = Vec::new();
+    let mut partition_buckets = HashMap::new();
+    let mut partition_time_level = PartitionTimeLevel::default();
     let mut full_text_search_keys = vec![];
+    let mut data_quality_rules = vec![];
+    let mut sampling_rules = vec![];
+    let mut derived_metrics = vec![];
+    let mut enrichments = vec![];
+    let mut processors = vec![];
+    let mut data_retention = 0;
+    let mut defined_schema = vec![];
+    let mut schema_mode = SchemaMode::default();
+    let mut timestamp_unit = None;
+    let mut nested_fields = vec![];
+    let mut virtual_fields: Vec<VirtualField> = vec![];
+    let mut notify_schema_changes = false;
+    let mut fulltext_index = false;
+    let mut bloom_filter_fields = vec![];
+    let mut stats_fields = vec![];
     let stream_settings = meta.get("settings");
 
     if let Some(value) = stream_settings {
@@ -135,6 +176,12 @@ fn stream_res(
                 partition_keys.push(value.as_str().unwrap().to_string());
             }
         }
+        if let Some(value) = settings.get("partition_buckets") {
+            partition_buckets = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("partition_time_level") {
+            partition_time_level = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
         let fts = settings.get("full_text_search_keys");
         if let Some(value) = fts {
             let v: Vec<_> = value.as_array().unwrap().iter().collect();
@@ -142,6 +189,59 @@ fn stream_res(
                 full_text_search_keys.push(item.as_str().unwrap().to_string())
             }
         }
+        if let Some(value) = settings.get("data_quality_rules") {
+            data_quality_rules = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("sampling_rules") {
+            sampling_rules = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("derived_metrics") {
+            derived_metrics = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("enrichments") {
+            enrichments = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("processors") {
+            processors = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("data_retention") {
+            data_retention = value.as_i64().unwrap_or_default();
+        }
+        if let Some(value) = settings.get("defined_schema") {
+            defined_schema = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("schema_mode") {
+            schema_mode = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("timestamp_unit") {
+            timestamp_unit = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("nested_fields") {
+            nested_fields = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("virtual_fields") {
+            virtual_fields = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("notify_schema_changes") {
+            notify_schema_changes = value.as_bool().unwrap_or_default();
+        }
+        if let Some(value) = settings.get("fulltext_index") {
+            fulltext_index = value.as_bool().unwrap_or_default();
+        }
+        if let Some(value) = settings.get("bloom_filter_fields") {
+            bloom_filter_fields = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+        if let Some(value) = settings.get("stats_fields") {
+            stats_fields = serde_json::from_value(value.clone()).unwrap_or_default();
+        }
+    }
+    // virtual fields are listed with the stored fields so they can be picked
+    // in queries like any other field
+    for field in virtual_fields.iter() {
+        mappings.push(StreamProperty {
+            name: field.name.to_string(),
+            prop_type: "Virtual".to_string(),
+        });
     }
 
     let storage_type = if is_local_disk_storage() { LOCAL } else { S3 };
@@ -158,7 +258,24 @@ fn stream_res(
         stats,
         settings: StreamSettings {
             partition_keys,
+            partition_buckets,
+            partition_time_level,
             full_text_search_keys,
+            data_quality_rules,
+            sampling_rules,
+            derived_metrics,
+            enrichments,
+            processors,
+            data_retention,
+            defined_schema,
+            schema_mode,
+            timestamp_unit,
+            nested_fields,
+            virtual_fields,
+            notify_schema_changes,
+            fulltext_index,
+            bloom_filter_fields,
+            stats_fields,
         },
     }
 }
@@ -171,11 +288,37 @@ pub async fn save_stream_settings(
 ) -> Result<HttpResponse, Error> {
     let loc_span = info_span!("service:streams:set_partition_keys");
     let _guard = loc_span.enter();
-    let schema = db::schema::get(org_id, stream_name, Some(stream_type))
+    let mut schema = db::schema::get(org_id, stream_name, Some(stream_type))
         .await
         .unwrap();
+    if let Err(e) = validate_stream_settings(stream_name, &schema, &setting) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(e.to_string()),
+        )));
+    }
 
     let mut meta = schema.metadata.clone();
+    // a new schema version starts with the new type of the time column
+    let mut min_ts = None;
+    if let Some(unit) = setting.timestamp_unit {
+        if let Ok(field) = schema.field_with_name(&CONFIG.common.time_stamp_col) {
+            if field.data_type() != &unit.data_type() {
+                schema = set_time_column_type(schema, &unit.data_type());
+                min_ts = Some(Utc::now().timestamp_micros());
+            }
+        }
+    }
+    // and with the declared types of the defined fields
+    for field in setting.defined_schema.iter() {
+        let data_type = field.field_type.data_type();
+        if let Ok(existing) = schema.field_with_name(&field.name) {
+            if existing.data_type() != &data_type {
+                schema = set_field_type(schema, &field.name, &data_type);
+                min_ts = Some(Utc::now().timestamp_micros());
+            }
+        }
+    }
 
     meta.insert("settings".to_string(), json::to_string(&setting).unwrap());
     log::info!("Saving setting for schema {:?}", stream_name);
@@ -184,7 +327,7 @@ pub async fn save_stream_settings(
         stream_name,
         stream_type,
         &schema.clone().with_metadata(meta),
-        None,
+        min_ts,
     )
     .await
     .unwrap();
@@ -195,6 +338,306 @@ pub async fn save_stream_settings(
     )))
 }
 
+/// Deletes the stream metadata and its file list, the data files are removed
+/// from storage by a background job
+pub async fn delete_stream(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:streams:delete_stream");
+    let _guard = loc_span.enter();
+    let schema = db::schema::get(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    if schema == Schema::empty() {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            Some("stream not found".to_owned()),
+        )));
+    }
+    // the files are marked deleted in the file list before the stream stats are
+    // removed, so the stats are not decremented once they are gone
+    let files = cache::file_list::get_file_list(
+        org_id,
+        stream_name,
+        stream_type,
+        0,
+        0,
+        PartitionTimeLevel::default(),
+    )
+    .await
+    .unwrap_or_default();
+    let now = Utc::now().timestamp_micros();
+    let mut deletion = StreamDeletion {
+        stream_name: stream_name.to_string(),
+        stream_type,
+        status: StreamDeletionStatus::Running,
+        files: files.len(),
+        deleted_files: 0,
+        failed_files: 0,
+        created_at: now,
+        updated_at: now,
+    };
+    if let Err(e) = db::deletion::set(org_id, &deletion).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(format!("save stream deletion status error: {}", e)),
+            )),
+        );
+    }
+    let deleted = match db::file_list::delete_files(&files).await {
+        Ok(_) => {
+            cache::file_list::remove_stream(org_id, stream_type, stream_name);
+            delete_stream_metadata(org_id, stream_name, stream_type).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = deleted {
+        deletion.status = StreamDeletionStatus::Failed;
+        deletion.updated_at = Utc::now().timestamp_micros();
+        if let Err(e) = db::deletion::set(org_id, &deletion).await {
+            log::error!("[DELETE] save stream deletion status error: {}", e);
+        }
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        );
+    }
+
+    let org_id = org_id.to_string();
+    tokio::task::spawn(async move { delete_stream_files(org_id, deletion, files).await });
+
+    Ok(HttpResponse::Accepted().json(MetaHttpResponse::message(
+        http::StatusCode::ACCEPTED.into(),
+        "stream deleted, data files are being removed".to_string(),
+    )))
+}
+
+async fn delete_stream_metadata(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<(), anyhow::Error> {
+    // schema versions, settings are stored in the schema metadata
+    db::schema::delete(org_id, stream_name, stream_type).await?;
+    db::compact::files::del_offset(org_id, stream_name, stream_type).await?;
+
+    // alerts and their triggers
+    for alert in db::alerts::list(org_id, Some(stream_name)).await? {
+        db::alerts::delete(org_id, stream_name, &alert.name).await?;
+        let _ = db::triggers::delete(&alert.name).await; // alert may have no trigger
+    }
+    // functions are only bound to logs streams
+    if stream_type == StreamType::Logs {
+        for function in db::udf::list(org_id, Some(stream_name.to_string())).await? {
+            db::udf::delete(org_id, Some(stream_name.to_string()), &function.name).await?;
+        }
+    }
+    db::delete_stream_stats(org_id, stream_name, &stream_type.to_string()).await?;
+    Ok(())
+}
+
+async fn delete_stream_files(org_id: String, mut deletion: StreamDeletion, files: Vec<String>) {
+    let storage = &storage::DEFAULT;
+    for (i, file) in files.iter().enumerate() {
+        tokio::task::yield_now().await; // yield to other tasks
+        sidecar::delete(file).await;
+        match storage.del(file).await {
+            Ok(_) => deletion.deleted_files += 1,
+            Err(e) => {
+                deletion.failed_files += 1;
+                log::error!("[DELETE] delete file {} error: {}", file, e);
+            }
+        }
+        if (i + 1) % 100 == 0 {
+            deletion.updated_at = Utc::now().timestamp_micros();
+            if let Err(e) = db::deletion::set(&org_id, &deletion).await {
+                log::error!("[DELETE] save stream deletion status error: {}", e);
+            }
+        }
+    }
+    deletion.status = if deletion.failed_files == 0 {
+        StreamDeletionStatus::Completed
+    } else {
+        StreamDeletionStatus::Failed
+    };
+    deletion.updated_at = Utc::now().timestamp_micros();
+    if let Err(e) = db::deletion::set(&org_id, &deletion).await {
+        log::error!("[DELETE] save stream deletion status error: {}", e);
+    }
+    log::info!(
+        "[DELETE] stream {}/{}/{} files deleted: {}, failed: {}",
+        org_id,
+        deletion.stream_type,
+        deletion.stream_name,
+        deletion.deleted_files,
+        deletion.failed_files
+    );
+}
+
+pub async fn get_stream_deletion(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<HttpResponse, Error> {
+    match db::deletion::get(org_id, stream_name, stream_type).await {
+        Ok(Some(deletion)) => Ok(HttpResponse::Ok().json(deletion)),
+        Ok(None) => Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            Some("stream deletion not found".to_owned()),
+        ))),
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
+pub async fn get_schema_versions(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:streams:get_schema_versions");
+    let _guard = loc_span.enter();
+    let versions = db::schema::get_versions(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    if versions.is_empty() {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            Some("stream not found".to_owned()),
+        )));
+    }
+    let list = versions
+        .iter()
+        .enumerate()
+        .map(|(version, schema)| schema_version(version, schema, false))
+        .collect();
+    Ok(HttpResponse::Ok().json(SchemaVersionList { list }))
+}
+
+pub async fn get_schema_version(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    version: usize,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:streams:get_schema_version");
+    let _guard = loc_span.enter();
+    let versions = db::schema::get_versions(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    match versions.get(version) {
+        Some(schema) => Ok(HttpResponse::Ok().json(schema_version(version, schema, true))),
+        None => Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            Some("schema version not found".to_owned()),
+        ))),
+    }
+}
+
+/// Diffs two schema versions, `to` defaults to the latest version
+pub async fn diff_schema_versions(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    from: usize,
+    to: Option<usize>,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:streams:diff_schema_versions");
+    let _guard = loc_span.enter();
+    let versions = db::schema::get_versions(org_id, stream_name, Some(stream_type))
+        .await
+        .unwrap();
+    let to = to.unwrap_or_else(|| versions.len().saturating_sub(1));
+    match (versions.get(from), versions.get(to)) {
+        (Some(from_schema), Some(to_schema)) => {
+            Ok(HttpResponse::Ok().json(schema_diff(from, from_schema, to, to_schema)))
+        }
+        _ => Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            StatusCode::NOT_FOUND.into(),
+            Some("schema version not found".to_owned()),
+        ))),
+    }
+}
+
+fn schema_version(version: usize, schema: &Schema, with_fields: bool) -> SchemaVersion {
+    let metadata = schema.metadata();
+    let start_dt = metadata
+        .get("start_dt")
+        .or_else(|| metadata.get("created_at"))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_default();
+    let end_dt = metadata.get("end_dt").and_then(|v| v.parse().ok());
+    let fields = if with_fields {
+        schema
+            .fields()
+            .iter()
+            .map(|field| StreamProperty {
+                name: field.name().to_string(),
+                prop_type: field.data_type().to_string(),
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+    SchemaVersion {
+        version,
+        start_dt,
+        end_dt,
+        fields_count: schema.fields().len(),
+        fields,
+    }
+}
+
+pub(crate) fn schema_diff(
+    from: usize,
+    from_schema: &Schema,
+    to: usize,
+    to_schema: &Schema,
+) -> SchemaDiff {
+    let mut diff = SchemaDiff {
+        from,
+        to,
+        added: vec![],
+        removed: vec![],
+        changed: vec![],
+    };
+    for field in to_schema.fields() {
+        match from_schema.field_with_name(field.name()) {
+            Ok(old) => {
+                if old.data_type() != field.data_type() {
+                    diff.changed.push(SchemaFieldChange {
+                        name: field.name().to_string(),
+                        from_type: old.data_type().to_string(),
+                        to_type: field.data_type().to_string(),
+                    });
+                }
+            }
+            Err(_) => diff.added.push(StreamProperty {
+                name: field.name().to_string(),
+                prop_type: field.data_type().to_string(),
+            }),
+        }
+    }
+    for field in from_schema.fields() {
+        if to_schema.field_with_name(field.name()).is_err() {
+            diff.removed.push(StreamProperty {
+                name: field.name().to_string(),
+                prop_type: field.data_type().to_string(),
+            });
+        }
+    }
+    diff
+}
+
 pub fn get_stream_setting_fts_fields(schema: &Schema) -> Result<Vec<String>, anyhow::Error> {
     let mut full_text_search_keys = vec![];
     let settings = schema.metadata.get("settings");
@@ -214,6 +657,527 @@ pub fn get_stream_setting_fts_fields(schema: &Schema) -> Result<Vec<String>, any
     Ok(full_text_search_keys)
 }
 
+/// Validates the settings of a stream, `schema` holds the fields the stream
+/// already has
+pub fn validate_stream_settings(
+    stream_name: &str,
+    schema: &Schema,
+    setting: &StreamSettings,
+) -> Result<(), anyhow::Error> {
+    validate_partition_buckets(schema, setting)
+        .and_then(|_| {
+            // existing files are not moved to the new layout
+            if !schema.fields().is_empty()
+                && setting.partition_time_level != get_stream_setting_partition_time_level(schema)
+            {
+                return Err(anyhow::anyhow!(
+                    "partition_time_level can not be changed once the stream has data"
+                ));
+            }
+            Ok(())
+        })
+        .and_then(|_| validate_quality_rules(stream_name, &setting.data_quality_rules))
+        .and_then(|_| validate_sampling_rules(&setting.sampling_rules))
+        .and_then(|_| validate_derived_metrics(&setting.derived_metrics))
+        .and_then(|_| validate_processors(&setting.processors))
+        .and_then(|_| {
+            // the files written with a timestamp unit can not be read as Int64
+            if setting.timestamp_unit.is_none()
+                && get_stream_setting_timestamp_unit(schema).is_some()
+            {
+                return Err(anyhow::anyhow!("timestamp_unit can not be removed"));
+            }
+            // the WAL of the stream would be read with the wrong unit
+            let keep_nanos = setting.timestamp_unit == Some(TimestampUnit::Nanosecond);
+            if !schema.fields().is_empty() && keep_nanos != get_stream_setting_keep_nanos(schema) {
+                return Err(anyhow::anyhow!(
+                    "timestamp_unit can not be changed to or from nanosecond once the stream has data"
+                ));
+            }
+            Ok(())
+        })
+        .and_then(|_| {
+            // the changes of the audit stream would be written into itself
+            if setting.notify_schema_changes && stream_name == CONFIG.common.schema_change_stream {
+                return Err(anyhow::anyhow!(
+                    "notify_schema_changes can not be enabled for the schema change stream"
+                ));
+            }
+            Ok(())
+        })
+        .and_then(|_| {
+            if setting.data_retention < 0 || setting.data_retention > MAX_DATA_RETENTION_DAYS {
+                return Err(anyhow::anyhow!(
+                    "data_retention must be between 0 and {} days",
+                    MAX_DATA_RETENTION_DAYS
+                ));
+            }
+            Ok(())
+        })
+        .and_then(|_| validate_defined_schema(schema, &setting.defined_schema, setting.schema_mode))
+        .and_then(|_| validate_nested_fields(schema, setting))
+        .and_then(|_| validate_virtual_fields(schema, setting))
+        .and_then(|_| {
+            // values are looked up as they are written in SQL
+            validate_typed_fields(
+                schema,
+                "bloom_filter_fields",
+                &setting.bloom_filter_fields,
+                "text",
+                |data_type| data_type == &DataType::Utf8,
+            )
+        })
+        .and_then(|_| {
+            validate_typed_fields(
+                schema,
+                "stats_fields",
+                &setting.stats_fields,
+                "numeric",
+                column_stats::is_numeric,
+            )
+        })
+}
+
+fn validate_partition_buckets(
+    schema: &Schema,
+    setting: &StreamSettings,
+) -> Result<(), anyhow::Error> {
+    // the bucket of a value depends on the bucket count, existing files would
+    // be skipped by the search
+    if !schema.fields().is_empty()
+        && setting.partition_buckets != get_stream_setting_partition_buckets(schema)
+    {
+        return Err(anyhow::anyhow!(
+            "partition_buckets can not be changed once the stream has data"
+        ));
+    }
+    for (field, buckets) in setting.partition_buckets.iter() {
+        if !setting.partition_keys.contains(field) {
+            return Err(anyhow::anyhow!(
+                "partition_buckets: field {} is not a partition key",
+                field
+            ));
+        }
+        if *buckets == 0 {
+            return Err(anyhow::anyhow!(
+                "partition_buckets: field {} requires at least one bucket",
+                field
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_quality_rules(stream_name: &str, rules: &[QualityRule]) -> Result<(), anyhow::Error> {
+    for rule in rules {
+        if rule.name.is_empty() || rule.field.is_empty() {
+            return Err(anyhow::anyhow!("data quality rule requires name and field"));
+        }
+        match rule.rule_type {
+            QualityRuleType::Regex => match &rule.pattern {
+                Some(pattern) => {
+                    if let Err(e) = regex::Regex::new(pattern) {
+                        return Err(anyhow::anyhow!(
+                            "rule {}: invalid pattern: {}",
+                            rule.name,
+                            e
+                        ));
+                    }
+                }
+                None => return Err(anyhow::anyhow!("rule {}: pattern is required", rule.name)),
+            },
+            QualityRuleType::Enum if rule.values.is_empty() => {
+                return Err(anyhow::anyhow!("rule {}: values are required", rule.name))
+            }
+            QualityRuleType::Range if rule.min.is_none() && rule.max.is_none() => {
+                return Err(anyhow::anyhow!(
+                    "rule {}: min or max is required",
+                    rule.name
+                ))
+            }
+            QualityRuleType::Type if rule.value_type.is_none() => {
+                return Err(anyhow::anyhow!(
+                    "rule {}: value_type is required",
+                    rule.name
+                ))
+            }
+            _ => {}
+        }
+        if rule.action == QualityAction::Route {
+            match &rule.route_to {
+                Some(target) if !target.is_empty() && target != stream_name => {}
+                _ => {
+                    return Err(anyhow::anyhow!(
+                        "rule {}: route_to must be another stream",
+                        rule.name
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_derived_metrics(metrics: &[DerivedMetric]) -> Result<(), anyhow::Error> {
+    for metric in metrics {
+        if metric.name.is_empty() {
+            return Err(anyhow::anyhow!("derived metric requires name"));
+        }
+        // the name is the name of a metrics stream
+        if metric.name.contains('/') {
+            return Err(anyhow::anyhow!(
+                "metric {}: name can not contain /",
+                metric.name
+            ));
+        }
+        if metric.metric_type != DerivedMetricType::Counter && metric.field.is_none() {
+            return Err(anyhow::anyhow!("metric {}: field is required", metric.name));
+        }
+        // a value is counted in the first bucket its bound covers
+        if metric.buckets.iter().any(|bound| !bound.is_finite())
+            || metric.buckets.windows(2).any(|pair| pair[0] >= pair[1])
+        {
+            return Err(anyhow::anyhow!(
+                "metric {}: buckets must be finite and increasing",
+                metric.name
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_sampling_rules(rules: &[SamplingRule]) -> Result<(), anyhow::Error> {
+    for rule in rules {
+        match rule.action {
+            SamplingAction::OneInN if rule.rate.unwrap_or_default() == 0 => {
+                return Err(anyhow::anyhow!("rule {}: rate is required", rule.name))
+            }
+            SamplingAction::Probability
+                if !rule.probability.map_or(false, |p| p > 0.0 && p <= 1.0) =>
+            {
+                return Err(anyhow::anyhow!(
+                    "rule {}: probability must be in (0, 1]",
+                    rule.name
+                ))
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn validate_processors(processors: &[EnrichmentProcessor]) -> Result<(), anyhow::Error> {
+    for processor in processors {
+        if processor.field.is_empty() {
+            return Err(anyhow::anyhow!(
+                "processor {:?}: field is required",
+                processor.processor
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// declared types must match the types of the fields already in the stream,
+/// existing data files are not rewritten
+fn validate_defined_schema(
+    schema: &Schema,
+    fields: &[DefinedField],
+    mode: SchemaMode,
+) -> Result<(), anyhow::Error> {
+    if mode == SchemaMode::Strict && fields.is_empty() {
+        return Err(anyhow::anyhow!(
+            "schema_mode strict requires a defined_schema"
+        ));
+    }
+    let mut names = std::collections::HashSet::new();
+    for field in fields {
+        if field.name.is_empty() {
+            return Err(anyhow::anyhow!("defined_schema: field name is required"));
+        }
+        if !names.insert(field.name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "defined_schema: field {} is defined more than once",
+                field.name
+            ));
+        }
+        let data_type = field.field_type.data_type();
+        if field.name == CONFIG.common.time_stamp_col && data_type != DataType::Int64 {
+            return Err(anyhow::anyhow!(
+                "defined_schema: field {} must be int64",
+                field.name
+            ));
+        }
+        // the files written before are cast to the declared type at query time
+        // when older schema versions are reconciled
+        if let Ok(existing) = schema.field_with_name(&field.name) {
+            if existing.data_type() != &data_type
+                && (!CONFIG.common.widening_schema_evoluation
+                    || !can_cast_types(existing.data_type(), &data_type))
+            {
+                return Err(anyhow::anyhow!(
+                    "defined_schema: field {} already exists with type {} which can not be cast to {}",
+                    field.name,
+                    existing.data_type(),
+                    data_type
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_nested_fields(schema: &Schema, setting: &StreamSettings) -> Result<(), anyhow::Error> {
+    let mut names = std::collections::HashSet::new();
+    for field in setting.nested_fields.iter() {
+        if field.name.is_empty() || field.name.contains('.') {
+            return Err(anyhow::anyhow!(
+                "nested_fields: field name must be a top level field"
+            ));
+        }
+        if field.name == CONFIG.common.time_stamp_col {
+            return Err(anyhow::anyhow!(
+                "nested_fields: field {} can not be nested",
+                field.name
+            ));
+        }
+        if !names.insert(field.name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "nested_fields: field {} is defined more than once",
+                field.name
+            ));
+        }
+        if setting.defined_schema.iter().any(|f| f.name == field.name)
+            || setting.partition_keys.contains(&field.name)
+        {
+            return Err(anyhow::anyhow!(
+                "nested_fields: field {} is a defined or partition field",
+                field.name
+            ));
+        }
+        // the flattened columns of the field stay as they are
+        if let Ok(existing) = schema.field_with_name(&field.name) {
+            let nested = matches!(
+                existing.data_type(),
+                DataType::Struct(_) | DataType::List(_) | DataType::Map(_, _)
+            );
+            if !nested {
+                return Err(anyhow::anyhow!(
+                    "nested_fields: field {} already exists with type {}",
+                    field.name,
+                    existing.data_type()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn validate_virtual_fields(schema: &Schema, setting: &StreamSettings) -> Result<(), anyhow::Error> {
+    let re = regex::Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
+    let mut names = std::collections::HashSet::new();
+    for field in setting.virtual_fields.iter() {
+        if !re.is_match(&field.name) {
+            return Err(anyhow::anyhow!(
+                "virtual_fields: field name {} must only contain letters, digits and _",
+                field.name
+            ));
+        }
+        if !names.insert(field.name.as_str()) {
+            return Err(anyhow::anyhow!(
+                "virtual_fields: field {} is defined more than once",
+                field.name
+            ));
+        }
+        if field.name == CONFIG.common.time_stamp_col
+            || schema.field_with_name(&field.name).is_ok()
+            || setting.defined_schema.iter().any(|f| f.name == field.name)
+            || setting.nested_fields.iter().any(|f| f.name == field.name)
+            || setting.partition_keys.contains(&field.name)
+        {
+            return Err(anyhow::anyhow!(
+                "virtual_fields: field {} is already a field of the stream",
+                field.name
+            ));
+        }
+        if field.expr.trim().is_empty() {
+            return Err(anyhow::anyhow!(
+                "virtual_fields: field {} has an empty expression",
+                field.name
+            ));
+        }
+        if !is_scalar_expr(&field.expr) {
+            return Err(anyhow::anyhow!(
+                "virtual_fields: field {} has an invalid expression {}",
+                field.name,
+                field.expr
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Whether the text is a single expression without subqueries
+fn is_scalar_expr(text: &str) -> bool {
+    let dialect = GenericDialect {};
+    let mut parser = match Parser::new(&dialect).try_with_sql(text) {
+        Ok(parser) => parser,
+        Err(_) => return false,
+    };
+    let expr = match parser.parse_expr() {
+        Ok(expr) => expr,
+        Err(_) => return false,
+    };
+    if parser.peek_token().token != Token::EOF {
+        return false;
+    }
+    let subquery = visit_expressions(&expr, |expr| match expr {
+        SqlExpr::Subquery(_)
+        | SqlExpr::ArraySubquery(_)
+        | SqlExpr::Exists { .. }
+        | SqlExpr::InSubquery { .. } => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    });
+    subquery.is_continue()
+}
+
+/// Checks a setting listing fields of one kind of type, eg: the numeric
+/// `stats_fields`, the fields the schema doesn't have yet are accepted
+fn validate_typed_fields(
+    schema: &Schema,
+    setting: &str,
+    fields: &[String],
+    type_name: &str,
+    has_type: fn(&DataType) -> bool,
+) -> Result<(), anyhow::Error> {
+    let mut names = std::collections::HashSet::new();
+    for field in fields.iter() {
+        if field.is_empty() || field == &CONFIG.common.time_stamp_col {
+            return Err(anyhow::anyhow!(
+                "{}: field {} can not be listed",
+                setting,
+                field
+            ));
+        }
+        if !names.insert(field.as_str()) {
+            return Err(anyhow::anyhow!(
+                "{}: field {} is listed more than once",
+                setting,
+                field
+            ));
+        }
+        if let Ok(existing) = schema.field_with_name(field) {
+            if !has_type(existing.data_type()) {
+                return Err(anyhow::anyhow!(
+                    "{}: field {} must be {}, it is {}",
+                    setting,
+                    field,
+                    type_name,
+                    existing.data_type()
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// partition keys are stored as an object ordered by its `L{index}` keys
+pub fn get_stream_setting_partition_keys(schema: &Schema) -> Vec<String> {
+    let keys: HashMap<String, String> = get_stream_setting_value(schema, "partition_keys");
+    let mut keys: Vec<_> = keys.into_iter().collect();
+    keys.sort();
+    keys.into_iter().map(|(_, field)| field).collect()
+}
+
+pub fn get_stream_setting_partition_buckets(schema: &Schema) -> HashMap<String, u64> {
+    get_stream_setting_value(schema, "partition_buckets")
+}
+
+pub fn get_stream_setting_partition_time_level(schema: &Schema) -> PartitionTimeLevel {
+    get_stream_setting_value(schema, "partition_time_level")
+}
+
+pub fn get_stream_setting_quality_rules(schema: &Schema) -> Vec<QualityRule> {
+    get_stream_setting_value(schema, "data_quality_rules")
+}
+
+pub fn get_stream_setting_sampling_rules(schema: &Schema) -> Vec<SamplingRule> {
+    get_stream_setting_value(schema, "sampling_rules")
+}
+
+pub fn get_stream_setting_derived_metrics(schema: &Schema) -> Vec<DerivedMetric> {
+    get_stream_setting_value(schema, "derived_metrics")
+}
+
+pub fn get_stream_setting_enrichments(schema: &Schema) -> Vec<LookupEnrichment> {
+    get_stream_setting_value(schema, "enrichments")
+}
+
+pub fn get_stream_setting_processors(schema: &Schema) -> Vec<EnrichmentProcessor> {
+    get_stream_setting_value(schema, "processors")
+}
+
+pub fn get_stream_setting_data_retention(schema: &Schema) -> i64 {
+    get_stream_setting_value(schema, "data_retention")
+}
+
+pub fn get_stream_setting_timestamp_unit(schema: &Schema) -> Option<TimestampUnit> {
+    get_stream_setting_value(schema, "timestamp_unit")
+}
+
+/// Whether the time column of the stream keeps the nanoseconds, the WAL then
+/// holds nanoseconds instead of microseconds
+pub fn get_stream_setting_keep_nanos(schema: &Schema) -> bool {
+    get_stream_setting_timestamp_unit(schema) == Some(TimestampUnit::Nanosecond)
+}
+
+pub fn get_stream_setting_nested_fields(schema: &Schema) -> Vec<NestedField> {
+    get_stream_setting_value(schema, "nested_fields")
+}
+
+pub fn get_stream_setting_virtual_fields(schema: &Schema) -> Vec<VirtualField> {
+    get_stream_setting_value(schema, "virtual_fields")
+}
+
+pub fn get_stream_setting_notify_schema_changes(schema: &Schema) -> bool {
+    get_stream_setting_value(schema, "notify_schema_changes")
+}
+
+pub fn get_stream_setting_fulltext_index(schema: &Schema) -> bool {
+    get_stream_setting_value(schema, "fulltext_index")
+}
+
+pub fn get_stream_setting_bloom_filter_fields(schema: &Schema) -> Vec<String> {
+    get_stream_setting_value(schema, "bloom_filter_fields")
+}
+
+pub fn get_stream_setting_stats_fields(schema: &Schema) -> Vec<String> {
+    get_stream_setting_value(schema, "stats_fields")
+}
+
+pub fn get_stream_setting_defined_schema(schema: &Schema) -> Vec<DefinedField> {
+    get_stream_setting_value(schema, "defined_schema")
+}
+
+pub fn get_stream_setting_schema_mode(schema: &Schema) -> SchemaMode {
+    get_stream_setting_value(schema, "schema_mode")
+}
+
+fn get_stream_setting_value<T: DeserializeOwned + Default>(schema: &Schema, key: &str) -> T {
+    let settings = match schema.metadata.get("settings") {
+        Some(settings) => settings,
+        None => return T::default(),
+    };
+    let settings: Value = match json::from_slice(settings.as_bytes()) {
+        Ok(v) => v,
+        Err(_) => return T::default(),
+    };
+    match settings.get(key) {
+        Some(value) => serde_json::from_value(value.clone()).unwrap_or_default(),
+        None => T::default(),
+    }
+}
+
 fn transform_stats(stats: &mut StreamStats) -> StreamStats {
     stats.storage_size /= SIZE_IN_MB;
     stats.compressed_size /= SIZE_IN_MB;
@@ -245,4 +1209,108 @@ mod test {
         let res = get_stream_setting_fts_fields(&sch);
         assert!(res.is_ok());
     }
+    #[test]
+    fn test_get_stream_setting_quality_rules() {
+        let mut meta = std::collections::HashMap::new();
+        meta.insert(
+            "settings".to_string(),
+            r#"{"partition_keys":{},"full_text_search_keys":[],"data_quality_rules":[{"name":"r1","field":"level","rule_type":"required"}]}"#.to_string(),
+        );
+        let sch = Schema::new(vec![Field::new("f.c", DataType::Int32, false)]).with_metadata(meta);
+        let res = get_stream_setting_quality_rules(&sch);
+        assert_eq!(res.len(), 1);
+    }
+    #[test]
+    fn test_validate_virtual_fields() {
+        let sch = Schema::new(vec![Field::new("path", DataType::Utf8, true)]);
+        let mut setting = StreamSettings {
+            virtual_fields: vec![VirtualField {
+                name: "route".to_string(),
+                expr: "split_part(path, '/', 2)".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(validate_virtual_fields(&sch, &setting).is_ok());
+        setting.virtual_fields[0].name = "path".to_string();
+        assert!(validate_virtual_fields(&sch, &setting).is_err());
+        setting.virtual_fields[0].name = "route".to_string();
+        setting.virtual_fields[0].expr = "path FROM x; DROP TABLE x;".to_string();
+        assert!(validate_virtual_fields(&sch, &setting).is_err());
+        setting.virtual_fields[0].expr = "(SELECT max(code) FROM tbl)".to_string();
+        assert!(validate_virtual_fields(&sch, &setting).is_err());
+        setting.virtual_fields[0].expr = "code IN (SELECT code FROM tbl)".to_string();
+        assert!(validate_virtual_fields(&sch, &setting).is_err());
+    }
+    #[test]
+    fn test_validate_derived_metrics() {
+        let metric = |name: &str, buckets: &str| -> Vec<DerivedMetric> {
+            serde_json::from_str(&format!(
+                r#"[{{"name":"{}","metric_type":"histogram","field":"took","buckets":{}}}]"#,
+                name, buckets
+            ))
+            .unwrap()
+        };
+        assert!(validate_derived_metrics(&metric("took", "[1.0, 10.0]")).is_ok());
+        assert!(validate_derived_metrics(&metric("took", "[]")).is_ok());
+        assert!(validate_derived_metrics(&metric("took", "[10.0, 1.0]")).is_err());
+        assert!(validate_derived_metrics(&metric("took", "[1.0, 1.0]")).is_err());
+        assert!(validate_derived_metrics(&metric("a/took", "[1.0]")).is_err());
+    }
+    #[test]
+    fn test_validate_typed_fields() {
+        let sch = Schema::new(vec![
+            Field::new("trace_id", DataType::Utf8, true),
+            Field::new("status", DataType::Int64, true),
+        ]);
+        let is_text = |data_type: &DataType| data_type == &DataType::Utf8;
+        let fields = vec!["trace_id".to_string(), "request_id".to_string()];
+        assert!(
+            validate_typed_fields(&sch, "bloom_filter_fields", &fields, "text", is_text).is_ok()
+        );
+        let fields = vec!["trace_id".to_string(), "trace_id".to_string()];
+        assert!(
+            validate_typed_fields(&sch, "bloom_filter_fields", &fields, "text", is_text).is_err()
+        );
+        let fields = vec!["status".to_string()];
+        assert!(
+            validate_typed_fields(&sch, "bloom_filter_fields", &fields, "text", is_text).is_err()
+        );
+        let is_numeric = column_stats::is_numeric;
+        let fields = vec!["status".to_string(), "duration".to_string()];
+        assert!(
+            validate_typed_fields(&sch, "stats_fields", &fields, "numeric", is_numeric).is_ok()
+        );
+        let fields = vec!["trace_id".to_string()];
+        assert!(
+            validate_typed_fields(&sch, "stats_fields", &fields, "numeric", is_numeric).is_err()
+        );
+        let fields = vec![CONFIG.common.time_stamp_col.clone()];
+        assert!(
+            validate_typed_fields(&sch, "stats_fields", &fields, "numeric", is_numeric).is_err()
+        );
+    }
+    #[test]
+    fn test_schema_diff() {
+        let from = Schema::new(vec![
+            Field::new("status", DataType::Int64, true),
+            Field::new("host", DataType::Utf8, true),
+        ]);
+        let to = Schema::new(vec![
+            Field::new("status", DataType::Utf8, true),
+            Field::new("path", DataType::Utf8, true),
+        ]);
+        let diff = schema_diff(0, &from, 1, &to);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].name, "path");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].name, "host");
+        assert_eq!(
+            diff.changed,
+            vec![SchemaFieldChange {
+                name: "status".to_string(),
+                from_type: "Int64".to_string(),
+                to_type: "Utf8".to_string(),
+            }]
+        );
+    }
 }
@@ -0,0 +1,170 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use datafusion::arrow::datatypes::Schema;
+use std::io::Error;
+use tracing::info_span;
+
+use crate::infra::config::STREAM_ALIASES;
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::stream::{StreamAlias, StreamAliasList};
+use crate::meta::StreamType;
+use crate::service::db;
+
+pub async fn save_alias(
+    org_id: &str,
+    name: &str,
+    mut alias: StreamAlias,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_aliases:save");
+    let _guard = loc_span.enter();
+    alias.name = name.to_string();
+    if let Err(e) = validate_alias(&mut alias) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(e.to_string()),
+        )));
+    }
+    if let Err(e) = check_streams(org_id, &alias).await {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(e.to_string()),
+        )));
+    }
+    if let Err(e) = db::stream_aliases::set(org_id, &alias).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        );
+    }
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        http::StatusCode::OK.into(),
+        "Stream alias saved".to_string(),
+    )))
+}
+
+pub async fn list_aliases(org_id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_aliases:list");
+    let _guard = loc_span.enter();
+    let mut list = db::stream_aliases::list(org_id).await.unwrap();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(HttpResponse::Ok().json(StreamAliasList { list }))
+}
+
+pub async fn delete_alias(
+    org_id: &str,
+    stream_type: StreamType,
+    name: &str,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_aliases:delete");
+    let _guard = loc_span.enter();
+    if db::stream_aliases::get(org_id, stream_type, name)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("stream alias not found".to_string()),
+        )));
+    }
+    db::stream_aliases::delete(org_id, stream_type, name)
+        .await
+        .unwrap();
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        http::StatusCode::OK.into(),
+        "Stream alias deleted".to_string(),
+    )))
+}
+
+/// Returns the streams of the alias, None if the name is not an alias
+pub fn resolve_alias(org_id: &str, stream_type: StreamType, name: &str) -> Option<Vec<String>> {
+    let map_key = format!("{}/{}/{}", org_id, stream_type, name);
+    STREAM_ALIASES
+        .get(&map_key)
+        .map(|alias| alias.streams.clone())
+}
+
+fn validate_alias(alias: &mut StreamAlias) -> Result<(), anyhow::Error> {
+    if alias.name.trim().is_empty() || alias.name.contains('/') {
+        return Err(anyhow::anyhow!("alias name is not valid"));
+    }
+    let mut streams = Vec::with_capacity(alias.streams.len());
+    for stream in alias.streams.iter() {
+        let stream = stream.trim();
+        if stream.is_empty() {
+            return Err(anyhow::anyhow!("alias streams must not be empty"));
+        }
+        if stream == alias.name {
+            return Err(anyhow::anyhow!("alias must not point to itself"));
+        }
+        if !streams.iter().any(|v: &String| v == stream) {
+            streams.push(stream.to_string());
+        }
+    }
+    if streams.is_empty() {
+        return Err(anyhow::anyhow!("alias requires at least one stream"));
+    }
+    alias.streams = streams;
+    Ok(())
+}
+
+/// the alias must not shadow a stream, and its streams must exist
+async fn check_streams(org_id: &str, alias: &StreamAlias) -> Result<(), anyhow::Error> {
+    let schema = db::schema::get(org_id, &alias.name, Some(alias.stream_type)).await?;
+    if schema != Schema::empty() {
+        return Err(anyhow::anyhow!(
+            "a stream named {} already exists",
+            alias.name
+        ));
+    }
+    for stream in alias.streams.iter() {
+        let schema = db::schema::get(org_id, stream, Some(alias.stream_type)).await?;
+        if schema == Schema::empty() {
+            return Err(anyhow::anyhow!("stream {} not found", stream));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate_alias() {
+        let mut alias = StreamAlias {
+            name: "web".to_string(),
+            stream_type: StreamType::Logs,
+            streams: vec![
+                "web_v1".to_string(),
+                " web_v2 ".to_string(),
+                "web_v1".to_string(),
+            ],
+        };
+        assert!(validate_alias(&mut alias).is_ok());
+        assert_eq!(alias.streams, vec!["web_v1", "web_v2"]);
+
+        alias.streams = vec![];
+        assert!(validate_alias(&mut alias).is_err());
+        alias.streams = vec!["web".to_string()];
+        assert!(validate_alias(&mut alias).is_err());
+        alias.name = "a/b".to_string();
+        alias.streams = vec!["web_v1".to_string()];
+        assert!(validate_alias(&mut alias).is_err());
+    }
+}
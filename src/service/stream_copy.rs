@@ -0,0 +1,505 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use ahash::AHashMap as HashMap;
+use chrono::Utc;
+use datafusion::arrow::datatypes::Schema;
+use regex::Regex;
+use std::io::Error;
+use std::sync::Arc;
+use tracing::info_span;
+
+use crate::common::time::time_column_literal;
+use crate::infra::config::CONFIG;
+use crate::infra::db::etcd;
+use crate::infra::{cache, storage};
+use crate::meta::common::{FileKey, FileMeta};
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::stream::{
+    StreamAlias, StreamCloneRequest, StreamCopyReport, StreamRename, StreamRenameRequest,
+};
+use crate::meta::StreamType;
+use crate::service::search::datafusion::exec::merge_parquet_files;
+use crate::service::stream::get_stream_setting_partition_time_level;
+use crate::service::{db, file_list, sidecar};
+
+/// Renames a stream: the data files, schema versions, settings, functions,
+/// alerts and compaction offset are moved to the new name. Data still in the
+/// ingesters' WAL is uploaded under the old name, so ingestion into the
+/// stream should be stopped first. The rename is journaled, an interrupted
+/// rename is resumed by repeating the request.
+pub async fn rename_stream(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    req: StreamRenameRequest,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_copy:rename");
+    let _guard = loc_span.enter();
+    // an alias resolves only when no stream has its name, only new logs
+    // streams are refused under the name of an alias
+    if req.keep_alias && stream_type != StreamType::Logs {
+        return Ok(bad_request(anyhow::anyhow!(
+            "keep_alias is only supported for logs streams, writes to the old name would create a new stream"
+        )));
+    }
+    let journal = match db::stream_rename::get(org_id, stream_name, stream_type).await {
+        Ok(v) => v,
+        Err(e) => return Ok(internal_error(e)),
+    };
+    let versions = match &journal {
+        Some(journal) if journal.new_name != req.new_name => {
+            return Ok(bad_request(anyhow::anyhow!(
+                "stream {} is being renamed to {}, repeat that rename to resume it",
+                stream_name,
+                journal.new_name
+            )))
+        }
+        Some(_) => resume_versions(org_id, stream_name, stream_type, &req.new_name).await,
+        None => validate_target(org_id, stream_name, stream_type, &req.new_name).await,
+    };
+    let versions = match versions {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let new_name = req.new_name.as_str();
+    if journal.is_none() {
+        let journal = StreamRename {
+            stream_name: stream_name.to_string(),
+            stream_type,
+            new_name: new_name.to_string(),
+            created_at: Utc::now().timestamp_micros(),
+        };
+        if let Err(e) = db::stream_rename::set(org_id, &journal).await {
+            return Ok(internal_error(e));
+        }
+    }
+
+    let mut locker = None;
+    if !CONFIG.common.local_mode {
+        // share the lock with merge, the files must not be compacted meanwhile
+        let lock_key = format!("compactor/files/{}/{}/{}", org_id, stream_type, stream_name);
+        let mut lock = etcd::Locker::new(&lock_key);
+        if let Err(e) = lock.lock(CONFIG.etcd.command_timeout).await {
+            return Ok(internal_error(e.into()));
+        }
+        locker = Some(lock);
+    }
+    let ret = move_stream(
+        org_id,
+        stream_name,
+        stream_type,
+        new_name,
+        &versions,
+        req.keep_alias,
+    )
+    .await;
+    if locker.is_some() {
+        // release cluster lock
+        let mut lock = locker.unwrap();
+        if let Err(e) = lock.unlock().await {
+            log::error!("[RENAME] unlock stream {} error: {}", stream_name, e);
+        }
+    }
+    let report = match ret {
+        Ok(v) => v,
+        Err(e) => return Ok(internal_error(e)),
+    };
+
+    if let Err(e) = update_aliases(org_id, stream_name, stream_type, new_name).await {
+        log::error!("[RENAME] update aliases of {} error: {}", stream_name, e);
+    }
+    if let Err(e) = db::stream_rename::delete(org_id, stream_name, stream_type).await {
+        log::error!("[RENAME] delete journal of {} error: {}", stream_name, e);
+    }
+    log::info!(
+        "[RENAME] stream {}/{}/{} renamed to {}, files: {}",
+        org_id,
+        stream_type,
+        stream_name,
+        new_name,
+        report.files
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Copies the data of a time range, the schema versions, settings and
+/// functions of a stream into a new stream
+pub async fn clone_stream(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    req: StreamCloneRequest,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_copy:clone");
+    let _guard = loc_span.enter();
+    if req.start_time < 0 || (req.end_time > 0 && req.end_time <= req.start_time) {
+        return Ok(bad_request(anyhow::anyhow!("invalid time range")));
+    }
+    let versions = match validate_target(org_id, stream_name, stream_type, &req.new_name).await {
+        Ok(v) => v,
+        Err(e) => return Ok(bad_request(e)),
+    };
+    let new_name = req.new_name.as_str();
+    let end_time = if req.end_time > 0 {
+        req.end_time
+    } else {
+        Utc::now().timestamp_micros()
+    };
+
+    // the schema goes first, the new files are checked against it
+    if let Err(e) = db::schema::set_versions(org_id, new_name, stream_type, &versions).await {
+        return Ok(internal_error(e));
+    }
+    let report = match copy_files(
+        org_id,
+        stream_name,
+        stream_type,
+        new_name,
+        (req.start_time, end_time),
+        false,
+    )
+    .await
+    {
+        Ok(v) => v,
+        Err(e) => return Ok(internal_error(e)),
+    };
+    if stream_type == StreamType::Logs {
+        for mut function in db::udf::list(org_id, Some(stream_name.to_string()))
+            .await
+            .unwrap_or_default()
+        {
+            if function.stream_name != stream_name {
+                continue;
+            }
+            let name = function.name.clone();
+            function.stream_name = new_name.to_string();
+            if let Err(e) = db::udf::set(org_id, Some(new_name.to_string()), &name, function).await
+            {
+                log::error!("[CLONE] copy function {} error: {}", name, e);
+            }
+        }
+    }
+    log::info!(
+        "[CLONE] stream {}/{}/{} cloned to {}, files: {}, records: {}",
+        org_id,
+        stream_type,
+        stream_name,
+        new_name,
+        report.files,
+        report.records
+    );
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// validates the new name, returns the schema versions of the stream
+async fn validate_target(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    new_name: &str,
+) -> Result<Vec<Schema>, anyhow::Error> {
+    if new_name.trim().is_empty() || new_name.contains('/') {
+        return Err(anyhow::anyhow!("new_name is not valid"));
+    }
+    if new_name == stream_name {
+        return Err(anyhow::anyhow!("new_name must differ from the stream name"));
+    }
+    let versions = db::schema::get_versions(org_id, stream_name, Some(stream_type)).await?;
+    if versions.is_empty() {
+        return Err(anyhow::anyhow!("stream {} not found", stream_name));
+    }
+    let schema = db::schema::get(org_id, new_name, Some(stream_type)).await?;
+    if schema != Schema::empty() {
+        return Err(anyhow::anyhow!("stream {} already exists", new_name));
+    }
+    if db::stream_aliases::get(org_id, stream_type, new_name)
+        .await?
+        .is_some()
+    {
+        return Err(anyhow::anyhow!(
+            "an alias named {} already exists",
+            new_name
+        ));
+    }
+    Ok(versions)
+}
+
+/// returns the schema versions of a journaled rename, the old stream's until
+/// its schema was deleted
+async fn resume_versions(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    new_name: &str,
+) -> Result<Vec<Schema>, anyhow::Error> {
+    let versions = db::schema::get_versions(org_id, stream_name, Some(stream_type)).await?;
+    if !versions.is_empty() {
+        return Ok(versions);
+    }
+    let versions = db::schema::get_versions(org_id, new_name, Some(stream_type)).await?;
+    if versions.is_empty() {
+        return Err(anyhow::anyhow!("stream {} not found", stream_name));
+    }
+    Ok(versions)
+}
+
+/// Moves the stream, every step can be repeated to resume an interrupted
+/// rename
+async fn move_stream(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    new_name: &str,
+    versions: &[Schema],
+    keep_alias: bool,
+) -> Result<StreamCopyReport, anyhow::Error> {
+    db::schema::set_versions(org_id, new_name, stream_type, versions).await?;
+    let report = copy_files(org_id, stream_name, stream_type, new_name, (0, 0), true).await?;
+
+    // the alias goes before the schema is deleted, so no write in between
+    // creates a new stream under the old name
+    if keep_alias {
+        let alias = StreamAlias {
+            name: stream_name.to_string(),
+            stream_type,
+            streams: vec![new_name.to_string()],
+        };
+        db::stream_aliases::set(org_id, &alias).await?;
+    }
+    // schema versions, settings are stored in the schema metadata
+    db::schema::delete(org_id, stream_name, stream_type).await?;
+    db::delete_stream_stats(org_id, stream_name, &stream_type.to_string()).await?;
+    cache::file_list::remove_stream(org_id, stream_type, stream_name);
+    let offset = db::compact::files::get_offset(org_id, stream_name, stream_type).await?;
+    if offset > 0 {
+        db::compact::files::set_offset(org_id, new_name, stream_type, offset).await?;
+        db::compact::files::del_offset(org_id, stream_name, stream_type).await?;
+    }
+
+    // alerts keep their name, so their triggers are kept as well
+    for mut alert in db::alerts::list(org_id, Some(stream_name)).await? {
+        if alert.stream != stream_name {
+            continue;
+        }
+        let name = alert.name.clone();
+        alert.stream = new_name.to_string();
+        if let Some(query) = alert.query.as_mut() {
+            query.sql = replace_source(&query.sql, stream_name, new_name);
+        }
+        db::alerts::set(org_id, new_name, &name, alert).await?;
+        db::alerts::delete(org_id, stream_name, &name).await?;
+    }
+    // functions are only bound to logs streams
+    if stream_type == StreamType::Logs {
+        for mut function in db::udf::list(org_id, Some(stream_name.to_string())).await? {
+            if function.stream_name != stream_name {
+                continue;
+            }
+            let name = function.name.clone();
+            function.stream_name = new_name.to_string();
+            db::udf::set(org_id, Some(new_name.to_string()), &name, function).await?;
+            db::udf::delete(org_id, Some(stream_name.to_string()), &name).await?;
+        }
+    }
+    Ok(report)
+}
+
+/// Copies the files overlapping the time range into the new stream, files
+/// crossing the range boundaries are rewritten with the records in range.
+/// When moving, the old files are removed. A zero time range copies all.
+async fn copy_files(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    new_name: &str,
+    time_range: (i64, i64),
+    move_files: bool,
+) -> Result<StreamCopyReport, anyhow::Error> {
+    let (start_time, end_time) = time_range;
+    let clip = start_time > 0 || end_time > 0;
+    let stream_schema = db::schema::get(org_id, stream_name, Some(stream_type)).await?;
+    let partition_time_level = get_stream_setting_partition_time_level(&stream_schema);
+    let schema = Arc::new(
+        stream_schema
+            .clone()
+            .with_metadata(std::collections::HashMap::new()),
+    );
+    let time_col = &CONFIG.common.time_stamp_col;
+    let exclude = format!(
+        "{} < {} OR {} >= {}",
+        time_col,
+        time_column_literal(&schema, start_time),
+        time_col,
+        time_column_literal(&schema, end_time)
+    );
+    let files = cache::file_list::get_file_list(
+        org_id,
+        stream_name,
+        stream_type,
+        start_time,
+        end_time,
+        partition_time_level,
+    )
+    .await?;
+
+    let from_prefix = format!("files/{}/{}/{}/", org_id, stream_type, stream_name);
+    let to_prefix = format!("files/{}/{}/{}/", org_id, stream_type, new_name);
+    let mut report = StreamCopyReport {
+        stream_name: stream_name.to_string(),
+        new_name: new_name.to_string(),
+        ..Default::default()
+    };
+    let mut hour_events: HashMap<String, Vec<FileKey>> = HashMap::new();
+    let storage = &storage::DEFAULT;
+    for file in files.iter() {
+        tokio::task::yield_now().await; // yield to other tasks
+        let file_meta = file_list::get_file_meta(file).await?;
+        if clip && (file_meta.max_ts < start_time || file_meta.min_ts >= end_time) {
+            continue;
+        }
+        let new_file = match file.strip_prefix(&from_prefix) {
+            Some(v) => format!("{}{}", to_prefix, v),
+            None => continue,
+        };
+        let new_file_meta =
+            if clip && (file_meta.min_ts < start_time || file_meta.max_ts >= end_time) {
+                let mut buf = Vec::new();
+                let mut new_file_meta =
+                    merge_parquet_files(&mut buf, schema.clone(), &[file.clone()], Some(&exclude))
+                        .await?;
+                if new_file_meta.records == 0 {
+                    continue;
+                }
+                // the original size is not known after rewrite, estimate it by records
+                new_file_meta.original_size = file_meta
+                    .original_size
+                    .checked_mul(new_file_meta.records)
+                    .and_then(|v| v.checked_div(file_meta.records))
+                    .unwrap_or(file_meta.original_size);
+                new_file_meta.compressed_size = buf.len() as u64;
+                storage.put(&new_file, buf.into()).await?;
+                new_file_meta
+            } else {
+                storage.copy(file, &new_file).await?;
+                file_meta
+            };
+        sidecar::copy(file, &new_file).await;
+
+        // eg: files/default/logs/olympics/2022/10/03/10/6982652937134804993_1.parquet
+        let hour = file
+            .split('/')
+            .skip(4)
+            .take(4)
+            .collect::<Vec<_>>()
+            .join("/");
+        report.files += 1;
+        report.records += new_file_meta.records;
+        let events = hour_events.entry(hour).or_default();
+        events.push(FileKey {
+            key: new_file,
+            meta: new_file_meta,
+            deleted: false,
+        });
+        if move_files {
+            events.push(FileKey {
+                key: file.clone(),
+                meta: FileMeta::default(),
+                deleted: true,
+            });
+        }
+    }
+
+    for (hour, events) in hour_events {
+        db::file_list::set_events(&hour, &events).await?;
+    }
+    if move_files {
+        for file in files.iter() {
+            tokio::task::yield_now().await; // yield to other tasks
+            if let Err(e) = storage.del(file).await {
+                log::error!("[RENAME] delete file {} failed: {}", file, e);
+            }
+            sidecar::delete(file).await;
+        }
+    }
+    Ok(report)
+}
+
+/// points the aliases of the old stream name to the new one
+async fn update_aliases(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    new_name: &str,
+) -> Result<(), anyhow::Error> {
+    for mut alias in db::stream_aliases::list(org_id).await? {
+        if alias.stream_type != stream_type || !alias.streams.iter().any(|v| v == stream_name) {
+            continue;
+        }
+        for stream in alias.streams.iter_mut() {
+            if stream == stream_name {
+                *stream = new_name.to_string();
+            }
+        }
+        db::stream_aliases::set(org_id, &alias).await?;
+    }
+    Ok(())
+}
+
+/// replaces the stream in the FROM clause of a query
+fn replace_source(sql: &str, stream_name: &str, new_name: &str) -> String {
+    let re = Regex::new(&format!(
+        r##"(?i)(\bfrom[ ]+['"]?){}(['"]?(?:\s|$))"##,
+        regex::escape(stream_name)
+    ))
+    .unwrap();
+    re.replace_all(sql, format!("${{1}}{}${{2}}", new_name).as_str())
+        .to_string()
+}
+
+fn bad_request(e: anyhow::Error) -> HttpResponse {
+    HttpResponse::BadRequest().json(MetaHttpResponse::error(
+        http::StatusCode::BAD_REQUEST.into(),
+        Some(e.to_string()),
+    ))
+}
+
+fn internal_error(e: anyhow::Error) -> HttpResponse {
+    HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+        http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+        Some(e.to_string()),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_replace_source() {
+        assert_eq!(
+            replace_source("SELECT * FROM web WHERE code = 500", "web", "web_v2"),
+            "SELECT * FROM web_v2 WHERE code = 500"
+        );
+        assert_eq!(
+            replace_source("select count(*) from \"web\"", "web", "web_v2"),
+            "select count(*) from \"web_v2\""
+        );
+        assert_eq!(
+            replace_source("SELECT * FROM webhooks", "web", "web_v2"),
+            "SELECT * FROM webhooks"
+        );
+    }
+}
@@ -0,0 +1,285 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use datafusion::arrow::datatypes::Schema;
+use std::io::Error;
+use tracing::info_span;
+
+use crate::common::json;
+use crate::infra::config::STREAM_TEMPLATES;
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::template::{StreamTemplate, StreamTemplateList, StreamTemplateMatch};
+use crate::meta::StreamType;
+use crate::service::{db, functions, stream};
+
+pub async fn save_template(
+    org_id: &str,
+    name: &str,
+    mut template: StreamTemplate,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_templates:save");
+    let _guard = loc_span.enter();
+    template.name = name.to_string();
+    if let Err(e) = validate_template(&mut template) {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some(e.to_string()),
+        )));
+    }
+    if let Err(e) = db::stream_templates::set(org_id, &template).await {
+        return Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        );
+    }
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        http::StatusCode::OK.into(),
+        "Stream template saved".to_string(),
+    )))
+}
+
+pub async fn list_templates(org_id: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_templates:list");
+    let _guard = loc_span.enter();
+    let mut list = db::stream_templates::list(org_id).await.unwrap();
+    list.sort_by(|a, b| b.priority.cmp(&a.priority).then(a.name.cmp(&b.name)));
+    Ok(HttpResponse::Ok().json(StreamTemplateList { list }))
+}
+
+pub async fn delete_template(org_id: &str, name: &str) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_templates:delete");
+    let _guard = loc_span.enter();
+    if db::stream_templates::get(org_id, name)
+        .await
+        .unwrap()
+        .is_none()
+    {
+        return Ok(HttpResponse::NotFound().json(MetaHttpResponse::error(
+            http::StatusCode::NOT_FOUND.into(),
+            Some("stream template not found".to_string()),
+        )));
+    }
+    db::stream_templates::delete(org_id, name).await.unwrap();
+    Ok(HttpResponse::Ok().json(MetaHttpResponse::message(
+        http::StatusCode::OK.into(),
+        "Stream template deleted".to_string(),
+    )))
+}
+
+/// Previews the template a new stream would be created from
+pub async fn match_template(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:stream_templates:match");
+    let _guard = loc_span.enter();
+    Ok(HttpResponse::Ok().json(StreamTemplateMatch {
+        stream_name: stream_name.to_string(),
+        template: find_template(org_id, stream_type, stream_name),
+    }))
+}
+
+/// Applies the matching template to the schema of a new stream: the template
+/// settings are added to the schema metadata and its functions are registered
+/// for the stream.
+pub async fn apply_template(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    schema: Schema,
+) -> Schema {
+    if schema.metadata().contains_key("settings") {
+        return schema;
+    }
+    let template = match find_template(org_id, stream_type, stream_name) {
+        Some(template) => template,
+        None => return schema,
+    };
+    log::info!(
+        "stream {}/{}/{} uses template {}",
+        org_id,
+        stream_type,
+        stream_name,
+        template.name
+    );
+    for mut trans in template.functions.clone() {
+        let name = trans.name.clone();
+        trans.stream_name = stream_name.to_string();
+        if let Err(e) = db::udf::set(org_id, Some(stream_name.to_string()), &name, trans).await {
+            log::error!(
+                "stream {}/{} register function {} error: {}",
+                org_id,
+                stream_name,
+                name,
+                e
+            );
+        }
+    }
+    with_template_settings(schema, &template)
+}
+
+/// Adds the settings of the matching template to the schema of a new stream
+/// without registering its functions, for the schema of a stream not written
+/// yet.
+pub fn template_settings(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    schema: Schema,
+) -> Schema {
+    if schema.metadata().contains_key("settings") {
+        return schema;
+    }
+    match find_template(org_id, stream_type, stream_name) {
+        Some(template) => with_template_settings(schema, &template),
+        None => schema,
+    }
+}
+
+fn with_template_settings(schema: Schema, template: &StreamTemplate) -> Schema {
+    let mut metadata = schema.metadata().clone();
+    metadata.insert(
+        "settings".to_string(),
+        json::to_string(&template.settings).unwrap(),
+    );
+    schema.with_metadata(metadata)
+}
+
+fn find_template(
+    org_id: &str,
+    stream_type: StreamType,
+    stream_name: &str,
+) -> Option<StreamTemplate> {
+    let prefix = format!("{}/", org_id);
+    let templates = STREAM_TEMPLATES
+        .iter()
+        .filter(|item| item.key().starts_with(&prefix))
+        .map(|item| item.value().clone())
+        .collect::<Vec<_>>();
+    select_template(templates, stream_type, stream_name)
+}
+
+/// picks the matching template with the highest priority, ties are broken by
+/// the template name
+fn select_template(
+    templates: Vec<StreamTemplate>,
+    stream_type: StreamType,
+    stream_name: &str,
+) -> Option<StreamTemplate> {
+    templates
+        .into_iter()
+        .filter(|template| {
+            template.stream_type == stream_type
+                && template
+                    .patterns
+                    .iter()
+                    .any(|pattern| pattern_matches(pattern, stream_name))
+        })
+        .min_by(|a, b| b.priority.cmp(&a.priority).then(a.name.cmp(&b.name)))
+}
+
+/// glob match, `*` matches any characters and `?` a single one
+fn pattern_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let name = name.chars().collect::<Vec<_>>();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = backtrack {
+            // let the last `*` match one more character
+            p = star_p + 1;
+            n = star_n + 1;
+            backtrack = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+fn validate_template(template: &mut StreamTemplate) -> Result<(), anyhow::Error> {
+    if template.patterns.is_empty() || template.patterns.iter().any(|p| p.trim().is_empty()) {
+        return Err(anyhow::anyhow!("template requires non-empty patterns"));
+    }
+    stream::validate_stream_settings("", &Schema::empty(), &template.settings)?;
+    if !template.functions.is_empty() && template.stream_type != StreamType::Logs {
+        return Err(anyhow::anyhow!(
+            "functions are only supported for logs streams"
+        ));
+    }
+    for function in template.functions.iter_mut() {
+        if function.name.is_empty() || function.order == 0 {
+            return Err(anyhow::anyhow!("template functions require name and order"));
+        }
+        if !function.function.contains('(') || !function.function.contains(')') {
+            return Err(anyhow::anyhow!("function {} is not valid", function.name));
+        }
+        functions::extract_num_args(function);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn template(name: &str, pattern: &str, priority: i64) -> StreamTemplate {
+        StreamTemplate {
+            name: name.to_string(),
+            patterns: vec![pattern.to_string()],
+            priority,
+            stream_type: StreamType::Logs,
+            settings: Default::default(),
+            functions: vec![],
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("k8s-*", "k8s-default"));
+        assert!(pattern_matches("k8s-*", "k8s-"));
+        assert!(pattern_matches("*-prod", "payments-prod"));
+        assert!(pattern_matches("app-?", "app-1"));
+        assert!(pattern_matches("a*b*c", "axxbyyc"));
+        assert!(!pattern_matches("k8s-*", "k8s"));
+        assert!(!pattern_matches("app-?", "app-10"));
+        assert!(!pattern_matches("*-prod", "payments-dev"));
+    }
+
+    #[test]
+    fn test_select_template() {
+        let templates = vec![
+            template("k8s", "k8s-*", 1),
+            template("k8s_system", "k8s-kube-*", 10),
+            template("all", "*", 0),
+        ];
+        let selected = select_template(templates.clone(), StreamType::Logs, "k8s-kube-system");
+        assert_eq!(selected.unwrap().name, "k8s_system");
+        let selected = select_template(templates.clone(), StreamType::Logs, "k8s-payments");
+        assert_eq!(selected.unwrap().name, "k8s");
+        let selected = select_template(templates.clone(), StreamType::Logs, "nginx");
+        assert_eq!(selected.unwrap().name, "all");
+        assert!(select_template(templates, StreamType::Metrics, "nginx").is_none());
+    }
+}
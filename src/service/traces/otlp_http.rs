@@ -26,8 +26,11 @@ use tracing::info_span;
 
 use crate::infra::config::CONFIG;
 use crate::infra::file_lock;
+use crate::meta::stream::TimestampUnit;
 use crate::meta::traces::Event;
+use crate::service::db;
 use crate::service::schema::{add_stream_schema, stream_schema_exists};
+use crate::service::stream::get_stream_setting_timestamp_unit;
 use crate::{
     common::json,
     infra::cluster,
@@ -79,6 +82,11 @@ pub async fn traces_json(
     let mut min_ts = (Utc::now() + Duration::hours(CONFIG.limit.allowed_upto)).timestamp_micros();
     let mut data_buf: AHashMap<String, Vec<String>> = AHashMap::new();
     let mut traces_schema_map: AHashMap<String, Schema> = AHashMap::new();
+    let keep_nanos = get_stream_setting_timestamp_unit(
+        &db::schema::get(org_id, traces_stream_name, Some(StreamType::Traces))
+            .await
+            .unwrap(),
+    ) == Some(TimestampUnit::Nanosecond);
     let mut service_name: String = traces_stream_name.to_string();
     let reader = BufReader::new(body.as_ref());
     for line in reader.lines() {
@@ -225,7 +233,7 @@ pub async fn traces_json(
                                 attributes: span_att_map,
                                 service: service_att_map.clone(),
                                 flags: 1, //TODO add appropriate value
-                                _timestamp: timestamp,
+                                _timestamp: if keep_nanos { start_time } else { timestamp },
                                 events: serde_json::to_string(&events).unwrap(),
                             };
                             if timestamp < min_ts.try_into().unwrap() {
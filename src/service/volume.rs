@@ -0,0 +1,207 @@
+// Copyright 2022 Zinc Labs Inc. and Contributors
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use actix_web::{http, HttpResponse};
+use ahash::AHashMap;
+use chrono::{Duration, Utc};
+use std::collections::BTreeMap;
+use std::io::Error;
+use tracing::info_span;
+
+use crate::common::json;
+use crate::infra::cache;
+use crate::infra::config::CONFIG;
+use crate::meta::http::HttpResponse as MetaHttpResponse;
+use crate::meta::prom::Metric;
+use crate::meta::stream::{PartitionTimeLevel, StreamVolume, StreamVolumeHistory};
+use crate::meta::StreamType;
+use crate::service::db;
+use crate::service::logs::derived;
+
+const RECORDS_METRIC: &str = "zo_stream_ingested_records";
+const BYTES_METRIC: &str = "zo_stream_ingested_bytes";
+
+pub async fn get_volume_history(
+    org_id: &str,
+    stream_name: &str,
+    stream_type: StreamType,
+    start_time: i64,
+    end_time: i64,
+    interval: &str,
+) -> Result<HttpResponse, Error> {
+    let loc_span = info_span!("service:volume:get");
+    let _guard = loc_span.enter();
+    let level = match interval {
+        "hour" => PartitionTimeLevel::Hour,
+        "day" => PartitionTimeLevel::Day,
+        _ => {
+            return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+                http::StatusCode::BAD_REQUEST.into(),
+                Some("interval must be hour or day".to_owned()),
+            )))
+        }
+    };
+    if end_time <= start_time {
+        return Ok(HttpResponse::BadRequest().json(MetaHttpResponse::error(
+            http::StatusCode::BAD_REQUEST.into(),
+            Some("invalid time range".to_owned()),
+        )));
+    }
+    match db::volume::list(org_id, stream_name, stream_type).await {
+        Ok(hours) => Ok(HttpResponse::Ok().json(StreamVolumeHistory {
+            stream_name: stream_name.to_string(),
+            stream_type,
+            interval: interval.to_string(),
+            list: group_volumes(hours, level, start_time, end_time),
+        })),
+        Err(e) => Ok(
+            HttpResponse::InternalServerError().json(MetaHttpResponse::error(
+                http::StatusCode::INTERNAL_SERVER_ERROR.into(),
+                Some(e.to_string()),
+            )),
+        ),
+    }
+}
+
+/// sums the hourly volumes within the time range by the level
+fn group_volumes(
+    hours: Vec<StreamVolume>,
+    level: PartitionTimeLevel,
+    start_time: i64,
+    end_time: i64,
+) -> Vec<StreamVolume> {
+    let mut groups: BTreeMap<i64, StreamVolume> = BTreeMap::new();
+    for hour in hours {
+        if hour.timestamp < start_time || hour.timestamp >= end_time {
+            continue;
+        }
+        let timestamp = level.truncate(hour.timestamp);
+        groups
+            .entry(timestamp)
+            .or_insert_with(|| StreamVolume {
+                timestamp,
+                ..Default::default()
+            })
+            .add(&hour);
+    }
+    groups.into_values().collect()
+}
+
+/// Writes the volumes ingested since the last flush into the metadata store,
+/// and the totals since the node started into the metrics streams when enabled
+pub async fn flush() -> Result<(), anyhow::Error> {
+    let volumes = cache::stats::take_stream_volumes();
+    if volumes.is_empty() {
+        return Ok(());
+    }
+
+    let mut streams: AHashMap<String, Vec<StreamVolume>> = AHashMap::new();
+    for (stream_key, volume) in volumes {
+        streams.entry(stream_key).or_default().push(volume);
+    }
+    let mut totals = Vec::with_capacity(streams.len());
+    let mut ret = Ok(());
+    for (stream_key, items) in streams {
+        if let Err(e) = db::volume::add(&stream_key, &items).await {
+            // keep them for the next flush
+            for item in items.iter() {
+                cache::stats::add_stream_volume(&stream_key, item);
+            }
+            ret = Err(e);
+            continue;
+        }
+        let total = cache::stats::add_stream_volume_total(&stream_key, &items);
+        totals.push((stream_key, total));
+    }
+
+    if !CONFIG.common.volume_history_metrics_enabled || totals.is_empty() {
+        return ret;
+    }
+    // values are the totals since the node started, like prometheus counters
+    let timestamp = Utc::now().timestamp_micros();
+    let mut metric_data_map: AHashMap<String, AHashMap<String, Vec<String>>> = AHashMap::new();
+    for (stream_key, total) in totals {
+        // eg: default/logs/olympics
+        let columns = stream_key.split('/').collect::<Vec<&str>>();
+        let org_buf = metric_data_map.entry(columns[0].to_string()).or_default();
+        for (name, value) in [
+            (RECORDS_METRIC, total.records as f64),
+            (BYTES_METRIC, total.original_size as f64),
+        ] {
+            let mut collection = AHashMap::new();
+            collection.insert("__name__".to_string(), name.to_string());
+            collection.insert("stream_type".to_string(), columns[1].to_string());
+            collection.insert("stream".to_string(), columns[2].to_string());
+            let metric = Metric::new(
+                name.to_string(),
+                value,
+                collection,
+                timestamp,
+                "Counter".to_string(),
+            );
+            org_buf
+                .entry(name.to_string())
+                .or_default()
+                .push(json::to_string(&metric).unwrap());
+        }
+    }
+    derived::write_metrics(timestamp, metric_data_map).await?;
+    ret
+}
+
+/// Deletes the volume history out of retention
+pub async fn prune() -> Result<(), anyhow::Error> {
+    if CONFIG.compact.volume_history_retention_days <= 0 {
+        return Ok(()); // keep forever
+    }
+    let cutoff = Utc::now().timestamp_micros()
+        - Duration::days(CONFIG.compact.volume_history_retention_days)
+            .num_microseconds()
+            .unwrap();
+    db::volume::delete_before(cutoff).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_volumes() {
+        let hour = Duration::hours(1).num_microseconds().unwrap();
+        // 2022-10-03 00:00:00
+        let day_start = 1664755200000000;
+        let hours = (0..30)
+            .map(|i| StreamVolume {
+                timestamp: day_start + i * hour,
+                records: 10,
+                files: 1,
+                original_size: 100,
+                compressed_size: 10,
+            })
+            .collect::<Vec<_>>();
+        let days = group_volumes(hours.clone(), PartitionTimeLevel::Day, 0, i64::MAX);
+        assert_eq!(days.len(), 2);
+        assert_eq!(days[0].timestamp, day_start);
+        assert_eq!(days[0].records, 240);
+        assert_eq!(days[1].files, 6);
+        let hours = group_volumes(
+            hours,
+            PartitionTimeLevel::Hour,
+            day_start + hour,
+            day_start + 3 * hour,
+        );
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].original_size, 100);
+    }
+}